@@ -1,7 +1,11 @@
 //! Structs defining Amazon data sets.
+use std::collections::HashMap;
+
 use parquet_derive::ParquetRecordWriter;
 use serde::{Deserialize, Serialize};
 
+use crate::cleaning::isbns::clean_isbn_chars;
+
 /// A rating as described in a source CSV file.
 #[derive(Serialize, Deserialize)]
 pub struct SourceRating {
@@ -50,3 +54,308 @@ pub struct ReviewRow {
     pub summary: String,
     pub text: String,
 }
+
+/// A rating from a 2023 rating-only CSV file.
+///
+/// Unlike the older files, these have a header row and millisecond timestamps.
+#[derive(Serialize, Deserialize)]
+pub struct SourceRating2023 {
+    pub user_id: String,
+    pub parent_asin: String,
+    pub rating: f32,
+    pub timestamp: i64,
+}
+
+/// A review as it is described in a 2023 JSONL file.
+#[derive(Serialize, Deserialize)]
+pub struct SourceReview2023 {
+    pub user_id: String,
+    pub asin: String,
+    pub parent_asin: String,
+    pub rating: f32,
+    /// The review timestamp, in milliseconds.
+    pub timestamp: i64,
+    pub title: Option<String>,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub helpful_vote: i32,
+    #[serde(default)]
+    pub verified_purchase: bool,
+}
+
+/// A product from a 2014 or 2018 metadata JSON file.
+#[derive(Serialize, Deserialize)]
+pub struct SourceMeta {
+    pub asin: String,
+    pub title: Option<String>,
+    /// The brand, which for books is usually the author.
+    pub brand: Option<String>,
+    pub main_cat: Option<String>,
+    #[serde(default)]
+    pub category: Vec<String>,
+    #[serde(default)]
+    pub details: HashMap<String, serde_json::Value>,
+}
+
+/// The author of a product in a 2023 metadata file.
+#[derive(Serialize, Deserialize)]
+pub struct SourceAuthor2023 {
+    pub name: Option<String>,
+}
+
+/// A product from a 2023 metadata JSONL file.
+#[derive(Serialize, Deserialize)]
+pub struct SourceMeta2023 {
+    pub parent_asin: String,
+    pub title: Option<String>,
+    pub author: Option<SourceAuthor2023>,
+    /// The store, which for books is usually the list of contributors.
+    pub store: Option<String>,
+    pub main_category: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub details: HashMap<String, serde_json::Value>,
+}
+
+/// Product metadata common to all metadata formats.
+#[derive(Debug, PartialEq)]
+pub struct ProductMeta {
+    pub asin: String,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub format: Option<String>,
+    pub main_cat: Option<String>,
+    pub categories: Vec<String>,
+    /// The product's ISBNs.
+    ///
+    /// This includes the ASIN itself when it is an ISBN-10, as it is for most
+    /// printed books, followed by any ISBNs listed in the details.
+    pub isbns: Vec<String>,
+}
+
+/// Structure for scanned 2023 reviews.
+///
+/// This data structure is serialized to `reviews.parquet` in the `az2023`
+/// directory.  The ratings file uses [RatingRow] with the parent ASIN, so all
+/// formats of a product are rated as one item; the reviews keep the ASIN of the
+/// specific format as well.
+#[derive(ParquetRecordWriter, Serialize, Deserialize)]
+pub struct Review2023Row {
+    pub user_id: i32,
+    pub asin: String,
+    pub parent_asin: String,
+    pub rating: f32,
+    pub timestamp: i64,
+    pub helpful_votes: i32,
+    /// Whether the review is a verified purchase (0 or 1).
+    pub verified: u8,
+    pub summary: String,
+    pub text: String,
+}
+
+/// Structure for ASIN-ISBN links.
+///
+/// This data structure is serialized to `isbn-links.parquet` in the Amazon directories.
+#[derive(ParquetRecordWriter, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ISBNLinkRow {
+    pub asin: String,
+    pub isbn: String,
+}
+
+/// Structure for product information.
+///
+/// This data structure is serialized to `item-info.parquet` in the Amazon directories.
+#[derive(ParquetRecordWriter, Serialize, Deserialize)]
+pub struct ItemInfoRow {
+    pub asin: String,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub format: Option<String>,
+    pub main_cat: Option<String>,
+}
+
+/// Structure for product categories.
+///
+/// This data structure is serialized to `item-categories.parquet` in the Amazon
+/// directories.  Categories are paths from the top of Amazon's category tree, so
+/// `depth` is the position of the category in the path (starting from 0).
+#[derive(ParquetRecordWriter, Serialize, Deserialize)]
+pub struct CategoryRow {
+    pub asin: String,
+    pub depth: u32,
+    pub category: String,
+}
+
+/// Book formats that appear as keys in the product details.
+static FORMATS: &[&str] = &[
+    "Hardcover",
+    "Paperback",
+    "Mass Market Paperback",
+    "Board book",
+    "Library Binding",
+    "Spiral-bound",
+    "Leather Bound",
+    "Audio CD",
+    "Audible Audiobook",
+    "Kindle Edition",
+];
+
+impl From<SourceMeta> for ProductMeta {
+    fn from(meta: SourceMeta) -> ProductMeta {
+        ProductMeta {
+            isbns: product_isbns(&meta.asin, &meta.details),
+            format: product_format(&meta.details),
+            author: meta.brand.as_deref().and_then(clean_author),
+            title: clean_text(meta.title),
+            main_cat: clean_text(meta.main_cat),
+            categories: meta.category,
+            asin: meta.asin,
+        }
+    }
+}
+
+impl From<SourceMeta2023> for ProductMeta {
+    fn from(meta: SourceMeta2023) -> ProductMeta {
+        let author = meta
+            .author
+            .and_then(|a| a.name)
+            .or(meta.store)
+            .as_deref()
+            .and_then(clean_author);
+        ProductMeta {
+            isbns: product_isbns(&meta.parent_asin, &meta.details),
+            format: product_format(&meta.details),
+            author,
+            title: clean_text(meta.title),
+            main_cat: clean_text(meta.main_category),
+            categories: meta.categories,
+            asin: meta.parent_asin,
+        }
+    }
+}
+
+/// Look up a product detail, ignoring case, trailing colons, and the difference
+/// between hyphens and spaces (2018 uses `ISBN-10:`, 2023 uses `ISBN 10`).
+fn detail<'a>(details: &'a HashMap<String, serde_json::Value>, name: &str) -> Option<&'a str> {
+    details.iter().find_map(|(k, v)| {
+        let k = k.trim().trim_end_matches(':').trim().replace('-', " ");
+        match v {
+            serde_json::Value::String(s) if k.eq_ignore_ascii_case(name) => Some(s.as_str()),
+            _ => None,
+        }
+    })
+}
+
+/// Get the ISBNs of a product.
+fn product_isbns(asin: &str, details: &HashMap<String, serde_json::Value>) -> Vec<String> {
+    let mut isbns = Vec::new();
+    if is_isbn10(asin) {
+        isbns.push(asin.to_string());
+    }
+    for key in ["ISBN 10", "ISBN 13"] {
+        if let Some(s) = detail(details, key) {
+            let isbn = clean_isbn_chars(s);
+            if (isbn.len() == 10 || isbn.len() == 13) && !isbns.contains(&isbn) {
+                isbns.push(isbn);
+            }
+        }
+    }
+    isbns
+}
+
+/// Get the format of a product from its details.
+///
+/// Kindle editions do not always have a format key, but are the only products
+/// with a file size.
+fn product_format(details: &HashMap<String, serde_json::Value>) -> Option<String> {
+    for fmt in FORMATS {
+        if details
+            .keys()
+            .any(|k| k.trim().trim_end_matches(':').trim() == *fmt)
+        {
+            return Some(fmt.to_string());
+        }
+    }
+    if detail(details, "File Size").is_some() {
+        Some("Kindle Edition".to_string())
+    } else {
+        None
+    }
+}
+
+/// Clean an author name from a brand or store string.
+///
+/// These have forms like “Visit Amazon's J.R.R. Tolkien Page” (2018) or
+/// “J.R.R. Tolkien (Author), Alan Lee (Illustrator)” (2023); we keep the
+/// first contributor's name.
+fn clean_author(brand: &str) -> Option<String> {
+    let name = brand.trim();
+    let name = name.strip_prefix("Visit Amazon's ").unwrap_or(name);
+    let name = name.strip_suffix(" Page").unwrap_or(name);
+    let name = match name.split_once(" (") {
+        Some((first, _)) => first,
+        None => name,
+    };
+    clean_text(Some(name.to_string()))
+}
+
+/// Trim a text field, treating empty strings as missing.
+fn clean_text(text: Option<String>) -> Option<String> {
+    text.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Check whether an ASIN is an ISBN-10.
+fn is_isbn10(asin: &str) -> bool {
+    asin.len() == 10
+        && asin
+            .bytes()
+            .enumerate()
+            .all(|(i, b)| b.is_ascii_digit() || (i == 9 && b == b'X'))
+}
+
+#[test]
+fn test_meta_isbns() {
+    let meta: SourceMeta2023 = serde_json::from_str(
+        r#"{"parent_asin": "0345339703", "details": {"ISBN 10": "0345339703", "ISBN 13": "978-0345339706", "Pages": 400}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        ProductMeta::from(meta).isbns,
+        vec!["0345339703", "9780345339706"]
+    );
+
+    let meta: SourceMeta2023 = serde_json::from_str(
+        r#"{"parent_asin": "B00KXTGMZ4", "details": {"ISBN 13": "978-0547928227"}}"#,
+    )
+    .unwrap();
+    assert_eq!(ProductMeta::from(meta).isbns, vec!["9780547928227"]);
+
+    let meta: SourceMeta2023 = serde_json::from_str(r#"{"parent_asin": "B00KXTGMZ4"}"#).unwrap();
+    assert!(ProductMeta::from(meta).isbns.is_empty());
+}
+
+#[test]
+fn test_meta_2018() {
+    let meta: SourceMeta = serde_json::from_str(
+        r#"{"asin": "B000FC1PJI", "title": " The Hobbit ", "brand": "Visit Amazon's J.R.R. Tolkien Page",
+            "main_cat": "Books", "category": ["Books", "Literature & Fiction"],
+            "details": {"File Size:": "2345 KB", "ISBN-13:": "978-0547928227"}}"#,
+    )
+    .unwrap();
+    let meta = ProductMeta::from(meta);
+    assert_eq!(meta.title.as_deref(), Some("The Hobbit"));
+    assert_eq!(meta.author.as_deref(), Some("J.R.R. Tolkien"));
+    assert_eq!(meta.format.as_deref(), Some("Kindle Edition"));
+    assert_eq!(meta.categories.len(), 2);
+    assert_eq!(meta.isbns, vec!["9780547928227"]);
+}
+
+#[test]
+fn test_clean_author() {
+    assert_eq!(
+        clean_author("J.R.R. Tolkien (Author), Alan Lee (Illustrator)").as_deref(),
+        Some("J.R.R. Tolkien")
+    );
+    assert_eq!(clean_author("  ").as_deref(), None);
+}
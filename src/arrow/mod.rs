@@ -3,5 +3,5 @@ pub mod reader;
 pub mod writer;
 
 pub use dfext::nonnull_schema;
-pub use reader::{scan_df_parquet, scan_parquet_file};
+pub use reader::{scan_df_batches, scan_df_parquet, scan_parquet_file};
 pub use writer::{save_df_parquet, TableWriter};
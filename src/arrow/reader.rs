@@ -23,6 +23,31 @@ pub fn scan_df_parquet<P: AsRef<Path>>(file: P) -> Result<LazyFrame> {
     Ok(df)
 }
 
+/// Read a Parquet file as a sequence of data frames of at most `batch_size` rows.
+///
+/// The file is read once, one row group at a time, so memory use is bounded by
+/// the row group and batch sizes rather than the file size.
+pub fn scan_df_batches<P: AsRef<Path>>(
+    file: P,
+    batch_size: usize,
+) -> Result<impl Iterator<Item = Result<DataFrame>>> {
+    let file = file.as_ref();
+    debug!("scanning {} in batches of {}", file.display(), batch_size);
+    let mut reader = File::open(file)?;
+    let meta = polars_parquet::read::read_metadata(&mut reader)?;
+    let schema = polars_parquet::read::infer_schema(&meta)?;
+    let fields = schema.fields.clone();
+    let reader = polars_parquet::read::FileReader::new(
+        reader,
+        meta.row_groups,
+        schema,
+        Some(batch_size),
+        None,
+        None,
+    );
+    Ok(reader.map(move |chunk| Ok(DataFrame::try_from((chunk?, fields.as_slice()))?)))
+}
+
 /// Iterator over deserialized records from a Parquet file.
 pub struct RecordIter<R>
 where
@@ -2,3 +2,4 @@
 pub mod isbns;
 pub mod names;
 pub mod strings;
+pub mod titles;
@@ -10,20 +10,34 @@
 //!   variants.
 //! - If the name has a year, we emit each variant both with and without the
 //!   year.
+//! - If the given name ends with particles (“Beauvoir, Simone de”), we also
+//!   emit the variant with the particles on the surname (“de Beauvoir,
+//!   Simone”).
+//! - If the name has a generational suffix (“Jr.”, “III”), we emit each variant
+//!   both with and without the suffix.
 //! - Leading and trailing junk is cleaned
+//! - If the name is in a non-Latin script, we also emit romanized variants, and
+//!   if it has accents, we also emit accent-folded variants (see [`romanize`]
+//!   and [`fold_accents`]).
 //!
 //! This maximizes our ability to match records across sources recording names
 //! in different formats.
 //!
 //! [`name_variants`] is the primary entry point for using this module.  The
 //! [`clean_name`] function provides cleanup utilities without parsing, for
-//! emitting names from book records.
+//! emitting names from book records, and [`name_key`] reduces a name to a
+//! single key for comparing names across records.  [`match_key`] is a looser
+//! key that also ignores script, accents, punctuation, and name order, and
+//! [`initials_key`] matches names with abbreviated given names.  Corporate
+//! names (MARC 110 and 710) should use [`corporate_name_variants`], which does
+//! not treat commas as separating name parts.
 
 use anyhow::Result;
 
 use super::strings::norm_unicode;
 
 mod parse;
+mod script;
 mod types;
 
 #[cfg(test)]
@@ -31,10 +45,12 @@ mod test_cleaning;
 #[cfg(test)]
 mod test_variants;
 
+use types::NameEntry;
 pub use types::NameError;
-use types::NameFmt;
 
-pub use parse::parse_name_entry;
+use parse::surname_given;
+pub use parse::{parse_corporate_entry, parse_name_entry};
+pub use script::{detect_script, fold_accents, romanize, Script};
 
 /// Pre-clean a string without copying.
 ///
@@ -110,15 +126,31 @@ pub fn clean_name<'a>(name: &'a str) -> String {
 /// See the [module documentation][self] for details on this parsing process.
 pub fn name_variants(name: &str) -> Result<Vec<String>, NameError> {
     let parse = parse_name_entry(name)?;
-    let mut variants = Vec::new();
-    match parse.name.simplify() {
-        NameFmt::Empty => (),
-        NameFmt::Single(n) => variants.push(n),
-        NameFmt::TwoPart(last, first) => {
-            variants.push(format!("{} {}", first, last));
-            variants.push(format!("{}, {}", last, first));
+    Ok(entry_variants(parse))
+}
+
+/// Extract all variants from a corporate name (e.g. MARC 110).
+///
+/// Corporate names are not inverted, but are otherwise expanded like personal
+/// names.
+pub fn corporate_name_variants(name: &str) -> Result<Vec<String>, NameError> {
+    let parse = parse_corporate_entry(name)?;
+    Ok(entry_variants(parse))
+}
+
+fn entry_variants(parse: NameEntry) -> Vec<String> {
+    let mut variants = parse.name.simplify().forms();
+
+    // create romanized and accent-folded versions
+    for i in 0..variants.len() {
+        let roman = romanize(&variants[i]);
+        let base = roman.as_ref().unwrap_or(&variants[i]);
+        let folded = fold_accents(base);
+        if let Some(r) = roman {
+            variants.push(r);
         }
-    };
+        variants.push(folded);
+    }
 
     // create a version with the year
     if let Some(y) = parse.year {
@@ -131,5 +163,71 @@ pub fn name_variants(name: &str) -> Result<Vec<String>, NameError> {
     variants.sort();
     variants.dedup();
 
-    Ok(variants)
+    variants
+}
+
+/// Compute a single matching key for a name.
+///
+/// The key is the lowercased “First Last” form of the name, without any year,
+/// so “Austen, Jane, 1775-1817” and “Jane Austen” have the same key.  Returns
+/// `None` for empty or unparseable names.
+pub fn name_key(name: &str) -> Option<String> {
+    let parse = parse_name_entry(name).ok()?;
+    let key = parse.name.simplify().natural()?;
+    let key = clean_name(&key).to_lowercase();
+    if key.is_empty() {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+/// Compute a script- and order-insensitive matching key for a name.
+///
+/// The name is romanized and accent-folded, and the key is its lowercased words
+/// without punctuation or year, in sorted order, so “Толстой, Лев” and “Lev
+/// Tolstoy” have the same key.  Returns `None` for empty or unparseable names.
+pub fn match_key(name: &str) -> Option<String> {
+    let parse = parse_name_entry(name).ok()?;
+    let name = parse.name.simplify().natural()?;
+    let name = fold_accents(&romanize(&name).unwrap_or(name));
+    let mut words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    words.sort();
+    Some(words.join(" "))
+}
+
+/// Compute a surname-and-initials matching key for a name.
+///
+/// The key is the romanized, accent-folded, lowercased surname followed by the
+/// initials of the given names, so “Tolkien, J. R. R.”, “J.R.R. Tolkien”, and
+/// “Tolkien, John Ronald Reuel” all have the key `tolkien, jrr`.  Particles and
+/// generational suffixes are ignored.  Returns `None` for names without both a
+/// surname and a given name.
+pub fn initials_key(name: &str) -> Option<String> {
+    let parse = parse_name_entry(name).ok()?;
+    let (last, given) = surname_given(&parse.name.simplify())?;
+    let last = fold_accents(&romanize(&last).unwrap_or(last));
+    let given = fold_accents(&romanize(&given).unwrap_or(given));
+    let last: Vec<String> = last
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    let initials: String = given
+        .split(|c: char| !c.is_alphanumeric())
+        .filter_map(|w| w.chars().next())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    if last.is_empty() || initials.is_empty() {
+        None
+    } else {
+        Some(format!("{}, {}", last.join(" "), initials))
+    }
 }
@@ -21,13 +21,44 @@ peg::parser! {
         = trailing_junk() { None }
         / y:year_tag() { Some(y) }
 
+        rule letter() = quiet!{['a'..='z' | 'A'..='Z']}
+
+        // generational suffixes; longer alternatives must come first
+        rule gen_suffix() -> String
+        = s:$("Junior" / "Senior" / "Jr" / "Sr" / "III" / "II" / "IV" / "2nd" / "3rd") "."? !letter() {
+            s.to_owned()
+        }
+
+        // name particles; longer alternatives must come first
+        rule particle()
+        = ("della" / "delle" / "dello" / "del" / "den" / "der" / "des" / "dos" / "das" / "de"
+          / "da" / "di" / "du" / "van" / "von" / "ten" / "ter" / "la" / "le") !letter()
+
+        rule particles() = particle() (space()+ particle())*
+
+        /// Split a generational suffix from a trailing name part.
+        pub rule suffix_part() -> String
+        = space()* s:gen_suffix() space()* ![_] { s }
+
+        /// Split a generational suffix from the end of a name part.
+        pub rule suffixed_part() -> (String, String)
+        = n:$((!(space()* ","? space()* gen_suffix() space()* ![_]) [_])+) space()* ","? space()* s:gen_suffix() space()* ![_] {
+            (n.trim().to_owned(), s)
+        }
+
+        /// Split trailing particles from a given name, as in “Simone de”.
+        pub rule given_particle() -> (String, String)
+        = g:$((!(space()+ particles() space()* ![_]) [_])+) space()+ p:$(particles()) space()* ![_] {
+            (g.trim().to_owned(), p.to_owned())
+        }
+
+        /// Split leading particles from a surname, as in “de Beauvoir”.
+        pub rule leading_particle() -> (String, String)
+        = p:$(particles()) space()+ l:$([_]+) { (p.to_owned(), l.trim().to_owned()) }
+
         rule cs_name() -> NameFmt
         = last:$([^',']*) "," space()* rest:$(([_] !ending())* [^',']?) {
-            if rest.trim().is_empty() {
-                NameFmt::Single(last.trim().to_owned())
-            } else {
-                NameFmt::TwoPart(last.trim().to_owned(), rest.trim().to_owned())
-            }
+            personal_name(last.trim(), rest.trim())
         }
 
         rule single_name() -> NameFmt
@@ -39,6 +70,42 @@ peg::parser! {
         pub rule name_entry() -> NameEntry
         = year:year_tag() { NameEntry { name:NameFmt::Empty, year: Some(year) } }
         / name:name() year:ending()? { NameEntry { name, year: year.flatten() } }
+
+        pub rule corporate_entry() -> NameEntry
+        = space()* name:$((!trailing_junk() [_])*) trailing_junk()? {
+            NameEntry { name: NameFmt::Corporate(name.trim().to_owned()), year: None }
+        }
+    }
+}
+
+/// Interpret the parts of a comma-separated personal name.
+fn personal_name(last: &str, rest: &str) -> NameFmt {
+    if rest.is_empty() {
+        return NameFmt::Single(last.to_owned());
+    }
+    // “King, Martin Luther, Jr.” may be a first-last name with a suffix
+    if let Ok(s) = name_parser::suffix_part(rest) {
+        return NameFmt::Suffixed(Box::new(NameFmt::Single(last.to_owned())), s);
+    }
+
+    // the suffix can follow the given name or the surname
+    let (first, last, suffix) = if let Ok((first, s)) = name_parser::suffixed_part(rest) {
+        (first, last.to_owned(), Some(s))
+    } else if let Ok((last, s)) = name_parser::suffixed_part(last) {
+        (rest.to_owned(), last, Some(s))
+    } else {
+        (rest.to_owned(), last.to_owned(), None)
+    };
+
+    let name = if let Ok((given, particle)) = name_parser::given_particle(&first) {
+        NameFmt::Particle(last, given, particle)
+    } else {
+        NameFmt::TwoPart(last, first)
+    };
+
+    match suffix {
+        Some(s) => NameFmt::Suffixed(Box::new(name), s),
+        None => name,
     }
 }
 
@@ -46,3 +113,36 @@ pub fn parse_name_entry(name: &str) -> Result<NameEntry, NameError> {
     let res = name_parser::name_entry(name)?;
     Ok(res)
 }
+
+/// Parse a corporate name entry (e.g. MARC 110 or 710).
+///
+/// Corporate names are not split into parts or inverted.
+pub fn parse_corporate_entry(name: &str) -> Result<NameEntry, NameError> {
+    let res = name_parser::corporate_entry(name)?;
+    Ok(res)
+}
+
+/// Split the surname and given names of a personal name, ignoring particles
+/// and generational suffixes.  Returns `None` for names without both parts.
+pub fn surname_given(name: &NameFmt) -> Option<(String, String)> {
+    match name {
+        NameFmt::TwoPart(l, f) => {
+            let last = match name_parser::leading_particle(l) {
+                Ok((_, l)) => l,
+                Err(_) => l.clone(),
+            };
+            Some((last, f.clone()))
+        }
+        NameFmt::Particle(l, f, _) => Some((l.clone(), f.clone())),
+        NameFmt::Suffixed(n, _) => surname_given(n),
+        NameFmt::Single(n) => {
+            let (given, last) = n.trim().rsplit_once(' ')?;
+            let given = match name_parser::given_particle(given) {
+                Ok((g, _)) => g,
+                Err(_) => given.to_owned(),
+            };
+            Some((last.to_owned(), given))
+        }
+        NameFmt::Corporate(_) | NameFmt::Empty => None,
+    }
+}
@@ -1,6 +1,12 @@
 //! Test name variant expansion
 
+use super::clean_name;
+use super::corporate_name_variants;
+use super::initials_key;
+use super::match_key;
+use super::name_key;
 use super::name_variants;
+use super::parse_corporate_entry;
 use super::parse_name_entry;
 use super::types::*;
 
@@ -12,8 +18,9 @@ fn check_name_decode(name: &str, exp_variants: &[&str]) {
     }
     assert_eq!(dec_variants.len(), exp_variants.len());
     for n in exp_variants {
+        // variants are in decomposed (NFD) form
         assert!(
-            dec_variants.contains(&(*n).to_owned()),
+            dec_variants.contains(&clean_name(n)),
             "expected variant {} not found",
             n
         );
@@ -113,3 +120,208 @@ fn test_leading_comma() {
 fn test_year_only() {
     check_name_decode("1941-", &[]);
 }
+
+#[test]
+fn test_key_first_last() {
+    assert_eq!(name_key("Jane Austen").as_deref(), Some("jane austen"));
+}
+
+#[test]
+fn test_key_last_first_year() {
+    assert_eq!(
+        name_key("Austen, Jane, 1775-1817").as_deref(),
+        Some("jane austen")
+    );
+}
+
+#[test]
+fn test_key_empty() {
+    assert_eq!(name_key(""), None);
+}
+
+#[test]
+fn test_accent_variants() {
+    check_name_decode(
+        "Brontë, Charlotte",
+        &[
+            "Brontë, Charlotte",
+            "Charlotte Brontë",
+            "Bronte, Charlotte",
+            "Charlotte Bronte",
+        ],
+    );
+}
+
+#[test]
+fn test_cyrillic_variants() {
+    check_name_decode(
+        "Толстой, Лев, 1828-1910",
+        &[
+            "Толстой, Лев",
+            "Лев Толстой",
+            "Tolstoy, Lev",
+            "Lev Tolstoy",
+            "Толстой, Лев, 1828-1910",
+            "Лев Толстой, 1828-1910",
+            "Tolstoy, Lev, 1828-1910",
+            "Lev Tolstoy, 1828-1910",
+        ],
+    );
+}
+
+#[test]
+fn test_match_key() {
+    assert_eq!(match_key("Austen, Jane").as_deref(), Some("austen jane"));
+    assert_eq!(
+        match_key("Austen, Jane, 1775-1817").as_deref(),
+        Some("austen jane")
+    );
+    assert_eq!(match_key("Толстой, Лев"), match_key("Lev Tolstoy"));
+    assert_eq!(
+        match_key("García Márquez, Gabriel"),
+        match_key("Gabriel Garcia-Marquez")
+    );
+    assert_eq!(match_key(""), None);
+}
+
+#[test]
+fn test_particle_variants() {
+    let parse = parse_name_entry("Beauvoir, Simone de").expect("parse error");
+    assert_eq!(
+        parse.name,
+        NameFmt::Particle("Beauvoir".into(), "Simone".into(), "de".into())
+    );
+    check_name_decode(
+        "Beauvoir, Simone de",
+        &[
+            "Beauvoir, Simone de",
+            "Simone de Beauvoir",
+            "de Beauvoir, Simone",
+        ],
+    );
+}
+
+#[test]
+fn test_multi_particle_variants() {
+    check_name_decode(
+        "Fontaine, Jean de la",
+        &[
+            "Fontaine, Jean de la",
+            "Jean de la Fontaine",
+            "de la Fontaine, Jean",
+        ],
+    );
+}
+
+#[test]
+fn test_capital_not_particle() {
+    check_name_decode("Pen, Marine Le", &["Pen, Marine Le", "Marine Le Pen"]);
+}
+
+#[test]
+fn test_suffix_variants() {
+    let parse = parse_name_entry("King, Martin Luther, Jr.").expect("parse error");
+    assert_eq!(
+        parse.name,
+        NameFmt::Suffixed(
+            Box::new(NameFmt::TwoPart("King".into(), "Martin Luther".into())),
+            "Jr".into()
+        )
+    );
+    check_name_decode(
+        "King, Martin Luther, Jr.",
+        &[
+            "King, Martin Luther",
+            "Martin Luther King",
+            "King, Martin Luther, Jr",
+            "Martin Luther King Jr",
+            "Martin Luther King, Jr",
+        ],
+    );
+}
+
+#[test]
+fn test_suffix_year_variants() {
+    let vs = name_variants("King, Martin Luther, Jr., 1929-1968").expect("parse error");
+    assert_eq!(vs.len(), 10);
+    assert!(vs.contains(&"King, Martin Luther, Jr, 1929-1968".to_string()));
+    assert!(vs.contains(&"Martin Luther King, 1929-1968".to_string()));
+}
+
+#[test]
+fn test_surname_suffix() {
+    check_name_decode(
+        "Jones Jr., Albert",
+        &[
+            "Jones, Albert",
+            "Albert Jones",
+            "Jones, Albert, Jr",
+            "Albert Jones Jr",
+            "Albert Jones, Jr",
+        ],
+    );
+}
+
+#[test]
+fn test_first_last_suffix() {
+    check_name_decode(
+        "Martin Luther King, Jr.",
+        &[
+            "Martin Luther King",
+            "Martin Luther King Jr",
+            "Martin Luther King, Jr",
+        ],
+    );
+}
+
+#[test]
+fn test_numeric_suffix() {
+    check_name_decode(
+        "Stevenson, Adlai E., III",
+        &[
+            "Stevenson, Adlai E",
+            "Adlai E Stevenson",
+            "Stevenson, Adlai E, III",
+            "Adlai E Stevenson III",
+            "Adlai E Stevenson, III",
+        ],
+    );
+}
+
+#[test]
+fn test_corporate_variants() {
+    let parse = parse_corporate_entry("Penguin Books, Ltd.").expect("parse error");
+    assert_eq!(parse.name, NameFmt::Corporate("Penguin Books, Ltd".into()));
+    assert_eq!(
+        corporate_name_variants("Penguin Books, Ltd.").expect("parse error"),
+        vec!["Penguin Books, Ltd".to_string()]
+    );
+    assert_eq!(
+        corporate_name_variants("United States. Congress.").expect("parse error"),
+        vec!["United States Congress".to_string()]
+    );
+    assert!(corporate_name_variants("").expect("parse error").is_empty());
+}
+
+#[test]
+fn test_initials_key() {
+    let key = initials_key("Tolkien, J. R. R.");
+    assert_eq!(key.as_deref(), Some("tolkien, jrr"));
+    assert_eq!(initials_key("Tolkien, John Ronald Reuel"), key);
+    assert_eq!(initials_key("J.R.R. Tolkien"), key);
+    assert_eq!(initials_key("Tolkien, J. R. R., 1892-1973"), key);
+    assert_ne!(initials_key("Tolkien, Christopher"), key);
+}
+
+#[test]
+fn test_initials_key_particles() {
+    let key = initials_key("Beauvoir, Simone de");
+    assert_eq!(key.as_deref(), Some("beauvoir, s"));
+    assert_eq!(initials_key("de Beauvoir, Simone"), key);
+    assert_eq!(initials_key("Simone de Beauvoir"), key);
+    assert_eq!(
+        initials_key("King, Martin Luther, Jr.").as_deref(),
+        Some("king, ml")
+    );
+    assert_eq!(initials_key("Manopoly"), None);
+}
@@ -12,13 +12,32 @@ pub enum NameError {
 #[derive(Debug, PartialEq, Eq)]
 pub enum NameFmt {
     Single(String),
+    /// A “Last, First” name.
     TwoPart(String, String),
+    /// A “Last, First particle” name, such as “Beauvoir, Simone de”.
+    Particle(String, String, String),
+    /// A name with a generational suffix, such as “Jr”.
+    Suffixed(Box<NameFmt>, String),
+    /// A corporate name (e.g. from MARC 110), which is never inverted.
+    Corporate(String),
     Empty,
 }
 
 impl NameFmt {
     pub fn simplify(self) -> NameFmt {
         match self {
+            NameFmt::Particle(l, f, p) => {
+                if f.is_empty() {
+                    NameFmt::Single(format!("{} {}", p, l)).simplify()
+                } else {
+                    NameFmt::Particle(l, f, p)
+                }
+            }
+            NameFmt::Suffixed(n, s) => match n.simplify() {
+                NameFmt::Empty => NameFmt::Empty,
+                n => NameFmt::Suffixed(Box::new(n), s),
+            },
+            NameFmt::Corporate(n) if n.is_empty() => NameFmt::Empty,
             NameFmt::TwoPart(l, f) => {
                 if f.is_empty() && l.is_empty() {
                     NameFmt::Empty
@@ -34,6 +53,50 @@ impl NameFmt {
             _ => self,
         }
     }
+
+    /// Get the natural-order (“First Last”) form of the name.
+    pub fn natural(&self) -> Option<String> {
+        match self {
+            NameFmt::Empty => None,
+            NameFmt::Single(n) | NameFmt::Corporate(n) => Some(n.clone()),
+            NameFmt::TwoPart(l, f) => Some(format!("{} {}", f, l)),
+            NameFmt::Particle(l, f, p) => Some(format!("{} {} {}", f, p, l)),
+            NameFmt::Suffixed(n, s) => n.natural().map(|n| format!("{} {}", n, s)),
+        }
+    }
+
+    /// Get the inverted (“Last, First”) forms of the name.
+    pub fn inverted(&self) -> Vec<String> {
+        match self {
+            NameFmt::Empty | NameFmt::Single(_) | NameFmt::Corporate(_) => Vec::new(),
+            NameFmt::TwoPart(l, f) => vec![format!("{}, {}", l, f)],
+            NameFmt::Particle(l, f, p) => {
+                vec![format!("{}, {} {}", l, f, p), format!("{} {}, {}", p, l, f)]
+            }
+            NameFmt::Suffixed(n, s) => n
+                .inverted()
+                .into_iter()
+                .map(|n| format!("{}, {}", n, s))
+                .collect(),
+        }
+    }
+
+    /// Get all display forms of the name.
+    ///
+    /// Suffixed names are emitted both with and without their suffix, since
+    /// many sources drop it.
+    pub fn forms(&self) -> Vec<String> {
+        let mut forms = Vec::new();
+        if let NameFmt::Suffixed(n, s) = self {
+            forms.extend(n.forms());
+            if let Some(nat) = n.natural() {
+                forms.push(format!("{}, {}", nat, s));
+            }
+        }
+        forms.extend(self.natural());
+        forms.extend(self.inverted());
+        forms
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
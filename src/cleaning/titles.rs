@@ -0,0 +1,167 @@
+//! Normalize and compare book titles.
+//!
+//! Titles for the same work differ in punctuation, case, subtitles, and edition
+//! notes (e.g. “Emma: A Novel (Penguin Classics)” and “emma”).  This module reduces
+//! titles to a normalized key for blocking and comparison, and computes a fuzzy
+//! similarity between normalized titles.
+use std::collections::HashMap;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Leading articles removed from titles.
+const ARTICLES: &[&str] = &["the", "a", "an"];
+
+/// Normalize a title for matching.
+///
+/// This removes diacritics, bracketed notes, and subtitles (following `:`, `;`, or
+/// ` / `), lowercases the title, replaces punctuation with spaces, and drops a
+/// leading article.
+pub fn normalize_title(title: &str) -> String {
+    let mut main = title;
+    for sep in [":", ";", " / ", " = "] {
+        if let Some((pre, _)) = main.split_once(sep) {
+            if !pre.trim().is_empty() {
+                main = pre;
+            }
+        }
+    }
+
+    let mut buf = String::with_capacity(main.len());
+    let mut depth = 0;
+    for c in main.nfkd() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = (depth - 1).max(0),
+            _ if depth > 0 || is_combining_mark(c) => (),
+            c if c.is_alphanumeric() => buf.extend(c.to_lowercase()),
+            _ => buf.push(' '),
+        }
+    }
+
+    let mut words = buf.split_whitespace().peekable();
+    if let Some(w) = words.peek() {
+        if ARTICLES.contains(w) {
+            words.next();
+        }
+    }
+    let words: Vec<_> = words.collect();
+    if words.is_empty() {
+        // the title was only an article or note; keep what we can
+        buf.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else {
+        words.join(" ")
+    }
+}
+
+/// Check whether a normalized title word is a volume number.
+///
+/// Volume numbers are digits or multi-letter Roman numerals (single letters like
+/// “i” are more often words).
+fn is_volume_token(word: &str) -> bool {
+    if word.chars().all(|c| c.is_ascii_digit()) {
+        true
+    } else {
+        word.len() >= 2 && word.len() <= 4 && word.chars().all(|c| "ivx".contains(c))
+    }
+}
+
+/// Check whether two normalized titles have the same volume numbers.
+///
+/// Titles of numbered volumes in a series (“foundation 2” and “foundation 3”)
+/// differ by only a character or two, so they are very similar even though they
+/// are different books; such titles should only match if their numbers do.
+pub fn volumes_match(a: &str, b: &str) -> bool {
+    let av = a.split_whitespace().filter(|w| is_volume_token(w));
+    let bv = b.split_whitespace().filter(|w| is_volume_token(w));
+    av.eq(bv)
+}
+
+/// Count the character bigrams in a string.
+fn bigrams(s: &str) -> HashMap<(char, char), u32> {
+    let chars: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut counts = HashMap::new();
+    for w in chars.windows(2) {
+        *counts.entry((w[0], w[1])).or_default() += 1;
+    }
+    counts
+}
+
+/// Compute the similarity of two normalized titles.
+///
+/// This is the Sørensen–Dice coefficient over character bigrams (ignoring spaces),
+/// ranging from 0 (nothing in common) to 1 (identical).
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let ab = bigrams(a);
+    let bb = bigrams(b);
+    let na: u32 = ab.values().sum();
+    let nb: u32 = bb.values().sum();
+    if na + nb == 0 {
+        return 0.0;
+    }
+    let shared: u32 = ab
+        .iter()
+        .map(|(k, n)| (*n).min(bb.get(k).copied().unwrap_or_default()))
+        .sum();
+    2.0 * shared as f64 / (na + nb) as f64
+}
+
+#[test]
+fn test_normalize_simple() {
+    assert_eq!(normalize_title("Emma"), "emma");
+}
+
+#[test]
+fn test_normalize_subtitle() {
+    assert_eq!(
+        normalize_title("The Hobbit: or, There and Back Again"),
+        "hobbit"
+    );
+}
+
+#[test]
+fn test_normalize_notes() {
+    assert_eq!(
+        normalize_title("Pride & Prejudice (Penguin Classics)"),
+        "pride prejudice"
+    );
+}
+
+#[test]
+fn test_normalize_accents() {
+    assert_eq!(normalize_title("Les Misérables"), "les miserables");
+}
+
+#[test]
+fn test_normalize_article_only() {
+    assert_eq!(normalize_title("The"), "the");
+}
+
+#[test]
+fn test_similarity_identical() {
+    assert_eq!(title_similarity("emma", "emma"), 1.0);
+}
+
+#[test]
+fn test_similarity_close() {
+    let s = title_similarity(
+        "harry potter and the sorcerers stone",
+        "harry potter sorcerers stone",
+    );
+    assert!(s > 0.8);
+    let s = title_similarity("emma", "persuasion");
+    assert!(s < 0.2);
+}
+
+#[test]
+fn test_volumes_match() {
+    assert!(volumes_match("foundation", "foundation"));
+    assert!(volumes_match("dune messiah 2", "dune messiah 2"));
+    assert!(!volumes_match("foundation 2", "foundation 3"));
+    assert!(!volumes_match("foundation 2", "foundation"));
+    assert!(!volumes_match("rocky ii", "rocky iii"));
+    assert!(volumes_match("i robot", "i robot"));
+}
@@ -1,4 +1,5 @@
 //! Cluster Amazon ratings.
+use crate::interactions::normalize_ratings;
 use crate::prelude::*;
 use polars::prelude::*;
 
@@ -10,24 +11,118 @@ pub struct ClusterRatings {
     #[arg(short = 'o', long = "output", name = "FILE")]
     ratings_out: PathBuf,
 
+    /// The data set name (az2014, az2018, or az2023), for rating normalization.
+    #[arg(long = "dataset", name = "DATASET")]
+    dataset: Option<String>,
+
+    /// Link ASINs to ISBNs with an ASIN-ISBN link table, in addition to treating
+    /// ASINs as ISBNs.
+    #[arg(long = "isbn-links", name = "LINKS")]
+    isbn_links: Option<PathBuf>,
+
+    /// Count each group of duplicate reviews in a duplicate map (from
+    /// `find-duplicates`) once per cluster.
+    #[arg(long = "duplicates", name = "DUPS")]
+    duplicates: Option<PathBuf>,
+
     /// Input file to cluster
     #[arg(name = "INPUT")]
     infile: PathBuf,
 }
 
+/// Resolve each ASIN to a single cluster.
+///
+/// An ASIN that is itself an ISBN keeps that ISBN's cluster.  Otherwise, it
+/// takes the smallest cluster of the ISBNs it is linked to, so that its ratings
+/// are not counted in more than one cluster.
+fn resolve_asins(isbns: LazyFrame, links: Option<LazyFrame>) -> Result<LazyFrame> {
+    let isbns = isbns.select(&[col("isbn"), col("cluster")]);
+    let direct = isbns
+        .clone()
+        .select(&[col("isbn").alias("asin"), col("cluster")]);
+    let Some(links) = links else {
+        return Ok(direct);
+    };
+
+    let linked = links
+        .join(
+            isbns,
+            &[col("isbn")],
+            &[col("isbn")],
+            JoinType::Inner.into(),
+        )
+        .select(&[col("asin"), col("cluster")]);
+    let cands = concat(
+        [
+            direct.with_column(lit(0u8).alias("link_rank")),
+            linked.with_column(lit(1u8).alias("link_rank")),
+        ],
+        UnionArgs::default(),
+    )?;
+    let best = cands
+        .clone()
+        .group_by(&[col("asin")])
+        .agg(&[col("link_rank").min()]);
+    Ok(cands
+        .join(
+            best,
+            &[col("asin"), col("link_rank")],
+            &[col("asin"), col("link_rank")],
+            JoinType::Inner.into(),
+        )
+        .group_by(&[col("asin")])
+        .agg(&[col("cluster").min()]))
+}
+
+/// Remove duplicate copies of reviews from clustered ratings.
+///
+/// Ratings in the duplicate map are kept once for each user, cluster, and
+/// duplicate group, so a review shown on several formats of a book counts once,
+/// but copies that resolve to different clusters are each kept.
+fn remove_duplicates(ratings: LazyFrame, dups: LazyFrame) -> Result<LazyFrame> {
+    let dups = dups.select(&[
+        col("user_id"),
+        col("asin"),
+        col("timestamp"),
+        col("dup_group"),
+    ]);
+    let key = [col("user_id"), col("asin"), col("timestamp")];
+    let joined = ratings.join(dups, key.clone(), key, JoinType::Left.into());
+    let unique = joined.clone().filter(col("dup_group").is_null());
+    // keep one copy of each duplicate review in each cluster
+    let deduped = joined.filter(col("dup_group").is_not_null()).unique(
+        Some(vec!["user_id".into(), "cluster".into(), "dup_group".into()]),
+        UniqueKeepStrategy::First,
+    );
+    Ok(concat([unique, deduped], UnionArgs::default())?)
+}
+
 impl Command for ClusterRatings {
     fn exec(&self) -> Result<()> {
         let isbns = LazyFrame::scan_parquet("book-links/isbn-clusters.parquet", default())?;
-        let isbns = isbns.select(&[col("isbn"), col("cluster")]);
+        let links = if let Some(path) = &self.isbn_links {
+            info!("linking ASINs through {}", path.display());
+            Some(LazyFrame::scan_parquet(path, default())?)
+        } else {
+            None
+        };
+        let asins = resolve_asins(isbns, links)?;
 
         let ratings = LazyFrame::scan_parquet(&self.infile, default())?;
 
         let joined = ratings.join(
-            isbns,
+            asins,
+            &[col("asin")],
             &[col("asin")],
-            &[col("isbn")],
             JoinType::Inner.into(),
         );
+        let joined = if let Some(path) = &self.duplicates {
+            info!("removing duplicate reviews in {}", path.display());
+            let dups = LazyFrame::scan_parquet(path, default())?;
+            remove_duplicates(joined, dups)?
+        } else {
+            joined
+        };
         let joined = joined
             .select(&[
                 col("user_id"),
@@ -44,6 +139,11 @@ impl Command for ClusterRatings {
             col("timestamp").max().alias("last_time"),
             col("item_id").count().alias("nratings"),
         ]);
+        let actions = if let Some(ds) = &self.dataset {
+            normalize_ratings(actions, ds)?
+        } else {
+            actions
+        };
 
         info!("collecting results");
         let actions = actions.collect()?;
@@ -54,3 +154,110 @@ impl Command for ClusterRatings {
         Ok(())
     }
 }
+
+#[test]
+fn test_resolve_asins() {
+    let isbns = df!(
+        "isbn" => ["0140449132", "0140449140", "0199536562"],
+        "cluster" => [5i32, 3, 7],
+    )
+    .unwrap()
+    .lazy();
+    let links = df!(
+        "asin" => ["0140449132", "B000FC1PJI", "B000FC1PJI"],
+        "isbn" => ["0140449140", "0199536562", "0140449140"],
+    )
+    .unwrap()
+    .lazy();
+    let asins = resolve_asins(isbns, Some(links))
+        .unwrap()
+        .sort("asin", default())
+        .collect()
+        .unwrap();
+    let names: Vec<_> = asins
+        .column("asin")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    let clusters: Vec<_> = asins
+        .column("cluster")
+        .unwrap()
+        .i32()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    // the ISBN-valued ASIN keeps its own cluster; the other takes the smallest
+    assert_eq!(
+        names,
+        vec!["0140449132", "0140449140", "0199536562", "B000FC1PJI"]
+    );
+    assert_eq!(clusters, vec![5, 3, 7, 3]);
+}
+
+#[test]
+fn test_remove_duplicates() {
+    let ratings = df!(
+        "user_id" => ["U1", "U1", "U1", "U1", "U2"],
+        "asin" => ["A1", "A2", "A3", "A1", "A1"],
+        "timestamp" => [100i64, 100, 100, 200, 100],
+        "rating" => [5.0f32, 5.0, 5.0, 2.0, 4.0],
+        "cluster" => [10i32, 10, 20, 10, 10],
+    )
+    .unwrap()
+    .lazy();
+    // U1's review at 100 is shown on three ASINs, two of them in cluster 10
+    let dups = df!(
+        "user_id" => ["U1", "U1", "U1"],
+        "asin" => ["A1", "A2", "A3"],
+        "timestamp" => [100i64, 100, 100],
+        "dup_group" => [1i32, 1, 1],
+        "copies" => [3u32, 3, 3],
+    )
+    .unwrap()
+    .lazy();
+    let kept = remove_duplicates(ratings, dups)
+        .unwrap()
+        .sort_by_exprs(
+            [col("user_id"), col("cluster"), col("timestamp")],
+            [false, false, false],
+            false,
+            false,
+        )
+        .collect()
+        .unwrap();
+
+    let rows: Vec<_> = kept
+        .column("user_id")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .zip(
+            kept.column("cluster")
+                .unwrap()
+                .i32()
+                .unwrap()
+                .into_no_null_iter(),
+        )
+        .zip(
+            kept.column("timestamp")
+                .unwrap()
+                .i64()
+                .unwrap()
+                .into_no_null_iter(),
+        )
+        .map(|((u, c), t)| (u, c, t))
+        .collect();
+    // the copy in cluster 20 and the unduplicated ratings are all kept
+    assert_eq!(
+        rows,
+        vec![
+            ("U1", 10, 100),
+            ("U1", 10, 200),
+            ("U1", 20, 100),
+            ("U2", 10, 100)
+        ]
+    );
+}
@@ -0,0 +1,250 @@
+//! Find duplicate Amazon reviews.
+//!
+//! Amazon shows a review on every format of a product, so the review files
+//! contain the same review (same user, text, and time) under several ASINs.  This
+//! command finds those groups and writes a duplicate map for `cluster-ratings`.
+use md5::{Digest, Md5};
+use polars::prelude::*;
+
+use crate::arrow::scan_df_batches;
+use crate::prelude::*;
+
+/// The number of reviews to fingerprint at a time.
+const BATCH_SIZE: usize = 100_000;
+
+/// Find duplicate reviews in an Amazon review file.
+#[derive(Args, Debug)]
+#[command(name = "find-duplicates")]
+pub struct FindDuplicates {
+    /// Duplicate map output file
+    #[arg(short = 'o', long = "output", name = "FILE")]
+    output: PathBuf,
+
+    /// The item column to record in the map (use `parent_asin` for AZ 2023).
+    #[arg(long = "asin-col", default_value = "asin")]
+    asin_col: String,
+
+    /// Input review file
+    #[arg(name = "INPUT")]
+    infile: PathBuf,
+}
+
+/// Normalize review text for fingerprinting.
+///
+/// This lowercases the text and collapses whitespace, so copies that differ only
+/// in spacing or case have the same fingerprint.
+fn normalize_text(text: &str) -> String {
+    let mut norm = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        if !norm.is_empty() {
+            norm.push(' ');
+        }
+        norm.extend(word.chars().flat_map(|c| c.to_lowercase()));
+    }
+    norm
+}
+
+/// Compute the fingerprint of a review's summary and text.
+///
+/// Returns `None` for reviews with no text, which we do not consider duplicates.
+fn fingerprint(summary: Option<&str>, text: Option<&str>) -> Option<u64> {
+    let summary = normalize_text(summary.unwrap_or_default());
+    let text = normalize_text(text.unwrap_or_default());
+    if summary.is_empty() && text.is_empty() {
+        return None;
+    }
+    let mut hash = Md5::new();
+    hash.update(summary.as_bytes());
+    hash.update([0u8]);
+    hash.update(text.as_bytes());
+    let digest = hash.finalize();
+    let mut fp = [0u8; 8];
+    fp.copy_from_slice(&digest[..8]);
+    Some(u64::from_le_bytes(fp))
+}
+
+impl FindDuplicates {
+    /// Fingerprint a batch of reviews.
+    ///
+    /// This keeps only the user, item, time, and fingerprint of reviews with text,
+    /// so the review text does not need to stay in memory.
+    fn fingerprint_batch(&self, reviews: DataFrame) -> Result<DataFrame> {
+        let summaries = reviews.column("summary")?.str()?;
+        let texts = reviews.column("text")?.str()?;
+        let fps: UInt64Chunked = summaries
+            .into_iter()
+            .zip(texts)
+            .map(|(s, t)| fingerprint(s, t))
+            .collect();
+        let df = DataFrame::new(vec![
+            reviews.column("user_id")?.clone(),
+            reviews.column(&self.asin_col)?.clone(),
+            reviews.column("timestamp")?.clone(),
+            fps.into_series().with_name("fingerprint"),
+        ])?;
+        let mask = df.column("fingerprint")?.is_not_null();
+        Ok(df.filter(&mask)?)
+    }
+
+    /// Compute the duplicate map from fingerprinted reviews.
+    fn find_groups(&self, reviews: DataFrame) -> Result<DataFrame> {
+        let key = [col("user_id"), col("timestamp"), col("fingerprint")];
+        let reviews = reviews.lazy();
+        let groups = reviews
+            .clone()
+            .group_by(key.clone())
+            .agg([col(&self.asin_col).count().alias("copies")])
+            .filter(col("copies").gt(lit(1)))
+            .sort_by_exprs(key.clone(), [false, false, false], false, false)
+            .with_row_index("dup_group", Some(1));
+
+        let dups = reviews.join(groups, key.clone(), key, JoinType::Inner.into());
+        let dups = dups
+            .select([
+                col("user_id"),
+                col(&self.asin_col).alias("asin"),
+                col("timestamp"),
+                col("dup_group").cast(DataType::Int32),
+                col("copies").cast(DataType::UInt32),
+            ])
+            // with parent ASINs, copies can have the same item
+            .unique(
+                Some(vec!["user_id".into(), "asin".into(), "timestamp".into()]),
+                UniqueKeepStrategy::First,
+            )
+            .sort_by_exprs(
+                [col("dup_group"), col("asin")],
+                [false, false],
+                false,
+                false,
+            );
+
+        Ok(dups.collect()?)
+    }
+}
+
+impl Command for FindDuplicates {
+    fn exec(&self) -> Result<()> {
+        info!("fingerprinting reviews from {}", self.infile.display());
+        let mut reviews: Option<DataFrame> = None;
+        let mut n_reviews = 0;
+        for batch in scan_df_batches(&self.infile, BATCH_SIZE)? {
+            let batch = batch?;
+            n_reviews += batch.height();
+            let fps = self.fingerprint_batch(batch)?;
+            match &mut reviews {
+                Some(df) => {
+                    df.vstack_mut(&fps)?;
+                }
+                None => reviews = Some(fps),
+            }
+        }
+        let reviews = reviews.ok_or_else(|| anyhow!("no reviews in {}", self.infile.display()))?;
+        info!(
+            "fingerprinted {} reviews ({} with text)",
+            friendly::scalar(n_reviews),
+            friendly::scalar(reviews.height())
+        );
+
+        let dups = self.find_groups(reviews)?;
+        let n_groups = dups.column("dup_group")?.n_unique()?;
+        info!(
+            "found {} duplicate groups with {} of {} reviews",
+            friendly::scalar(n_groups),
+            friendly::scalar(dups.height()),
+            friendly::scalar(n_reviews)
+        );
+
+        save_df_parquet(dups, &self.output)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_fingerprint() {
+    let fp = fingerprint(Some("Great"), Some("Loved  it\n"));
+    assert!(fp.is_some());
+    assert_eq!(fp, fingerprint(Some("great"), Some("loved it")));
+    assert_ne!(fp, fingerprint(Some("Great"), Some("Hated it")));
+    assert_eq!(fingerprint(Some(""), Some("  ")), None);
+}
+
+#[test]
+fn test_find_groups() {
+    let cmd = FindDuplicates {
+        output: PathBuf::new(),
+        asin_col: "asin".into(),
+        infile: PathBuf::new(),
+    };
+    let reviews = df!(
+        "user_id" => [1, 1, 1, 2, 2, 3, 3],
+        "asin" => ["A1", "A2", "A3", "A1", "A2", "A1", "A2"],
+        "timestamp" => [100i64, 100, 200, 100, 300, 100, 100],
+        "summary" => ["Good", "Good", "Good", "Meh", "Meh", "", ""],
+        "text" => ["Fun book", "fun book", "Fun book", "ok", "ok", "", ""]
+    )
+    .unwrap();
+    let reviews = cmd.fingerprint_batch(reviews).unwrap();
+    assert_eq!(
+        reviews.get_column_names(),
+        ["user_id", "asin", "timestamp", "fingerprint"]
+    );
+    let dups = cmd.find_groups(reviews).unwrap();
+    // only user 1's first two reviews are the same review; user 3's have no text
+    assert_eq!(dups.height(), 2);
+    let asins: Vec<_> = dups
+        .column("asin")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(asins, vec!["A1", "A2"]);
+    let copies: Vec<_> = dups
+        .column("copies")
+        .unwrap()
+        .u32()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(copies, vec![2, 2]);
+}
+
+#[test]
+fn test_find_duplicates_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let cmd = FindDuplicates {
+        output: dir.path().join("dups.parquet"),
+        asin_col: "parent_asin".into(),
+        infile: dir.path().join("reviews.parquet"),
+    };
+    let reviews = df!(
+        "user_id" => ["U1", "U1", "U2"],
+        "parent_asin" => ["P1", "P2", "P1"],
+        "timestamp" => [100i64, 100, 100],
+        "rating" => [5.0f32, 5.0, 3.0],
+        "summary" => ["Good", "Good", "Good"],
+        "text" => ["Fun book", "Fun book", "Fun book"]
+    )
+    .unwrap();
+    save_df_parquet(reviews, &cmd.infile).unwrap();
+    cmd.exec().unwrap();
+
+    let dups = crate::arrow::scan_df_parquet(&cmd.output)
+        .unwrap()
+        .collect()
+        .unwrap();
+    assert_eq!(
+        dups.get_column_names(),
+        ["user_id", "asin", "timestamp", "dup_group", "copies"]
+    );
+    assert_eq!(dups.height(), 2);
+    let users: Vec<_> = dups
+        .column("user_id")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(users, vec!["U1", "U1"]);
+}
@@ -1,8 +1,15 @@
 //! Amazon commands.
 pub mod cluster_ratings;
+pub mod find_duplicates;
+pub mod scan_meta;
 pub mod scan_ratings;
 pub mod scan_reviews;
+pub mod user_overlap;
+mod users;
 
 pub use cluster_ratings::ClusterRatings;
+pub use find_duplicates::FindDuplicates;
+pub use scan_meta::ScanMeta;
 pub use scan_ratings::ScanRatings;
 pub use scan_reviews::ScanReviews;
+pub use user_overlap::UserOverlap;
@@ -0,0 +1,227 @@
+//! Scan Amazon product metadata.
+//!
+//! Besides writing the product information, this links ASINs to ISBNs.  Printed
+//! books usually have ISBN ASINs or list their ISBNs in the product details, but
+//! Kindle editions and other `B0…` ASINs rarely do; we link those to the ISBNs of
+//! other products with the same normalized title and author.
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+
+use crate::amazon::*;
+use crate::arrow::*;
+use crate::cleaning::names::match_key;
+use crate::cleaning::titles::normalize_title;
+use crate::prelude::*;
+use crate::util::logging::data_progress;
+
+/// Scan an Amazon metadata JSON file into item info and ISBN links.
+#[derive(Args, Debug)]
+#[command(name = "scan-meta")]
+pub struct ScanMeta {
+    /// Read the AZ 2023 metadata format
+    #[arg(long = "az2023")]
+    az2023: bool,
+
+    /// ISBN link output file
+    #[arg(short = 'L', long = "isbn-links", name = "FILE")]
+    links_out: PathBuf,
+
+    /// Item information output file
+    #[arg(short = 'I', long = "item-info", name = "INFO")]
+    info_out: Option<PathBuf>,
+
+    /// Item category output file
+    #[arg(short = 'C', long = "categories", name = "CATS")]
+    cats_out: Option<PathBuf>,
+
+    /// Input file
+    #[arg(name = "INPUT")]
+    infile: PathBuf,
+}
+
+/// Key for linking products by title and author.
+type TitleKey = (String, String);
+
+/// Accumulate ASIN-ISBN links.
+struct LinkBuilder {
+    writer: TableWriter<ISBNLinkRow>,
+    /// ISBNs for each title key, from products with ISBNs.
+    titles: HashMap<TitleKey, Vec<String>>,
+    /// Products without ISBNs, to link by title key.
+    unlinked: Vec<(String, TitleKey)>,
+    n_direct: usize,
+}
+
+impl LinkBuilder {
+    fn open(path: &Path) -> Result<LinkBuilder> {
+        Ok(LinkBuilder {
+            writer: TableWriter::open(path)?,
+            titles: HashMap::new(),
+            unlinked: Vec::new(),
+            n_direct: 0,
+        })
+    }
+
+    /// Add a product's links.
+    fn add(&mut self, meta: &ProductMeta) -> Result<()> {
+        let key = title_key(meta);
+        if meta.isbns.is_empty() {
+            if let Some(key) = key {
+                self.unlinked.push((meta.asin.clone(), key));
+            }
+            return Ok(());
+        }
+
+        self.n_direct += 1;
+        for isbn in &meta.isbns {
+            self.writer.write_object(ISBNLinkRow {
+                asin: meta.asin.clone(),
+                isbn: isbn.clone(),
+            })?;
+        }
+        if let Some(key) = key {
+            let isbns = self.titles.entry(key).or_default();
+            for isbn in &meta.isbns {
+                if !isbns.contains(isbn) {
+                    isbns.push(isbn.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Link the products without ISBNs by title, and finish writing.
+    fn finish(mut self) -> Result<usize> {
+        info!(
+            "linking {} products without ISBNs by title and author",
+            friendly::scalar(self.unlinked.len())
+        );
+        let mut n_titled = 0;
+        for (asin, key) in self.unlinked {
+            if let Some(isbns) = self.titles.get(&key) {
+                n_titled += 1;
+                for isbn in isbns {
+                    self.writer.write_object(ISBNLinkRow {
+                        asin: asin.clone(),
+                        isbn: isbn.clone(),
+                    })?;
+                }
+            }
+        }
+        info!(
+            "linked {} products by ISBN and {} by title",
+            friendly::scalar(self.n_direct),
+            friendly::scalar(n_titled)
+        );
+        self.writer.finish()
+    }
+}
+
+/// Get the title key for a product, if it has a title and author.
+fn title_key(meta: &ProductMeta) -> Option<TitleKey> {
+    let title = normalize_title(meta.title.as_deref()?);
+    let author = match_key(meta.author.as_deref()?)?;
+    if title.is_empty() {
+        None
+    } else {
+        Some((title, author))
+    }
+}
+
+impl ScanMeta {
+    fn scan<R>(&self) -> Result<()>
+    where
+        R: DeserializeOwned + Into<ProductMeta>,
+    {
+        info!("writing ISBN links to {}", self.links_out.display());
+        let mut links = LinkBuilder::open(&self.links_out)?;
+        let mut info = if let Some(ref p) = self.info_out {
+            info!("writing item info to {}", p.display());
+            Some(TableWriter::open(p)?)
+        } else {
+            None
+        };
+        let mut cats = if let Some(ref p) = self.cats_out {
+            info!("writing item categories to {}", p.display());
+            Some(TableWriter::open(p)?)
+        } else {
+            None
+        };
+
+        let pb = data_progress(0);
+        let src = LineProcessor::open_gzip(&self.infile, pb.clone())?;
+        let mut nprods = 0;
+        for (i, row) in src.json_records().enumerate() {
+            let row: R = row.map_err(|e| {
+                error!("parse error on line {}: {}", i + 1, e);
+                e
+            })?;
+            let meta: ProductMeta = row.into();
+            nprods += 1;
+            links.add(&meta)?;
+
+            if let Some(ref mut cw) = cats {
+                for (depth, category) in meta.categories.iter().enumerate() {
+                    cw.write_object(CategoryRow {
+                        asin: meta.asin.clone(),
+                        depth: depth as u32,
+                        category: category.trim().to_owned(),
+                    })?;
+                }
+            }
+            if let Some(ref mut iw) = info {
+                iw.write_object(ItemInfoRow {
+                    asin: meta.asin,
+                    title: meta.title,
+                    author: meta.author,
+                    format: meta.format,
+                    main_cat: meta.main_cat,
+                })?;
+            }
+        }
+
+        info!("scanned {} products", friendly::scalar(nprods));
+        let nlinks = links.finish()?;
+        info!("wrote {} ISBN links", friendly::scalar(nlinks));
+        if let Some(iw) = info {
+            iw.finish()?;
+        }
+        if let Some(cw) = cats {
+            cw.finish()?;
+        }
+        Ok(())
+    }
+}
+
+impl Command for ScanMeta {
+    fn exec(&self) -> Result<()> {
+        info!("scanning Amazon metadata from {}", self.infile.display());
+        if self.az2023 {
+            self.scan::<SourceMeta2023>()
+        } else {
+            self.scan::<SourceMeta>()
+        }
+    }
+}
+
+#[test]
+fn test_title_key() {
+    let meta = ProductMeta {
+        asin: "B000FC1PJI".into(),
+        title: Some("The Hobbit: Or There and Back Again".into()),
+        author: Some("J.R.R. Tolkien".into()),
+        format: None,
+        main_cat: None,
+        categories: vec![],
+        isbns: vec![],
+    };
+    let key = title_key(&meta).expect("missing key");
+    assert_eq!(key.0, "hobbit");
+
+    let meta = ProductMeta {
+        author: None,
+        ..meta
+    };
+    assert!(title_key(&meta).is_none());
+}
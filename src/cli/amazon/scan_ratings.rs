@@ -2,9 +2,9 @@
 use csv;
 use std::fs::File;
 
+use super::users::UserIndexSpec;
 use crate::amazon::*;
 use crate::arrow::*;
-use crate::ids::index::IdIndex;
 use crate::prelude::*;
 use crate::util::logging::data_progress;
 
@@ -16,6 +16,13 @@ pub struct ScanRatings {
     #[arg(long = "swap-id-columns")]
     swap_columns: bool,
 
+    /// Read the AZ 2023 rating format (with a header and parent ASINs)
+    #[arg(long = "az2023", conflicts_with = "swap_columns")]
+    az2023: bool,
+
+    #[command(flatten)]
+    users: UserIndexSpec,
+
     /// Rating output file
     #[arg(short = 'o', long = "rating-output", name = "FILE")]
     ratings_out: PathBuf,
@@ -37,25 +44,38 @@ impl Command for ScanRatings {
         pb.set_prefix("ratings");
         let src = pb.wrap_read(src);
         let src = csv::ReaderBuilder::new()
-            .has_headers(false)
+            .has_headers(self.az2023)
             .from_reader(src);
-        let src = src.into_deserialize();
-        let mut index: IdIndex<String> = IdIndex::new();
-        for row in src {
-            let mut row: SourceRating = row?;
-            if self.swap_columns {
-                std::mem::swap(&mut row.user, &mut row.asin);
+        let mut index = self.users.open()?;
+        if self.az2023 {
+            for row in src.into_deserialize() {
+                let row: SourceRating2023 = row?;
+                let user_id = index.intern(row.user_id.as_str())?;
+                writer.write_object(RatingRow {
+                    user_id,
+                    asin: row.parent_asin,
+                    rating: row.rating,
+                    timestamp: row.timestamp / 1000,
+                })?;
+            }
+        } else {
+            for row in src.into_deserialize() {
+                let mut row: SourceRating = row?;
+                if self.swap_columns {
+                    std::mem::swap(&mut row.user, &mut row.asin);
+                }
+                let user_id = index.intern(row.user.as_str())?;
+                writer.write_object(RatingRow {
+                    user_id,
+                    asin: row.asin,
+                    rating: row.rating,
+                    timestamp: row.timestamp,
+                })?;
             }
-            let user_id = index.intern(row.user.as_str())?;
-            writer.write_object(RatingRow {
-                user_id,
-                asin: row.asin,
-                rating: row.rating,
-                timestamp: row.timestamp,
-            })?;
         }
 
         writer.finish()?;
+        self.users.save(&index)?;
         Ok(())
     }
 }
@@ -1,7 +1,7 @@
 //! Scan Amazon reviews.
+use super::users::UserIndexSpec;
 use crate::amazon::*;
 use crate::arrow::*;
-use crate::ids::index::IdIndex;
 use crate::prelude::*;
 use crate::util::logging::data_progress;
 
@@ -17,6 +17,13 @@ pub struct ScanReviews {
     #[arg(short = 'r', long = "review-output")]
     reviews_out: Option<PathBuf>,
 
+    /// Read the AZ 2023 review format
+    #[arg(long = "az2023")]
+    az2023: bool,
+
+    #[command(flatten)]
+    users: UserIndexSpec,
+
     /// Input file
     #[arg(name = "INPUT")]
     infile: PathBuf,
@@ -25,7 +32,17 @@ pub struct ScanReviews {
 impl Command for ScanReviews {
     fn exec(&self) -> Result<()> {
         info!("scanning Amazon reviews");
+        if self.az2023 {
+            self.scan_2023()
+        } else {
+            self.scan_2018()
+        }
+    }
+}
 
+impl ScanReviews {
+    /// Scan reviews in the 2014 and 2018 format.
+    fn scan_2018(&self) -> Result<()> {
         let out = &self.ratings_out;
         info!("writing ratings to {}", out.display());
         let mut ratings = TableWriter::open(out)?;
@@ -40,7 +57,7 @@ impl Command for ScanReviews {
         let pb = data_progress(0);
         let src = LineProcessor::open_gzip(&self.infile, pb.clone())?;
         // let mut timer = Timer::new();
-        let mut users: IdIndex<String> = IdIndex::new();
+        let mut users = self.users.open()?;
         let mut lno: usize = 0;
         // let iter = timer.iter_progress("reading reviews", 5.0, src.json_records());
         for row in src.json_records() {
@@ -75,6 +92,65 @@ impl Command for ScanReviews {
         if let Some(rvw) = reviews {
             rvw.finish()?;
         }
+        self.users.save(&users)?;
+        Ok(())
+    }
+
+    /// Scan reviews in the 2023 format.
+    ///
+    /// Ratings are keyed by the parent ASIN, and timestamps are converted from
+    /// milliseconds to seconds to match the other data sets.
+    fn scan_2023(&self) -> Result<()> {
+        let out = &self.ratings_out;
+        info!("writing ratings to {}", out.display());
+        let mut ratings = TableWriter::open(out)?;
+
+        let mut reviews = if let Some(ref p) = self.reviews_out {
+            info!("writing reviews to {}", p.display());
+            Some(TableWriter::open(p)?)
+        } else {
+            None
+        };
+
+        let pb = data_progress(0);
+        let src = LineProcessor::open_gzip(&self.infile, pb.clone())?;
+        let mut users = self.users.open()?;
+        for (i, row) in src.json_records().enumerate() {
+            let row: SourceReview2023 = row.map_err(|e| {
+                error!("parse error on line {}: {}", i + 1, e);
+                e
+            })?;
+            let user_id = users.intern(row.user_id.as_str())?;
+            let timestamp = row.timestamp / 1000;
+            ratings.write_object(RatingRow {
+                user_id,
+                asin: row.parent_asin.clone(),
+                rating: row.rating,
+                timestamp,
+            })?;
+
+            if let Some(ref mut rvw) = reviews {
+                if row.title.is_some() || row.text.is_some() {
+                    rvw.write_object(Review2023Row {
+                        user_id,
+                        asin: row.asin,
+                        parent_asin: row.parent_asin,
+                        rating: row.rating,
+                        timestamp,
+                        helpful_votes: row.helpful_vote,
+                        verified: row.verified_purchase as u8,
+                        summary: row.title.unwrap_or_default().trim().to_owned(),
+                        text: row.text.unwrap_or_default().trim().to_owned(),
+                    })?;
+                }
+            }
+        }
+
+        ratings.finish()?;
+        if let Some(rvw) = reviews {
+            rvw.finish()?;
+        }
+        self.users.save(&users)?;
         Ok(())
     }
 }
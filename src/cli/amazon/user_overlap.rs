@@ -0,0 +1,154 @@
+//! Report user overlap between Amazon data sets.
+use std::fs::File;
+use std::io::Write;
+
+use polars::prelude::*;
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// Report the users shared between Amazon rating files.
+///
+/// The rating files must have been scanned with a shared user index, so the
+/// same reviewer has the same `user_id` in each.
+#[derive(Args, Debug)]
+#[command(name = "user-overlap")]
+pub struct UserOverlap {
+    /// Report output file
+    #[arg(short = 'o', long = "output", name = "FILE")]
+    output: PathBuf,
+
+    /// The item column, for counting shared user-item pairs.
+    #[arg(long = "item-col", default_value = "item_id")]
+    item_col: String,
+
+    /// Rating files to compare
+    #[arg(name = "INPUT", num_args = 2..)]
+    inputs: Vec<PathBuf>,
+}
+
+/// User overlap report.
+#[derive(Debug, Serialize)]
+struct OverlapReport {
+    files: Vec<FileUsers>,
+    /// Number of users in all files.
+    shared_users: usize,
+    /// Number of user-item pairs in all files.
+    shared_pairs: usize,
+}
+
+/// User statistics for a single rating file.
+#[derive(Debug, Serialize)]
+struct FileUsers {
+    file: PathBuf,
+    users: usize,
+    actions: usize,
+    /// Number of actions by users shared with all other files.
+    shared_user_actions: usize,
+}
+
+impl UserOverlap {
+    /// Load the user and item columns of a rating file.
+    fn load(&self, path: &Path) -> Result<DataFrame> {
+        info!("reading ratings from {}", path.display());
+        let ratings = LazyFrame::scan_parquet(path, default())?;
+        let ratings = ratings.select([col("user_id"), col(&self.item_col)]);
+        Ok(ratings.collect()?)
+    }
+}
+
+/// Find the values of the key columns present in all frames.
+fn shared_keys(frames: &[DataFrame], keys: &[&str]) -> Result<DataFrame> {
+    let exprs: Vec<Expr> = keys.iter().map(|k| col(k)).collect();
+    let mut shared: Option<LazyFrame> = None;
+    for df in frames {
+        let df = df
+            .clone()
+            .lazy()
+            .select(exprs.clone())
+            .unique(None, UniqueKeepStrategy::First);
+        shared = Some(match shared {
+            Some(prev) => prev.join(df, exprs.clone(), exprs.clone(), JoinType::Inner.into()),
+            None => df,
+        });
+    }
+    let shared = shared.ok_or_else(|| anyhow!("no rating files"))?;
+    Ok(shared.collect()?)
+}
+
+/// Compute the overlap report.
+fn overlap(files: &[PathBuf], frames: &[DataFrame], item_col: &str) -> Result<OverlapReport> {
+    let users = shared_keys(frames, &["user_id"])?;
+    let pairs = shared_keys(frames, &["user_id", item_col])?;
+
+    let mut stats = Vec::with_capacity(frames.len());
+    for (file, df) in files.iter().zip(frames) {
+        let shared = df.clone().lazy().join(
+            users.clone().lazy(),
+            [col("user_id")],
+            [col("user_id")],
+            JoinType::Inner.into(),
+        );
+        stats.push(FileUsers {
+            file: file.clone(),
+            users: df.column("user_id")?.n_unique()?,
+            actions: df.height(),
+            shared_user_actions: shared.collect()?.height(),
+        });
+    }
+
+    Ok(OverlapReport {
+        files: stats,
+        shared_users: users.height(),
+        shared_pairs: pairs.height(),
+    })
+}
+
+impl Command for UserOverlap {
+    fn exec(&self) -> Result<()> {
+        let frames = self
+            .inputs
+            .iter()
+            .map(|p| self.load(p))
+            .collect::<Result<Vec<_>>>()?;
+
+        let report = overlap(&self.inputs, &frames, &self.item_col)?;
+        for fu in &report.files {
+            info!(
+                "{}: {} users, {} shared users with {} of {} actions",
+                fu.file.display(),
+                friendly::scalar(fu.users),
+                friendly::scalar(report.shared_users),
+                friendly::scalar(fu.shared_user_actions),
+                friendly::scalar(fu.actions),
+            );
+        }
+
+        info!("saving report to {}", self.output.display());
+        let mut out = File::create(&self.output)?;
+        serde_json::to_writer_pretty(&mut out, &report)?;
+        out.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_overlap() {
+    let a = df!(
+        "user_id" => [1, 1, 2, 3],
+        "item_id" => [10, 11, 10, 12]
+    )
+    .unwrap();
+    let b = df!(
+        "user_id" => [1, 3, 3, 4],
+        "item_id" => [10, 10, 11, 12]
+    )
+    .unwrap();
+    let files = vec![PathBuf::from("a"), PathBuf::from("b")];
+    let report = overlap(&files, &[a, b], "item_id").unwrap();
+    assert_eq!(report.shared_users, 2);
+    assert_eq!(report.shared_pairs, 1);
+    assert_eq!(report.files[0].users, 3);
+    assert_eq!(report.files[0].shared_user_actions, 3);
+    assert_eq!(report.files[1].shared_user_actions, 3);
+}
@@ -0,0 +1,38 @@
+//! Shared user indexes for Amazon scans.
+//!
+//! The 2014 and 2018 data use the same reviewer IDs, so scanning one data set
+//! with the user index from another gives users the same numeric IDs in both.
+use crate::ids::index::IdIndex;
+use crate::prelude::*;
+
+/// Options for sharing a user index between scans.
+#[derive(Args, Debug, Clone)]
+pub struct UserIndexSpec {
+    /// Start from the user index in FILE, so known users keep their IDs.
+    #[arg(long = "users", name = "USERS")]
+    users: Option<PathBuf>,
+
+    /// Save the user index (including any loaded users) to FILE.
+    #[arg(long = "save-users", name = "SAVE_USERS")]
+    save_users: Option<PathBuf>,
+}
+
+impl UserIndexSpec {
+    /// Open the user index, loading it if requested.
+    pub fn open(&self) -> Result<IdIndex<String>> {
+        if let Some(path) = &self.users {
+            IdIndex::load_standard(path)
+        } else {
+            Ok(IdIndex::new())
+        }
+    }
+
+    /// Save the user index, if requested.
+    pub fn save(&self, index: &IdIndex<String>) -> Result<()> {
+        if let Some(path) = &self.save_users {
+            info!("saving {} users", friendly::scalar(index.len()));
+            index.save_standard(path)?;
+        }
+        Ok(())
+    }
+}
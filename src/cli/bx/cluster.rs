@@ -1,6 +1,7 @@
 //! BookCrossing interaction clustering.
 use std::path::PathBuf;
 
+use crate::interactions::normalize_ratings;
 use crate::prelude::*;
 use polars::prelude::*;
 
@@ -50,10 +51,11 @@ impl Command for Cluster {
             col("cluster").alias("item_id"),
         ]);
         let agg = if self.ratings {
-            grouped.agg(&[
+            let agg = grouped.agg(&[
                 col("rating").median().alias("rating"),
                 col("cluster").count().alias("nratings"),
-            ])
+            ]);
+            normalize_ratings(agg, "bx")?
         } else {
             grouped.agg(&[col("cluster").count().alias("nactions")])
         };
@@ -1,17 +1,37 @@
 //! BookCrossing data extraction.
 //!
 //! The BookCrossing CSV files are corrupt, so this command extracts them and fixes
-//! up the character sets to make them well-formed CSV.
+//! up the character sets to make them well-formed (semicolon-separated) CSV.
+//! Besides the ratings, it can
+//! extract the user (location and age) and book (title, author, year, and
+//! publisher) tables into Parquet files.
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 
+use parquet_derive::ParquetRecordWriter;
 use zip::ZipArchive;
 
+use crate::arrow::*;
 use crate::prelude::*;
 
+/// The youngest plausible user age.
+const MIN_AGE: i32 = 5;
+/// The oldest plausible user age.
+const MAX_AGE: i32 = 100;
+/// The latest plausible publication year (the data was collected in 2004).
+const MAX_YEAR: i32 = 2005;
+
 #[derive(Args, Debug)]
 pub struct Extract {
+    /// Write cleaned users to FILE (in Parquet format).
+    #[arg(long = "users", name = "FILE")]
+    users: Option<PathBuf>,
+
+    /// Write cleaned books to BOOKFILE (in Parquet format).
+    #[arg(long = "books", name = "BOOKFILE")]
+    books: Option<PathBuf>,
+
     /// The zip file to read.
     #[arg(name = "ZIPFILE")]
     zipfile: PathBuf,
@@ -21,57 +41,143 @@ pub struct Extract {
     outfile: PathBuf,
 }
 
-impl Command for Extract {
-    fn exec(&self) -> Result<()> {
-        info!("reading {:?}", self.zipfile);
-        let file = File::open(&self.zipfile)?;
-        let mut zip = ZipArchive::new(file)?;
-        let mut entry = zip.by_name("BX-Book-Ratings.csv")?;
-        let mut data = entry.read_all_sized()?;
+/// A BookCrossing user, as written to `users.parquet`.
+#[derive(ParquetRecordWriter, Debug, PartialEq)]
+struct UserRow {
+    user_id: i32,
+    /// The location as entered.
+    location: Option<String>,
+    city: Option<String>,
+    region: Option<String>,
+    country: Option<String>,
+    /// The user's age, if it is plausible.
+    age: Option<i32>,
+}
 
-        info!("cleaning up data file");
+/// A BookCrossing book, as written to `books.parquet`.
+#[derive(ParquetRecordWriter, Debug, PartialEq)]
+struct BookRow {
+    book_id: i32,
+    isbn: String,
+    title: Option<String>,
+    author: Option<String>,
+    year: Option<i32>,
+    publisher: Option<String>,
+}
 
-        debug!("removing non-ASCII characters and carriage returns");
-        data.retain(|b| *b < 128 && *b != b'\r');
+/// Read a file from the zip archive, and clean up its character set.
+///
+/// Returns the data after the header, after checking that the header begins with
+/// the expected text.  The data is still semicolon-separated.
+fn read_cleaned(zip: &mut ZipArchive<File>, name: &str, header: &str) -> Result<String> {
+    let mut entry = zip.by_name(name)?;
+    let mut data = entry.read_all_sized()?;
 
-        debug!("replacing semicolons to make CSV");
-        // can this be done with retain_with?
-        for i in 0..data.len() {
-            let c = data[i];
-            if c == b';' {
-                data[i] = b',';
-            }
-        }
+    info!("cleaning up data file {}", name);
 
-        debug!("splitting CSV header");
-        let data = String::from_utf8(data)?;
-        let pos = if let Some(p) = data.find('\n') {
-            p
-        } else {
-            error!("no newline found, corrupt input data?");
-            return Err(anyhow!("corrupt data"));
-        };
-        let (hdr, rest) = data.split_at(pos + 1);
-        if !hdr.starts_with("\"User-ID\",") {
-            error!("unexpected file header found");
-            info!("found header: “{}“", hdr);
-            info!("expected cleaned header to begin with “\"User-ID\",“");
-            return Err(anyhow!("corrupt data"));
-        }
+    debug!("removing non-ASCII characters and carriage returns");
+    data.retain(|b| *b < 128 && *b != b'\r');
+
+    debug!("splitting CSV header");
+    let mut data = String::from_utf8(data)?;
+    let pos = if let Some(p) = data.find('\n') {
+        p
+    } else {
+        error!("no newline found, corrupt input data?");
+        return Err(anyhow!("corrupt data"));
+    };
+    let rest = data.split_off(pos + 1);
+    if !data.starts_with(header) {
+        error!("unexpected file header found");
+        info!("found header: “{}“", data);
+        info!("expected cleaned header to begin with “{}“", header);
+        return Err(anyhow!("corrupt data"));
+    }
+
+    Ok(rest)
+}
+
+/// Clean an ISBN from the BookCrossing data.
+fn clean_isbn(isbn: &str) -> String {
+    let mut isbn = isbn.to_uppercase();
+    isbn.retain(|c| c.is_ascii_digit() || c == 'X');
+    isbn
+}
+
+/// Clean a text field, treating empty strings and `NULL` as missing.
+fn clean_field(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() || text == "NULL" || text == "n/a" {
+        None
+    } else {
+        Some(
+            text.replace("&amp;", "&")
+                .replace("&quot;", "\"")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">"),
+        )
+    }
+}
+
+/// Parse a number, treating implausible values as missing.
+fn clean_number(text: &str, min: i32, max: i32) -> Option<i32> {
+    let num: i32 = text.trim().parse().ok()?;
+    if num >= min && num <= max {
+        Some(num)
+    } else {
+        None
+    }
+}
+
+/// Parse a location into city, region, and country.
+///
+/// BookCrossing locations come from a three-part form, and are stored as
+/// “city, region, country”.  Extra parts are kept in the region, and missing
+/// parts (empty or `n/a`) are `None`.
+fn parse_location(loc: &str) -> (Option<String>, Option<String>, Option<String>) {
+    let parts: Vec<&str> = loc
+        .split(',')
+        .map(|p| p.trim().trim_end_matches('.'))
+        .collect();
+    let n = parts.len();
+    let city = parts.first().and_then(|p| clean_field(p));
+    let region = if n > 1 {
+        let end = if n > 2 { n - 1 } else { n };
+        let region: Vec<String> = parts[1..end]
+            .iter()
+            .filter_map(|p| clean_field(p))
+            .collect();
+        clean_field(&region.join(", "))
+    } else {
+        None
+    };
+    let country = if n > 2 {
+        clean_field(parts[n - 1])
+    } else {
+        None
+    };
+    (city, region, country)
+}
+
+impl Extract {
+    /// Extract the ratings into the cleaned CSV file.
+    fn extract_ratings(&self, zip: &mut ZipArchive<File>) -> Result<()> {
+        let rest = read_cleaned(zip, "BX-Book-Ratings.csv", "\"User-ID\";")?;
 
         info!("writing cleaned output");
         let mut out = File::create(&self.outfile)?;
         write!(out, "user,isbn,rating\n")?;
-        let csvin = csv::Reader::from_reader(rest.as_bytes());
+        let csvin = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(b';')
+            .from_reader(rest.as_bytes());
         for row in csvin.into_records() {
             let row = row?;
             let user = row.get(0).ok_or(anyhow!("invalid CSV row"))?;
             let isbn = row.get(1).ok_or(anyhow!("invalid CSV row"))?;
             let rating = row.get(2).ok_or(anyhow!("invalid CSV row"))?;
 
-            let mut isbn = isbn.to_uppercase();
-            isbn.retain(|c| (c >= '0' && c <= '9') || c == 'X');
-
+            let isbn = clean_isbn(isbn);
             if isbn.len() > 0 {
                 write!(out, "{},{},{}\n", user, isbn, rating)?;
             }
@@ -79,4 +185,154 @@ impl Command for Extract {
 
         Ok(())
     }
+
+    /// Extract the users into a Parquet file.
+    fn extract_users(&self, zip: &mut ZipArchive<File>, path: &Path) -> Result<()> {
+        let rest = read_cleaned(zip, "BX-Users.csv", "\"User-ID\";")?;
+
+        info!("writing users to {}", path.display());
+        let mut out = TableWriter::open(path)?;
+        let csvin = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(b';')
+            .from_reader(rest.as_bytes());
+        let mut n_aged = 0;
+        for row in csvin.into_records() {
+            let row = row?;
+            let user = row.get(0).ok_or(anyhow!("invalid CSV row"))?;
+            let location = row.get(1).ok_or(anyhow!("invalid CSV row"))?;
+            let age = row.get(2).ok_or(anyhow!("invalid CSV row"))?;
+
+            let (city, region, country) = parse_location(location);
+            let age = clean_number(age, MIN_AGE, MAX_AGE);
+            if age.is_some() {
+                n_aged += 1;
+            }
+            out.write_object(UserRow {
+                user_id: user.trim().parse()?,
+                location: clean_field(location),
+                city,
+                region,
+                country,
+                age,
+            })?;
+        }
+
+        let n = out.finish()?;
+        info!(
+            "wrote {} users ({} with plausible ages)",
+            friendly::scalar(n),
+            friendly::scalar(n_aged)
+        );
+        Ok(())
+    }
+
+    /// Extract the books into a Parquet file.
+    fn extract_books(&self, zip: &mut ZipArchive<File>, path: &Path) -> Result<()> {
+        let rest = read_cleaned(zip, "BX-Books.csv", "\"ISBN\";")?;
+
+        info!("writing books to {}", path.display());
+        let mut out = TableWriter::open(path)?;
+        // titles have backslash-escaped quotes, but some also end in a
+        // backslash, which would escape the closing quote
+        let rest = rest.replace("\\\";\"", "\";\"");
+        let csvin = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(b';')
+            .escape(Some(b'\\'))
+            .flexible(true)
+            .from_reader(rest.as_bytes());
+        let mut book_id = 0;
+        let mut n_bad = 0;
+        for row in csvin.into_records() {
+            let row = row?;
+            if row.len() < 5 {
+                n_bad += 1;
+                continue;
+            }
+            let isbn = clean_isbn(&row[0]);
+            if isbn.is_empty() {
+                continue;
+            }
+            book_id += 1;
+            out.write_object(BookRow {
+                book_id,
+                isbn,
+                title: clean_field(&row[1]),
+                author: clean_field(&row[2]),
+                year: clean_number(&row[3], 1, MAX_YEAR),
+                publisher: clean_field(&row[4]),
+            })?;
+        }
+
+        if n_bad > 0 {
+            warn!("skipped {} malformed book rows", n_bad);
+        }
+        let n = out.finish()?;
+        info!("wrote {} books", friendly::scalar(n));
+        Ok(())
+    }
+}
+
+impl Command for Extract {
+    fn exec(&self) -> Result<()> {
+        info!("reading {:?}", self.zipfile);
+        let file = File::open(&self.zipfile)?;
+        let mut zip = ZipArchive::new(file)?;
+
+        self.extract_ratings(&mut zip)?;
+        if let Some(path) = &self.users {
+            self.extract_users(&mut zip, path)?;
+        }
+        if let Some(path) = &self.books {
+            self.extract_books(&mut zip, path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_location() {
+    assert_eq!(
+        parse_location("nyc, new york, usa"),
+        (
+            Some("nyc".to_string()),
+            Some("new york".to_string()),
+            Some("usa".to_string())
+        )
+    );
+    assert_eq!(
+        parse_location("porto, , portugal."),
+        (
+            Some("porto".to_string()),
+            None,
+            Some("portugal".to_string())
+        )
+    );
+    assert_eq!(
+        parse_location("london, n/a, england, united kingdom"),
+        (
+            Some("london".to_string()),
+            Some("england".to_string()),
+            Some("united kingdom".to_string())
+        )
+    );
+}
+
+#[test]
+fn test_clean_number() {
+    assert_eq!(clean_number("34", MIN_AGE, MAX_AGE), Some(34));
+    assert_eq!(clean_number("NULL", MIN_AGE, MAX_AGE), None);
+    assert_eq!(clean_number("0", MIN_AGE, MAX_AGE), None);
+    assert_eq!(clean_number("244", MIN_AGE, MAX_AGE), None);
+}
+
+#[test]
+fn test_clean_field() {
+    assert_eq!(
+        clean_field(" Simon &amp; Schuster ").as_deref(),
+        Some("Simon & Schuster")
+    );
+    assert_eq!(clean_field("NULL"), None);
 }
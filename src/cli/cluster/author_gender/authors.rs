@@ -1,30 +1,142 @@
 //! Support for loading author info.
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use parquet_derive::ParquetRecordReader;
+use parquet_derive::{ParquetRecordReader, ParquetRecordWriter};
+use polars::prelude::*;
 
 use crate::arrow::*;
+use crate::cleaning::names::name_variants;
+use crate::cli::link_authors::AuthorNS;
 use crate::gender::*;
 use crate::prelude::*;
 use crate::util::logging::item_progress;
 
+/// Mask bit for gender records from VIAF.
+pub const SRC_VIAF: u8 = 1;
+/// Mask bit for gender records from Wikidata.
+pub const SRC_WIKIDATA: u8 = 2;
+
 #[derive(Debug, Default)]
 pub struct AuthorInfo {
     pub n_author_recs: u32,
     pub genders: GenderBag,
+    /// Mask of the sources that contributed gender records.
+    pub gender_sources: u8,
 }
 
 pub type AuthorTable = HashMap<String, AuthorInfo>;
 
+/// Author gender records keyed by author graph node code.
+pub type NodeTable = HashMap<i64, AuthorInfo>;
+
+impl AuthorInfo {
+    /// Merge another author's records into this one.
+    pub fn merge_from(&mut self, other: &AuthorInfo) {
+        self.n_author_recs += other.n_author_recs;
+        self.genders.merge_from(&other.genders);
+        self.gender_sources |= other.gender_sources;
+    }
+}
+
+/// Describe a mask of gender sources.
+pub fn source_label(mask: u8) -> Option<&'static str> {
+    match mask {
+        0 => None,
+        SRC_VIAF => Some("viaf"),
+        SRC_WIKIDATA => Some("wikidata"),
+        _ => Some("viaf+wikidata"),
+    }
+}
+
 #[derive(Debug, ParquetRecordReader)]
 struct NameRow {
     rec_id: u32,
     name: String,
 }
 
+/// The gender assertions of an author record, for auditing gender resolution.
+#[derive(ParquetRecordWriter, Debug)]
+pub struct AuthorGenderDetail {
+    /// The authority file of the record (`viaf` or `wikidata`).
+    pub authority: String,
+    pub rec_id: u32,
+    /// The resolved gender, after applying source preferences.
+    pub gender: String,
+    /// The distinct asserted genders, separated by `|`.
+    pub asserted: String,
+    /// The `source=gender` assertions, separated by `|`.
+    pub sources: String,
+    pub n_assertions: u32,
+    /// The fraction of assertions agreeing with the most common gender.
+    pub agreement: Option<f32>,
+}
+
+/// Writer for author-level gender assertion details.
+pub struct DetailWriter {
+    writer: TableWriter<AuthorGenderDetail>,
+    prefs: Vec<String>,
+}
+
+impl DetailWriter {
+    pub fn open(path: &Path, prefs: &[String]) -> Result<DetailWriter> {
+        Ok(DetailWriter {
+            writer: TableWriter::open(path)?,
+            prefs: prefs.to_vec(),
+        })
+    }
+
+    /// Write the gender details of an authority's records.
+    fn write_bags(&mut self, authority: &str, bags: &HashMap<u32, GenderBag>) -> Result<()> {
+        let mut ids: Vec<_> = bags.keys().copied().collect();
+        ids.sort_unstable();
+        for rec_id in ids {
+            let bag = &bags[&rec_id];
+            let mut asserted: Vec<String> = bag
+                .sources()
+                .iter()
+                .map(|sg| sg.gender.to_string())
+                .collect();
+            asserted.sort();
+            asserted.dedup();
+            let sources: Vec<String> = bag
+                .sources()
+                .iter()
+                .map(|sg| {
+                    format!(
+                        "{}={}",
+                        sg.source.as_deref().unwrap_or("unspecified"),
+                        sg.gender
+                    )
+                })
+                .collect();
+            self.writer.write_object(AuthorGenderDetail {
+                authority: authority.to_string(),
+                rec_id,
+                gender: bag.resolve(&self.prefs).to_string(),
+                asserted: asserted.join("|"),
+                sources: sources.join("|"),
+                n_assertions: bag.len() as u32,
+                agreement: bag.agreement(),
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<usize> {
+        self.writer.finish()
+    }
+}
+
 #[derive(Debug, ParquetRecordReader)]
-struct GenderRow {
-    rec_id: u32,
+struct WDNameRow {
+    id: u32,
+    name: String,
+}
+
+#[derive(Debug, ParquetRecordReader)]
+struct WDGenderRow {
+    id: u32,
     gender: String,
 }
 
@@ -59,15 +171,32 @@ fn viaf_load_genders() -> Result<HashMap<u32, GenderBag>> {
     let timer = Timer::new();
 
     info!("loading VIAF author genders");
-    let iter = scan_parquet_file("viaf/author-genders.parquet")?;
+    // the source column is optional, so we read with Polars
+    let df = scan_df_parquet("viaf/author-genders.parquet")?.collect()?;
+    let rec_ids = df.column("rec_id")?.u32()?;
+    let genders = df.column("gender")?.str()?;
+    // files extracted before sources were recorded have no source column
+    let no_sources;
+    let sources = match df.column("source") {
+        Ok(col) => col.str()?,
+        Err(_) => {
+            no_sources = StringChunked::full_null("source", df.height());
+            &no_sources
+        }
+    };
 
-    let pb = item_progress(iter.remaining(), "authors");
-    let iter = pb.wrap_iter(iter);
+    let pb = item_progress(df.height(), "authors");
+    let rows = pb.wrap_iter(rec_ids.into_iter().zip(genders).zip(sources));
 
-    for row in iter {
-        let row: GenderRow = row?;
-        let gender: Gender = row.gender.into();
-        map.entry(row.rec_id).or_default().add(gender);
+    // share source names between records
+    let mut source_names: HashMap<&str, Arc<str>> = HashMap::new();
+    for ((rec_id, gender), source) in rows {
+        let (Some(rec_id), Some(gender)) = (rec_id, gender) else {
+            continue;
+        };
+        let gender: Gender = gender.into();
+        let source = source.map(|s| source_names.entry(s).or_insert_with(|| s.into()).clone());
+        map.entry(rec_id).or_default().add_from(source, gender);
     }
 
     info!(
@@ -79,14 +208,14 @@ fn viaf_load_genders() -> Result<HashMap<u32, GenderBag>> {
     Ok(map)
 }
 
-/// Load the VIAF author gender records.
-#[inline(never)]
-pub fn viaf_author_table() -> Result<AuthorTable> {
+/// Merge per-record names and genders into an author table.
+fn merge_records(
+    rec_names: HashMap<u32, Vec<String>>,
+    rec_genders: HashMap<u32, GenderBag>,
+    source: u8,
+) -> AuthorTable {
     let mut table = AuthorTable::new();
 
-    let rec_names = viaf_load_names()?;
-    let rec_genders = viaf_load_genders()?;
-
     info!("merging gender records");
     let pb = item_progress(rec_names.len() as u64, "clusters");
     let timer = Timer::new();
@@ -97,11 +226,159 @@ pub fn viaf_author_table() -> Result<AuthorTable> {
             rec.n_author_recs += 1;
             if let Some(bag) = genders {
                 rec.genders.merge_from(bag);
+                rec.gender_sources |= source;
             }
         }
     }
 
     info!("merged {} gender records in {}", table.len(), timer);
 
-    Ok(table)
+    table
+}
+
+/// Load the VIAF author gender records.
+#[inline(never)]
+pub fn viaf_author_table(detail: Option<&mut DetailWriter>) -> Result<AuthorTable> {
+    let rec_names = viaf_load_names()?;
+    let rec_genders = viaf_load_genders()?;
+    if let Some(detail) = detail {
+        detail.write_bags("viaf", &rec_genders)?;
+    }
+    Ok(merge_records(rec_names, rec_genders, SRC_VIAF))
+}
+
+/// Key per-record genders by their author graph node codes.
+fn node_records(rec_genders: HashMap<u32, GenderBag>, ns: AuthorNS, source: u8) -> NodeTable {
+    rec_genders
+        .into_iter()
+        .map(|(id, genders)| {
+            let info = AuthorInfo {
+                n_author_recs: 1,
+                genders,
+                gender_sources: source,
+            };
+            (ns.code(id as u64), info)
+        })
+        .collect()
+}
+
+/// Load the VIAF author gender records by author graph node.
+pub fn viaf_node_table(detail: Option<&mut DetailWriter>) -> Result<NodeTable> {
+    let rec_genders = viaf_load_genders()?;
+    if let Some(detail) = detail {
+        detail.write_bags("viaf", &rec_genders)?;
+    }
+    Ok(node_records(rec_genders, AuthorNS::Viaf, SRC_VIAF))
+}
+
+/// Expand a Wikidata name into variants for matching book author names.
+///
+/// Wikidata labels are usually in “First Last” order, while library records use
+/// “Last, First”, so we also emit the inverted form of multi-word names.
+fn wikidata_name_variants(name: &str) -> Vec<String> {
+    let mut variants = name_variants(name).unwrap_or_default();
+    if !name.contains(',') {
+        if let Some((first, last)) = name.trim().rsplit_once(' ') {
+            variants.push(format!("{}, {}", last, first.trim()));
+        }
+    }
+    variants
+}
+
+/// Load Wikidata human names.
+fn wikidata_load_names() -> Result<HashMap<u32, Vec<String>>> {
+    let mut map: HashMap<u32, Vec<String>> = HashMap::new();
+
+    info!("loading Wikidata author names");
+    let iter = scan_parquet_file("wikidata/names.parquet")?;
+
+    let pb = item_progress(iter.remaining() as u64, "names");
+    let timer = Timer::new();
+
+    for row in pb.wrap_iter(iter) {
+        let row: WDNameRow = row?;
+        let names = map.entry(row.id).or_default();
+        for name in wikidata_name_variants(&row.name) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    info!(
+        "loaded names for {} humans in {}",
+        map.len(),
+        timer.human_elapsed()
+    );
+
+    Ok(map)
+}
+
+/// Load Wikidata human genders.
+fn wikidata_load_genders() -> Result<HashMap<u32, GenderBag>> {
+    let mut map: HashMap<u32, GenderBag> = HashMap::new();
+    let timer = Timer::new();
+
+    info!("loading Wikidata author genders");
+    let iter = scan_parquet_file("wikidata/genders.parquet")?;
+
+    let pb = item_progress(iter.remaining(), "humans");
+    let source: Arc<str> = "wikidata".into();
+    for row in pb.wrap_iter(iter) {
+        let row: WDGenderRow = row?;
+        let gender: Gender = row.gender.into();
+        map.entry(row.id)
+            .or_default()
+            .add_from(Some(source.clone()), gender);
+    }
+
+    info!(
+        "loaded genders for {} humans in {}",
+        map.len(),
+        timer.human_elapsed()
+    );
+
+    Ok(map)
+}
+
+/// Load the Wikidata author gender records.
+#[inline(never)]
+pub fn wikidata_author_table(detail: Option<&mut DetailWriter>) -> Result<AuthorTable> {
+    let rec_names = wikidata_load_names()?;
+    let rec_genders = wikidata_load_genders()?;
+    if let Some(detail) = detail {
+        detail.write_bags("wikidata", &rec_genders)?;
+    }
+    Ok(merge_records(rec_names, rec_genders, SRC_WIKIDATA))
+}
+
+/// Load the Wikidata author gender records by author graph node.
+pub fn wikidata_node_table(detail: Option<&mut DetailWriter>) -> Result<NodeTable> {
+    let rec_genders = wikidata_load_genders()?;
+    if let Some(detail) = detail {
+        detail.write_bags("wikidata", &rec_genders)?;
+    }
+    Ok(node_records(rec_genders, AuthorNS::Wikidata, SRC_WIKIDATA))
+}
+
+/// Merge one author table into another.
+pub fn merge_tables(table: &mut AuthorTable, other: AuthorTable) {
+    info!("merging {} names into author table", other.len());
+    for (name, info) in other {
+        table.entry(name).or_default().merge_from(&info);
+    }
+}
+
+#[test]
+fn test_wikidata_variants() {
+    let vs = wikidata_name_variants("Jane Austen");
+    assert!(vs.contains(&"Jane Austen".to_string()));
+    assert!(vs.contains(&"Austen, Jane".to_string()));
+}
+
+#[test]
+fn test_source_label() {
+    assert_eq!(source_label(0), None);
+    assert_eq!(source_label(SRC_WIKIDATA), Some("wikidata"));
+    assert_eq!(source_label(SRC_VIAF | SRC_WIKIDATA), Some("viaf+wikidata"));
 }
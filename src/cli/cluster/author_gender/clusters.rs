@@ -3,8 +3,10 @@ use std::collections::HashMap;
 use std::convert::identity;
 use std::path::Path;
 
-use super::authors::AuthorTable;
-use crate::arrow::scan_parquet_file;
+use super::authors::{AuthorInfo, AuthorTable, NodeTable};
+use crate::arrow::{scan_df_parquet, scan_parquet_file};
+use crate::cleaning::names::initials_key;
+use crate::cli::link_authors::AuthorNS;
 use crate::gender::*;
 use crate::prelude::*;
 use crate::util::logging::item_progress;
@@ -12,12 +14,80 @@ use anyhow::Result;
 use parquet_derive::ParquetRecordReader;
 use polars::prelude::*;
 
+/// Gender category of a single book author, for counting a cluster's authors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorCategory {
+    Female,
+    Male,
+    /// The author's gender records disagree.
+    Ambiguous,
+    /// The author has a gender other than female or male.
+    Other,
+    /// The author has no author records or no gender records.
+    Unresolved,
+}
+
+impl AuthorCategory {
+    /// Categorize an author's resolved gender.
+    pub fn of(gender: &Gender) -> AuthorCategory {
+        match gender {
+            Gender::Female => AuthorCategory::Female,
+            Gender::Male => AuthorCategory::Male,
+            Gender::Ambiguous => AuthorCategory::Ambiguous,
+            Gender::Open(_) => AuthorCategory::Other,
+            Gender::Unknown => AuthorCategory::Unresolved,
+        }
+    }
+
+    /// Get the label for this category.
+    pub fn label(self) -> &'static str {
+        match self {
+            AuthorCategory::Female => "female",
+            AuthorCategory::Male => "male",
+            AuthorCategory::Ambiguous => "ambiguous",
+            AuthorCategory::Other => "other",
+            AuthorCategory::Unresolved => "unresolved",
+        }
+    }
+}
+
+/// A book author of a cluster, with their resolved gender.
+#[derive(Debug)]
+pub struct BookAuthor {
+    pub name: String,
+    pub position: i16,
+    /// The author's gender, with conflicts resolved by source preference.
+    pub gender: Gender,
+    pub category: AuthorCategory,
+}
+
 /// Record for storing a cluster's gender statistics while aggregating.
 #[derive(Debug, Default)]
 pub struct ClusterStats {
     pub n_book_authors: u32,
     pub n_author_recs: u32,
     pub genders: GenderBag,
+    pub gender_sources: u8,
+    pub authors: Vec<BookAuthor>,
+}
+
+impl ClusterStats {
+    /// Count the cluster's authors in a gender category.
+    pub fn count(&self, cat: AuthorCategory) -> u32 {
+        self.authors.iter().filter(|a| a.category == cat).count() as u32
+    }
+
+    /// Merge the resolved genders of the cluster's authors.
+    pub fn merged_gender(&self) -> Gender {
+        self.authors
+            .iter()
+            .fold(Gender::Unknown, |g, a| g.merge(&a.gender))
+    }
+
+    /// Get the author with the lowest position.
+    pub fn first_author(&self) -> Option<&BookAuthor> {
+        self.authors.iter().min_by_key(|a| a.position)
+    }
 }
 
 /// Row struct for reading cluster author names.
@@ -25,12 +95,41 @@ pub struct ClusterStats {
 struct ClusterAuthor {
     cluster: i32,
     author_name: String,
+    position: i16,
 }
 
 pub type ClusterTable = HashMap<i32, ClusterStats>;
 
+/// Add a book author to its cluster, with its resolved gender.
+fn add_author(
+    table: &mut ClusterTable,
+    cluster: i32,
+    name: String,
+    position: i16,
+    info: Option<&AuthorInfo>,
+    prefs: &[String],
+) {
+    let rec = table.entry(cluster).or_default();
+    rec.n_book_authors += 1;
+    if let Some(info) = info {
+        rec.n_author_recs += info.n_author_recs;
+        rec.genders.merge_from(&info.genders);
+        rec.gender_sources |= info.gender_sources;
+    }
+    let gender = info.map(|i| i.genders.resolve(prefs)).unwrap_or_default();
+    rec.authors.push(BookAuthor {
+        name,
+        position,
+        category: AuthorCategory::of(&gender),
+        gender,
+    });
+}
+
 /// Read cluster author names and resolve them to gender information.
-pub fn read_resolve(path: &Path, authors: &AuthorTable) -> Result<ClusterTable> {
+///
+/// Names without author records fall back to their surname-and-initials key,
+/// which matches if the name index was built with `index-names --initials`.
+pub fn read_resolve(path: &Path, authors: &AuthorTable, prefs: &[String]) -> Result<ClusterTable> {
     let timer = Timer::new();
     info!("reading cluster authors from {}", path.display());
     let iter = scan_parquet_file(path)?;
@@ -41,16 +140,108 @@ pub fn read_resolve(path: &Path, authors: &AuthorTable) -> Result<ClusterTable>
 
     for row in pb.wrap_iter(iter) {
         let row: ClusterAuthor = row?;
-        let rec = table.entry(row.cluster).or_default();
-        rec.n_book_authors += 1;
-        if let Some(info) = authors.get(row.author_name.as_str()) {
-            rec.n_author_recs += info.n_author_recs;
+        let info = authors
+            .get(row.author_name.as_str())
+            .or_else(|| initials_key(&row.author_name).and_then(|key| authors.get(key.as_str())));
+        add_author(
+            &mut table,
+            row.cluster,
+            row.author_name,
+            row.position,
+            info,
+            prefs,
+        );
+    }
+
+    info!(
+        "scanned genders for {} clusters in {}",
+        table.len(),
+        timer.human_elapsed()
+    );
+
+    Ok(table)
+}
+
+/// Collect the gender records of each author cluster's authority records.
+///
+/// `authorities` lists the namespaces of the author graph nodes that count as
+/// author records (VIAF records and/or Wikidata items).
+fn author_cluster_genders(
+    cluster_path: &Path,
+    nodes: &NodeTable,
+    authorities: &[AuthorNS],
+) -> Result<HashMap<i64, AuthorInfo>> {
+    info!("reading author clusters from {}", cluster_path.display());
+    let df = scan_df_parquet(cluster_path)?
+        .select([col("author_code"), col("author_cluster")])
+        .collect()?;
+    let codes = df.column("author_code")?.i64()?;
+    let clusters = df.column("author_cluster")?.i64()?;
+
+    let mut table: HashMap<i64, AuthorInfo> = HashMap::new();
+    for (code, cluster) in codes.into_iter().zip(clusters) {
+        let (Some(code), Some(cluster)) = (code, cluster) else {
+            continue;
+        };
+        if !AuthorNS::of_code(code).map_or(false, |ns| authorities.contains(&ns)) {
+            continue;
+        }
+        let rec = table.entry(cluster).or_default();
+        rec.n_author_recs += 1;
+        if let Some(info) = nodes.get(&code) {
             rec.genders.merge_from(&info.genders);
+            rec.gender_sources |= info.gender_sources;
         }
     }
+    info!("found author records for {} author clusters", table.len());
+
+    Ok(table)
+}
+
+/// Read book cluster authors from the author clusters and resolve their genders.
+///
+/// Each author cluster attached to a book cluster is one book author, with the
+/// gender records of all the authority records in that author cluster, instead
+/// of the records whose names match.
+pub fn read_resolve_clusters(
+    path: &Path,
+    cluster_path: &Path,
+    nodes: &NodeTable,
+    authorities: &[AuthorNS],
+    prefs: &[String],
+) -> Result<ClusterTable> {
+    let timer = Timer::new();
+    let ac_genders = author_cluster_genders(cluster_path, nodes, authorities)?;
+
+    info!("reading book author clusters from {}", path.display());
+    let df = scan_df_parquet(path)?
+        .select([
+            col("cluster"),
+            col("author_cluster"),
+            col("position").cast(DataType::Int16),
+        ])
+        .collect()?;
+    let clusters = df.column("cluster")?.i32()?;
+    let authors = df.column("author_cluster")?.i64()?;
+    let positions = df.column("position")?.i16()?;
+
+    let mut table = ClusterTable::new();
+    for ((cluster, author), position) in clusters.into_iter().zip(authors).zip(positions) {
+        let (Some(cluster), Some(author)) = (cluster, author) else {
+            continue;
+        };
+        add_author(
+            &mut table,
+            cluster,
+            AuthorNS::label(author),
+            position.unwrap_or_default(),
+            ac_genders.get(&author),
+            prefs,
+        );
+    }
 
     info!(
-        "scanned genders for {} clusters in {}",
+        "resolved author cluster genders for {} clusters in {}",
         table.len(),
         timer.human_elapsed()
     );
@@ -75,3 +266,59 @@ pub fn all_clusters<P: AsRef<Path>>(path: P) -> Result<Vec<i32>> {
 
     Ok(ids.into_iter().filter_map(identity).collect())
 }
+
+#[test]
+fn test_read_resolve_clusters() {
+    use crate::arrow::save_df_parquet;
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let ac_path = dir.path().join("author-clusters.parquet");
+    let bac_path = dir.path().join("book-author-clusters.parquet");
+
+    let ol = AuthorNS::OlAuthor.code(5);
+    let viaf = AuthorNS::Viaf.code(17);
+    let wd = AuthorNS::Wikidata.code(36322);
+    let lonely = AuthorNS::OlAuthor.code(9);
+    save_df_parquet(
+        df!(
+            "author_code" => [ol, viaf, wd, lonely],
+            "author_cluster" => [ol, ol, ol, lonely],
+        )
+        .unwrap(),
+        &ac_path,
+    )
+    .expect("save");
+    save_df_parquet(
+        df!(
+            "cluster" => [100i32, 100, 200],
+            "author_cluster" => [ol, lonely, ol],
+            "position" => [0i16, 1, 0],
+        )
+        .unwrap(),
+        &bac_path,
+    )
+    .expect("save");
+
+    let mut bag = GenderBag::default();
+    bag.add_from(Some(Arc::from("LC")), Gender::Female);
+    let nodes = NodeTable::from([(
+        viaf,
+        AuthorInfo {
+            n_author_recs: 1,
+            genders: bag,
+            gender_sources: super::authors::SRC_VIAF,
+        },
+    )]);
+
+    let table = read_resolve_clusters(&bac_path, &ac_path, &nodes, &[AuthorNS::Viaf], &[])
+        .expect("resolve");
+    let stats = &table[&100];
+    assert_eq!(stats.n_book_authors, 2);
+    // the Wikidata item is not an author record when only using VIAF
+    assert_eq!(stats.n_author_recs, 1);
+    assert_eq!(stats.count(AuthorCategory::Female), 1);
+    assert_eq!(stats.count(AuthorCategory::Unresolved), 1);
+    assert_eq!(stats.first_author().unwrap().name, "OL-A:5");
+    assert_eq!(table[&200].count(AuthorCategory::Female), 1);
+}
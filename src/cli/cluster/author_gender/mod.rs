@@ -5,23 +5,62 @@
 //!
 //! We use a lot of left joins so that we can compute statistics across
 //! the integration pipeline.
+//!
+//! Besides a single gender label for each cluster, chosen by a [LabelStrategy],
+//! the output counts the cluster's authors in each gender category so analyses
+//! can apply their own multi-author rules.
+//!
+//! Book authors are matched to author records by name, or, with
+//! `--author-clusters`, through the author clusters built by `link-authors`.
 use std::path::{Path, PathBuf};
 
 use parquet_derive::ParquetRecordWriter;
+use parse_display::{Display, FromStr};
 use serde::{Deserialize, Serialize};
 
 use crate::arrow::*;
+use crate::cli::link_authors::{AuthorNS, CLUSTER_PATH};
 use crate::ids::codes::*;
 use crate::prelude::*;
 
+use clusters::{AuthorCategory, ClusterStats};
+
 mod authors;
 mod clusters;
 
-// #[derive(Display, FromStr, Debug)]
-// #[display(style="lowercase")]
-// enum GenderSource {
-//   VIAF,
-// }
+/// Sources of author gender information.
+#[derive(Display, FromStr, Debug, Clone, Copy, PartialEq, Eq)]
+#[display(style = "lowercase")]
+enum GenderSource {
+    Viaf,
+    Wikidata,
+    Both,
+}
+
+impl GenderSource {
+    /// Get the author graph namespaces of this source's author records.
+    fn namespaces(self) -> &'static [AuthorNS] {
+        match self {
+            GenderSource::Viaf => &[AuthorNS::Viaf],
+            GenderSource::Wikidata => &[AuthorNS::Wikidata],
+            GenderSource::Both => &[AuthorNS::Viaf, AuthorNS::Wikidata],
+        }
+    }
+}
+
+/// Strategies for labeling a cluster with a single author gender.
+#[derive(Display, FromStr, Debug, Clone, Copy, PartialEq, Eq)]
+#[display(style = "kebab-case")]
+enum LabelStrategy {
+    /// Merge the gender records of all authors; disagreement is ambiguous.
+    Merged,
+    /// Use the gender of the first author.
+    FirstAuthor,
+    /// Label clusters by whether any author is a woman.
+    AnyFemale,
+    /// Label clusters by the majority gender of their resolved authors.
+    Majority,
+}
 
 #[derive(Args, Debug)]
 #[command(name = "extract-author-genders")]
@@ -32,8 +71,37 @@ pub struct AuthorGender {
     output: PathBuf,
 
     /// Specify the cluster-author file.
-    #[arg(short = 'A', long = "cluster-authors")]
-    author_file: PathBuf,
+    #[arg(
+        short = 'A',
+        long = "cluster-authors",
+        required_unless_present = "AUTHOR_CLUSTERS",
+        conflicts_with = "AUTHOR_CLUSTERS"
+    )]
+    author_file: Option<PathBuf>,
+
+    /// Resolve authors through the book author clusters in FILE instead of by name.
+    #[arg(long = "author-clusters", name = "AUTHOR_CLUSTERS")]
+    author_clusters: Option<PathBuf>,
+
+    /// Select the author gender source (viaf, wikidata, or both).
+    #[arg(long = "source", name = "SOURCE", default_value = "viaf")]
+    source: GenderSource,
+
+    /// Select the cluster labeling strategy (merged, first-author, any-female, or majority).
+    #[arg(long = "label", name = "STRATEGY", default_value = "merged")]
+    label: LabelStrategy,
+
+    /// Write the gender of each cluster author to FILE.
+    #[arg(long = "author-output", name = "FILE")]
+    author_output: Option<PathBuf>,
+
+    /// Resolve conflicting genders by preferring SOURCES (e.g. `LC,DNB`), in order.
+    #[arg(long = "prefer-sources", name = "SOURCES", value_delimiter = ',')]
+    prefer_sources: Vec<String>,
+
+    /// Write the gender assertions of each author record to FILE.
+    #[arg(long = "gender-detail", name = "DETAIL_FILE")]
+    gender_detail: Option<PathBuf>,
 }
 
 /// Record format for saving gender information.
@@ -41,14 +109,70 @@ pub struct AuthorGender {
 struct ClusterGenderInfo {
     cluster: i32,
     gender: String,
+    gender_source: Option<String>,
+    n_authors: u32,
+    n_female: u32,
+    n_male: u32,
+    n_ambiguous: u32,
+    n_other_gender: u32,
+    n_unresolved: u32,
+    prop_female: Option<f32>,
+    prop_male: Option<f32>,
+    first_author_gender: Option<String>,
+}
+
+/// Record format for saving the gender of each cluster author.
+#[derive(ParquetRecordWriter)]
+struct ClusterAuthorGender {
+    cluster: i32,
+    position: i16,
+    author_name: String,
+    gender: String,
 }
 
-fn save_genders(clusters: Vec<i32>, genders: clusters::ClusterTable, outf: &Path) -> Result<()> {
+impl LabelStrategy {
+    /// Label a cluster that has at least one author with gender records.
+    fn label(&self, stats: &ClusterStats) -> String {
+        let n_female = stats.count(AuthorCategory::Female);
+        let n_male = stats.count(AuthorCategory::Male);
+        let n_resolved = stats.authors.len() as u32 - stats.count(AuthorCategory::Unresolved);
+        match self {
+            LabelStrategy::Merged => stats.merged_gender().to_string(),
+            LabelStrategy::FirstAuthor => stats
+                .first_author()
+                .map(|a| a.category.label())
+                .unwrap_or("unresolved")
+                .to_string(),
+            LabelStrategy::AnyFemale if n_female > 0 => "any-female".to_string(),
+            LabelStrategy::AnyFemale => "no-female".to_string(),
+            LabelStrategy::Majority if n_female * 2 > n_resolved => "majority-female".to_string(),
+            LabelStrategy::Majority if n_male * 2 > n_resolved => "majority-male".to_string(),
+            LabelStrategy::Majority => "mixed".to_string(),
+        }
+    }
+}
+
+/// Compute the share of a cluster's resolved authors with a count.
+fn proportion(count: u32, n_resolved: u32) -> Option<f32> {
+    if n_resolved > 0 {
+        Some(count as f32 / n_resolved as f32)
+    } else {
+        None
+    }
+}
+
+fn save_genders(
+    clusters: Vec<i32>,
+    genders: &clusters::ClusterTable,
+    strategy: LabelStrategy,
+    outf: &Path,
+) -> Result<()> {
     info!("writing cluster genders to {}", outf.display());
     let mut out = TableWriter::open(outf)?;
 
     for cluster in clusters {
         let mut gender = "no-book-author".to_owned();
+        let mut gender_source = None;
         if NS_ISBN.from_code(cluster).is_some() {
             gender = "no-book".to_owned();
         }
@@ -62,10 +186,73 @@ fn save_genders(clusters: Vec<i32>, genders: clusters::ClusterTable, outf: &Path
             } else if stats.genders.is_empty() {
                 gender = "no-gender".to_owned()
             } else {
-                gender = stats.genders.to_gender().to_string()
+                gender = strategy.label(stats);
+                gender_source = authors::source_label(stats.gender_sources).map(str::to_string);
             };
         }
-        out.write_object(ClusterGenderInfo { cluster, gender })?;
+        let rec = match genders.get(&cluster) {
+            Some(stats) => {
+                let n_female = stats.count(AuthorCategory::Female);
+                let n_male = stats.count(AuthorCategory::Male);
+                let n_unresolved = stats.count(AuthorCategory::Unresolved);
+                let n_resolved = stats.authors.len() as u32 - n_unresolved;
+                ClusterGenderInfo {
+                    cluster,
+                    gender,
+                    gender_source,
+                    n_authors: stats.authors.len() as u32,
+                    n_female,
+                    n_male,
+                    n_ambiguous: stats.count(AuthorCategory::Ambiguous),
+                    n_other_gender: stats.count(AuthorCategory::Other),
+                    n_unresolved,
+                    prop_female: proportion(n_female, n_resolved),
+                    prop_male: proportion(n_male, n_resolved),
+                    first_author_gender: stats
+                        .first_author()
+                        .map(|a| a.category.label().to_string()),
+                }
+            }
+            None => ClusterGenderInfo {
+                cluster,
+                gender,
+                gender_source,
+                n_authors: 0,
+                n_female: 0,
+                n_male: 0,
+                n_ambiguous: 0,
+                n_other_gender: 0,
+                n_unresolved: 0,
+                prop_female: None,
+                prop_male: None,
+                first_author_gender: None,
+            },
+        };
+        out.write_object(rec)?;
+    }
+
+    out.finish()?;
+
+    Ok(())
+}
+
+fn save_author_genders(genders: &clusters::ClusterTable, outf: &Path) -> Result<()> {
+    info!("writing cluster author genders to {}", outf.display());
+    let mut out = TableWriter::open(outf)?;
+
+    let mut clusters: Vec<_> = genders.keys().copied().collect();
+    clusters.sort_unstable();
+    for cluster in clusters {
+        let mut authors: Vec<_> = genders[&cluster].authors.iter().collect();
+        authors.sort_by_key(|a| (a.position, &a.name));
+        for author in authors {
+            out.write_object(ClusterAuthorGender {
+                cluster,
+                position: author.position,
+                author_name: author.name.clone(),
+                gender: author.category.label().to_string(),
+            })?;
+        }
     }
 
     out.finish()?;
@@ -76,10 +263,95 @@ fn save_genders(clusters: Vec<i32>, genders: clusters::ClusterTable, outf: &Path
 impl Command for AuthorGender {
     fn exec(&self) -> Result<()> {
         let clusters = clusters::all_clusters("book-links/cluster-stats.parquet")?;
-        let name_genders = authors::viaf_author_table()?;
-        let cluster_genders = clusters::read_resolve(&self.author_file, &name_genders)?;
-        save_genders(clusters, cluster_genders, self.output.as_ref())?;
+        let prefs = &self.prefer_sources;
+        let mut detail = match &self.gender_detail {
+            Some(path) => Some(authors::DetailWriter::open(path, prefs)?),
+            None => None,
+        };
+        let cluster_genders = if let Some(path) = &self.author_clusters {
+            let nodes = match self.source {
+                GenderSource::Viaf => authors::viaf_node_table(detail.as_mut())?,
+                GenderSource::Wikidata => authors::wikidata_node_table(detail.as_mut())?,
+                GenderSource::Both => {
+                    let mut table = authors::viaf_node_table(detail.as_mut())?;
+                    table.extend(authors::wikidata_node_table(detail.as_mut())?);
+                    table
+                }
+            };
+            let namespaces = self.source.namespaces();
+            clusters::read_resolve_clusters(path, CLUSTER_PATH.as_ref(), &nodes, namespaces, prefs)?
+        } else {
+            let name_genders = match self.source {
+                GenderSource::Viaf => authors::viaf_author_table(detail.as_mut())?,
+                GenderSource::Wikidata => authors::wikidata_author_table(detail.as_mut())?,
+                GenderSource::Both => {
+                    let mut table = authors::viaf_author_table(detail.as_mut())?;
+                    let wd_table = authors::wikidata_author_table(detail.as_mut())?;
+                    authors::merge_tables(&mut table, wd_table);
+                    table
+                }
+            };
+            let path = self
+                .author_file
+                .as_ref()
+                .ok_or_else(|| anyhow!("no cluster author file specified"))?;
+            clusters::read_resolve(path, &name_genders, prefs)?
+        };
+        if let Some(detail) = detail {
+            detail.finish()?;
+        }
+        save_genders(clusters, &cluster_genders, self.label, self.output.as_ref())?;
+        if let Some(path) = &self.author_output {
+            save_author_genders(&cluster_genders, path)?;
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+fn test_stats(cats: &[AuthorCategory]) -> ClusterStats {
+    use crate::gender::Gender;
+    let mut stats = ClusterStats::default();
+    for (i, cat) in cats.iter().enumerate() {
+        let gender = match cat {
+            AuthorCategory::Female => Gender::Female,
+            AuthorCategory::Male => Gender::Male,
+            _ => Gender::Unknown,
+        };
+        if gender != Gender::Unknown {
+            stats.genders.add(gender.clone());
+        }
+        stats.authors.push(clusters::BookAuthor {
+            name: format!("author {}", i),
+            position: i as i16,
+            gender,
+            category: *cat,
+        });
+    }
+    stats
+}
+
+#[test]
+fn test_label_strategies() {
+    use AuthorCategory::*;
+    let stats = test_stats(&[Male, Female, Female, Unresolved]);
+    assert_eq!(LabelStrategy::Merged.label(&stats), "ambiguous");
+    assert_eq!(LabelStrategy::FirstAuthor.label(&stats), "male");
+    assert_eq!(LabelStrategy::AnyFemale.label(&stats), "any-female");
+    assert_eq!(LabelStrategy::Majority.label(&stats), "majority-female");
+    assert_eq!(stats.count(Unresolved), 1);
+
+    let stats = test_stats(&[Male, Female]);
+    assert_eq!(LabelStrategy::Majority.label(&stats), "mixed");
+    let stats = test_stats(&[Unresolved, Male]);
+    assert_eq!(LabelStrategy::FirstAuthor.label(&stats), "unresolved");
+    assert_eq!(LabelStrategy::AnyFemale.label(&stats), "no-female");
+    assert_eq!(LabelStrategy::Majority.label(&stats), "majority-male");
+}
+
+#[test]
+fn test_proportion() {
+    assert_eq!(proportion(1, 4), Some(0.25));
+    assert_eq!(proportion(0, 0), None);
+}
@@ -68,6 +68,7 @@ fn scan_openlib(first_only: bool) -> Result<LazyFrame> {
         col("name")
             .alias("author_name")
             .map(udf_clean_name, GetOutput::from_type(DataType::String)),
+        col("pos").alias("position"),
     ]);
 
     Ok(authors)
@@ -106,6 +107,7 @@ fn scan_loc(first_only: bool) -> Result<LazyFrame> {
     let authors = linked.select(vec![
         col("cluster"),
         col("author_name").map(udf_clean_name, GetOutput::from_type(DataType::String)),
+        lit(0i16).alias("position"),
     ]);
 
     Ok(authors)
@@ -140,7 +142,10 @@ impl Command for ClusterAuthors {
                 .and(col("author_name").neq("".lit())),
         );
 
-        let authors = authors.unique(None, UniqueKeepStrategy::First);
+        // an author may appear at different positions in different records
+        let authors = authors
+            .group_by([col("cluster"), col("author_name")])
+            .agg([col("position").min()]);
 
         debug!("plan: {}", authors.describe_plan());
 
@@ -0,0 +1,496 @@
+//! Extract consensus descriptive metadata for book clusters.
+//!
+//! Each cluster combines records from several sources that often disagree on
+//! details such as the title or author spelling.  This command picks a single
+//! representative value for each field by voting: records are grouped by a
+//! normalized key (normalized title, author name key, or language code), the key
+//! with the most records wins, and ties are broken by [source priority][SOURCE_PRIORITY].
+//! The representative value is taken from the highest-priority record with the
+//! winning key, and the source of that record is recorded alongside the value.
+use std::path::PathBuf;
+
+use super::records::*;
+use crate::arrow::dfext::udf_clean_name;
+use crate::ids::codes::*;
+use crate::prelude::*;
+use polars::prelude::*;
+
+#[derive(Args, Debug)]
+#[command(name = "extract-info")]
+/// Extract representative metadata for each book cluster.
+pub struct ExtractInfo {
+    /// Write cluster information to FILE.
+    #[arg(
+        short = 'o',
+        long = "output",
+        name = "FILE",
+        default_value = "book-links/cluster-info.parquet"
+    )]
+    output: PathBuf,
+}
+
+/// Characters trimmed from the end of titles (MARC titles end with ISBD punctuation).
+const TITLE_TRAILING: &[char] = &['/', ':', ';', ',', '=', '.'];
+
+/// Parse the publication year (date 1) from a MARC 008 field.
+fn parse_008_year(field: &str) -> Option<i16> {
+    let date = field.get(7..11)?;
+    if date.bytes().all(|b| b.is_ascii_digit()) {
+        date.parse().ok().filter(|y| *y > 0)
+    } else {
+        None
+    }
+}
+
+/// Parse the language code from a MARC 008 field.
+fn parse_008_language(field: &str) -> Option<&str> {
+    let lang = field.get(35..38)?;
+    if lang.bytes().all(|b| b.is_ascii_alphabetic()) {
+        Some(lang)
+    } else {
+        None
+    }
+}
+
+/// Parse the form of item (position 23 for books) from a MARC 008 field.
+fn parse_008_format(field: &str) -> Option<&str> {
+    let form = field.get(23..24)?;
+    Some(match form {
+        " " | "r" => "print",
+        "d" => "large_print",
+        "o" | "q" | "s" => "ebook",
+        _ => "other_format",
+    })
+}
+
+/// Normalize a language code to a MARC language code.
+///
+/// LOC and OpenLibrary use MARC codes; GoodReads mostly does too, but uses locale
+/// codes (`en-US`, `en-GB`) for English.
+fn normalize_language(code: &str) -> Option<String> {
+    let code = code.trim().to_lowercase();
+    if code == "en" || code.starts_with("en-") {
+        Some("eng".into())
+    } else if code.len() == 3 && code.bytes().all(|b| b.is_ascii_alphabetic()) {
+        Some(code)
+    } else {
+        None
+    }
+}
+
+/// Classify a format name from OpenLibrary or GoodReads (e.g. “Mass Market
+/// Paperback” or “Kindle Edition”).
+fn parse_format_name(name: &str) -> Option<&str> {
+    let name = name.trim().to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| name.contains(w));
+    if name.is_empty() {
+        None
+    } else if has(&["ebook", "e-book", "kindle", "electronic", "epub", "nook"]) {
+        Some("ebook")
+    } else if has(&["large print", "large type"]) {
+        Some("large_print")
+    } else if has(&[
+        "paperback",
+        "hardcover",
+        "hardback",
+        "softcover",
+        "mass market",
+        "board book",
+        "library binding",
+        "print",
+    ]) {
+        Some("print")
+    } else {
+        Some("other_format")
+    }
+}
+
+/// Apply a string parser to a column.
+fn map_str<F>(parse: F) -> impl Fn(Series) -> PolarsResult<Option<Series>> + Clone
+where
+    F: Fn(&str) -> Option<&str> + Clone,
+{
+    move |col| {
+        let col = col.str()?;
+        let res: StringChunked = col.into_iter().map(|f| f.and_then(&parse)).collect();
+        Ok(Some(res.into_series()))
+    }
+}
+
+/// Extract publication years from a column of 008 fields.
+fn udf_008_year(col: Series) -> PolarsResult<Option<Series>> {
+    let col = col.str()?;
+    let res: Int16Chunked = col
+        .into_iter()
+        .map(|f| f.and_then(parse_008_year))
+        .collect();
+    Ok(Some(res.into_series()))
+}
+
+/// Trim trailing punctuation from a column of titles.
+fn udf_trim_title(col: Series) -> PolarsResult<Option<Series>> {
+    let col = col.str()?;
+    let res: StringChunked = col
+        .into_iter()
+        .map(|t| {
+            t.map(|t| {
+                t.trim_end_matches(|c: char| c.is_whitespace() || TITLE_TRAILING.contains(&c))
+            })
+        })
+        .collect();
+    Ok(Some(res.into_series()))
+}
+
+/// Rebuild string columns that contain nulls.
+///
+/// The Polars Parquet encoder miscounts the bytes of string arrays whose null
+/// slots still reference data, as the left joins in [cluster_info] produce, so
+/// we copy such columns into fresh arrays before writing.
+fn rebuild_null_strings(df: &mut DataFrame) -> Result<()> {
+    let names: Vec<String> = df
+        .get_columns()
+        .iter()
+        .filter(|c| c.dtype() == &DataType::String && c.null_count() > 0)
+        .map(|c| c.name().to_string())
+        .collect();
+    for name in names {
+        let strs: StringChunked = df.column(&name)?.str()?.into_iter().collect();
+        df.with_column(strs.into_series().with_name(&name))?;
+    }
+    Ok(())
+}
+
+/// Choose a representative value for each cluster by voting on a key.
+///
+/// The input frame must have `cluster` and `book_code` columns; the output has
+/// `cluster`, `{name}`, and `{name}_source` columns.
+fn vote(records: LazyFrame, key: Expr, value: Expr, name: &str) -> LazyFrame {
+    let records = records
+        .select([
+            col("cluster"),
+            col("book_code"),
+            key.alias("key"),
+            value.alias("value"),
+            source_priority().alias("priority"),
+            source_name().alias("source"),
+        ])
+        .filter(col("key").is_not_null().and(col("value").is_not_null()));
+
+    let keys = records.group_by([col("cluster"), col("key")]).agg([
+        col("value").count().alias("votes"),
+        col("priority").min(),
+        col("value")
+            .sort_by([col("priority"), col("book_code")], [false, false])
+            .first()
+            .alias("value"),
+        col("source")
+            .sort_by([col("priority"), col("book_code")], [false, false])
+            .first()
+            .alias("source"),
+    ]);
+
+    let order = [col("votes"), col("priority"), col("key")];
+    let desc = [true, false, false];
+    keys.group_by([col("cluster")]).agg([
+        col("value")
+            .sort_by(order.clone(), desc)
+            .first()
+            .alias(name),
+        col("source")
+            .sort_by(order, desc)
+            .first()
+            .alias(&format!("{}_source", name)),
+    ])
+}
+
+/// Scan the LOC 008 fields, with their clusters.
+fn scan_008(nodes: LazyFrame) -> Result<LazyFrame> {
+    Ok(scan_loc_008()?.join(
+        nodes,
+        [col("book_code")],
+        [col("book_code")],
+        JoinType::Inner.into(),
+    ))
+}
+
+/// Compute the earliest publication year of each cluster.
+fn first_years(cfg: &Config, nodes: LazyFrame) -> Result<LazyFrame> {
+    let mut frames = vec![scan_008(nodes.clone())?.select([
+        col("cluster"),
+        col("book_code"),
+        col("contents")
+            .map(udf_008_year, GetOutput::from_type(DataType::Int16))
+            .alias("year"),
+    ])];
+    if cfg.goodreads.enabled {
+        for (path, id, ns) in [
+            ("goodreads/gr-book-info.parquet", "book_id", NS_GR_BOOK),
+            ("goodreads/gr-work-info.parquet", "work_id", NS_GR_WORK),
+        ] {
+            let df = scan_df_parquet(path)?
+                .select([
+                    book_code(id, ns).alias("book_code"),
+                    col("pub_year").cast(DataType::Int16).alias("year"),
+                ])
+                .join(
+                    nodes.clone(),
+                    [col("book_code")],
+                    [col("book_code")],
+                    JoinType::Inner.into(),
+                );
+            frames.push(df.select([col("cluster"), col("book_code"), col("year")]));
+        }
+    }
+
+    let years = concat(frames, UnionArgs::default())?
+        .filter(col("year").is_not_null().and(col("year").gt(lit(0i16))))
+        .select([
+            col("cluster"),
+            col("year"),
+            source_priority().alias("priority"),
+            source_name().alias("source"),
+        ]);
+    Ok(years.group_by([col("cluster")]).agg([
+        col("year").min().alias("first_year"),
+        col("source")
+            .sort_by([col("year"), col("priority")], [false, false])
+            .first()
+            .alias("first_year_source"),
+    ]))
+}
+
+/// Normalize a column of language codes.
+fn udf_language(col: Series) -> PolarsResult<Option<Series>> {
+    let col = col.str()?;
+    let res: StringChunked = col
+        .into_iter()
+        .map(|l| l.and_then(normalize_language))
+        .collect();
+    Ok(Some(res.into_series()))
+}
+
+/// Scan a string column from a source table, keyed by book code.
+fn scan_record_field(
+    path: &str,
+    id: &str,
+    ns: NS<'_>,
+    field: &str,
+    name: &str,
+) -> Result<LazyFrame> {
+    let df = scan_df_parquet(path)?;
+    Ok(df.select([book_code(id, ns).alias("book_code"), col(field).alias(name)]))
+}
+
+/// Scan the record languages (from LOC, OpenLibrary, and GoodReads), with their clusters.
+fn scan_languages(cfg: &Config, nodes: LazyFrame) -> Result<LazyFrame> {
+    let mut frames = vec![
+        scan_loc_008()?.select([
+            col("book_code"),
+            col("contents")
+                .map(
+                    map_str(parse_008_language),
+                    GetOutput::from_type(DataType::String),
+                )
+                .alias("language"),
+        ]),
+        scan_record_field(
+            "openlibrary/editions.parquet",
+            "id",
+            NS_EDITION,
+            "language",
+            "language",
+        )?,
+    ];
+    if cfg.goodreads.enabled {
+        frames.push(scan_record_field(
+            "goodreads/gr-book-info.parquet",
+            "book_id",
+            NS_GR_BOOK,
+            "language_code",
+            "language",
+        )?);
+    }
+
+    let langs = concat(frames, UnionArgs::default())?.join(
+        nodes,
+        [col("book_code")],
+        [col("book_code")],
+        JoinType::Inner.into(),
+    );
+    Ok(langs.select([
+        col("cluster"),
+        col("book_code"),
+        col("language").map(udf_language, GetOutput::from_type(DataType::String)),
+    ]))
+}
+
+/// Count the record formats (from LOC, OpenLibrary, and GoodReads) in each cluster.
+///
+/// LOC formats come from the 008 field; OpenLibrary and GoodReads formats are
+/// classified from their format names.
+fn format_counts(cfg: &Config, nodes: LazyFrame) -> Result<LazyFrame> {
+    let parse_name = map_str(parse_format_name);
+    let mut frames = vec![
+        scan_loc_008()?.select([
+            col("book_code"),
+            col("contents")
+                .map(
+                    map_str(parse_008_format),
+                    GetOutput::from_type(DataType::String),
+                )
+                .alias("format"),
+        ]),
+        scan_record_field(
+            "openlibrary/editions.parquet",
+            "id",
+            NS_EDITION,
+            "physical_format",
+            "format",
+        )?
+        .select([
+            col("book_code"),
+            col("format").map(parse_name.clone(), GetOutput::from_type(DataType::String)),
+        ]),
+    ];
+    if cfg.goodreads.enabled {
+        frames.push(
+            scan_record_field(
+                "goodreads/gr-book-info.parquet",
+                "book_id",
+                NS_GR_BOOK,
+                "format",
+                "format",
+            )?
+            .select([
+                col("book_code"),
+                col("format").map(parse_name, GetOutput::from_type(DataType::String)),
+            ]),
+        );
+    }
+
+    let formats = concat(frames, UnionArgs::default())?.join(
+        nodes,
+        [col("book_code")],
+        [col("book_code")],
+        JoinType::Inner.into(),
+    );
+    let counts = ["print", "ebook", "large_print", "other_format"].map(|f| {
+        col("format")
+            .eq(lit(f))
+            .cast(DataType::UInt32)
+            .sum()
+            .alias(&format!("n_{}", f))
+    });
+    Ok(formats.group_by([col("cluster")]).agg(counts))
+}
+
+/// Build the cluster information table.
+fn cluster_info(cfg: &Config) -> Result<LazyFrame> {
+    let nodes = scan_cluster_nodes()?;
+
+    let stats = scan_df_parquet("book-links/cluster-stats.parquet")?.select([
+        col("cluster"),
+        col("n_isbns"),
+        (col("n_loc_recs") + col("n_ol_editions") + col("n_gr_books")).alias("n_editions"),
+    ]);
+
+    info!("voting on cluster titles");
+    let titles = scan_titles(cfg)?.join(
+        nodes.clone(),
+        [col("book_code")],
+        [col("book_code")],
+        JoinType::Inner.into(),
+    );
+    let titles = vote(
+        titles,
+        col("title").map(udf_normalize_title, GetOutput::from_type(DataType::String)),
+        col("title").map(udf_trim_title, GetOutput::from_type(DataType::String)),
+        "title",
+    );
+
+    info!("voting on cluster first authors");
+    let authors = scan_first_authors(cfg)?.join(
+        nodes.clone(),
+        [col("book_code")],
+        [col("book_code")],
+        JoinType::Inner.into(),
+    );
+    let authors = vote(
+        authors,
+        col("author_name").map(udf_name_key, GetOutput::from_type(DataType::String)),
+        col("author_name").map(udf_clean_name, GetOutput::from_type(DataType::String)),
+        "first_author",
+    );
+
+    info!("voting on cluster languages");
+    let langs = scan_languages(cfg, nodes.clone())?;
+    let langs = vote(langs, col("language"), col("language"), "language");
+
+    let years = first_years(cfg, nodes.clone())?;
+    let formats = format_counts(cfg, nodes)?;
+
+    let info = stats
+        .left_join(titles, col("cluster"), col("cluster"))
+        .left_join(authors, col("cluster"), col("cluster"))
+        .left_join(years, col("cluster"), col("cluster"))
+        .left_join(langs, col("cluster"), col("cluster"))
+        .left_join(formats, col("cluster"), col("cluster"))
+        .with_columns(
+            ["n_print", "n_ebook", "n_large_print", "n_other_format"]
+                .map(|c| col(c).fill_null(lit(0u32))),
+        )
+        .sort("cluster", SortOptions::default());
+    Ok(info)
+}
+
+impl Command for ExtractInfo {
+    fn exec(&self) -> Result<()> {
+        let cfg = load_config()?;
+        let info = cluster_info(&cfg)?;
+        info!("collecting cluster information");
+        let mut info = info.collect()?;
+        rebuild_null_strings(&mut info)?;
+        info!("saving {} clusters to {:?}", info.height(), self.output);
+        save_df_parquet(info, &self.output)?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_008() {
+    let field = "850423s1984    nyu           000 1 eng  ";
+    assert_eq!(parse_008_year(field), Some(1984));
+    assert_eq!(parse_008_language(field), Some("eng"));
+    assert_eq!(parse_008_format(field), Some("print"));
+}
+
+#[test]
+fn test_parse_008_missing() {
+    let field = "850423nuuuu    nyu     o     000 1 |||  ";
+    assert_eq!(parse_008_year(field), None);
+    assert_eq!(parse_008_language(field), None);
+    assert_eq!(parse_008_format(field), Some("ebook"));
+    assert_eq!(parse_008_year("short"), None);
+}
+
+#[test]
+fn test_normalize_language() {
+    assert_eq!(normalize_language("eng").as_deref(), Some("eng"));
+    assert_eq!(normalize_language("en-US").as_deref(), Some("eng"));
+    assert_eq!(normalize_language("FRE").as_deref(), Some("fre"));
+    assert_eq!(normalize_language(""), None);
+    assert_eq!(normalize_language("--"), None);
+}
+
+#[test]
+fn test_parse_format_name() {
+    assert_eq!(parse_format_name("Mass Market Paperback"), Some("print"));
+    assert_eq!(parse_format_name("Kindle Edition"), Some("ebook"));
+    assert_eq!(
+        parse_format_name("Large Print Hardcover"),
+        Some("large_print")
+    );
+    assert_eq!(parse_format_name("Audio CD"), Some("other_format"));
+    assert_eq!(parse_format_name(" "), None);
+}
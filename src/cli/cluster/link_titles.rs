@@ -0,0 +1,258 @@
+//! Propose links between clusters with matching titles and authors.
+//!
+//! Clusters are built from shared identifiers, so editions of the same work that
+//! no source links (e.g. a hardcover and an ebook) stay in separate clusters.  This
+//! command proposes candidate links between clusters whose first authors share a
+//! name variant and whose normalized titles are similar.  Titles must have the same
+//! volume numbers, so numbered volumes of a series are not linked.
+//!
+//! To keep the comparison tractable, clusters are only compared within blocks of
+//! the same author name key and title prefix, and oversized blocks are skipped.
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use parquet_derive::ParquetRecordWriter;
+
+use super::records::{scan_cluster_nodes, scan_titles, udf_name_key, udf_normalize_title};
+use crate::arrow::*;
+use crate::cleaning::titles::{title_similarity, volumes_match};
+use crate::prelude::*;
+use crate::util::logging::item_progress;
+use polars::prelude::*;
+
+#[derive(Args, Debug)]
+#[command(name = "link-titles")]
+/// Propose candidate links between clusters with similar titles and authors.
+pub struct LinkTitles {
+    /// Write candidate links to FILE.
+    #[arg(
+        short = 'o',
+        long = "output",
+        name = "FILE",
+        default_value = "book-links/title-candidate-links.parquet"
+    )]
+    output: PathBuf,
+
+    /// Read cluster first authors from FILE.
+    #[arg(
+        short = 'A',
+        long = "author-file",
+        name = "AUTHORS",
+        default_value = "book-links/cluster-first-authors.parquet"
+    )]
+    author_file: PathBuf,
+
+    /// Minimum title similarity for a candidate link.
+    #[arg(short = 't', long = "threshold", default_value = "0.9")]
+    threshold: f64,
+
+    /// Number of normalized title characters to include in the blocking key.
+    #[arg(long = "block-prefix", default_value = "2")]
+    block_prefix: usize,
+
+    /// Skip blocks with more than N titles.
+    #[arg(long = "max-block", name = "N", default_value = "1000")]
+    max_block: usize,
+}
+
+/// A candidate link between two clusters.
+#[derive(ParquetRecordWriter, Debug, Clone)]
+pub struct CandidateLink {
+    pub cluster: i32,
+    pub other_cluster: i32,
+    pub score: f32,
+    pub author: String,
+    pub title: String,
+    pub other_title: String,
+}
+
+impl LinkTitles {
+    /// Load the (author key, title key, cluster) triples, sorted for blocking.
+    fn load_keys(&self) -> Result<DataFrame> {
+        let cfg = load_config()?;
+        let nodes = scan_cluster_nodes()?;
+        let titles = scan_titles(&cfg)?
+            .join(
+                nodes,
+                [col("book_code")],
+                [col("book_code")],
+                JoinType::Inner.into(),
+            )
+            .select([
+                col("cluster"),
+                col("title").map(udf_normalize_title, GetOutput::from_type(DataType::String)),
+            ])
+            .filter(col("title").is_not_null())
+            .unique(None, UniqueKeepStrategy::Any);
+
+        info!("reading authors from {:?}", self.author_file);
+        let authors = scan_df_parquet(&self.author_file)?
+            .select([
+                col("cluster"),
+                col("author_name")
+                    .map(udf_name_key, GetOutput::from_type(DataType::String))
+                    .alias("author"),
+            ])
+            .filter(col("author").is_not_null())
+            .unique(None, UniqueKeepStrategy::Any);
+
+        let keys = titles
+            .join(
+                authors,
+                [col("cluster")],
+                [col("cluster")],
+                JoinType::Inner.into(),
+            )
+            .sort_by_exprs(
+                [col("author"), col("title"), col("cluster")],
+                [false, false, false],
+                false,
+                false,
+            );
+
+        info!("collecting title and author keys");
+        Ok(keys.collect()?)
+    }
+
+    /// Compare the titles within a block, adding candidate links.
+    fn compare_block(
+        &self,
+        author: &str,
+        block: &[(i32, &str)],
+        links: &mut HashMap<(i32, i32), CandidateLink>,
+    ) {
+        for (i, (c1, t1)) in block.iter().enumerate() {
+            for (c2, t2) in &block[i + 1..] {
+                if c1 == c2 || !volumes_match(t1, t2) {
+                    continue;
+                }
+                let score = title_similarity(t1, t2);
+                if score < self.threshold {
+                    continue;
+                }
+                let (a, b, ta, tb) = if c1 < c2 {
+                    (*c1, *c2, t1, t2)
+                } else {
+                    (*c2, *c1, t2, t1)
+                };
+                let link = CandidateLink {
+                    cluster: a,
+                    other_cluster: b,
+                    score: score as f32,
+                    author: author.to_string(),
+                    title: ta.to_string(),
+                    other_title: tb.to_string(),
+                };
+                match links.get(&(a, b)) {
+                    Some(l) if l.score >= link.score => (),
+                    _ => {
+                        links.insert((a, b), link);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Compute the blocking key for a title.
+fn block_key(title: &str, prefix: usize) -> &str {
+    match title.char_indices().nth(prefix) {
+        Some((i, _)) => &title[..i],
+        None => title,
+    }
+}
+
+impl Command for LinkTitles {
+    fn exec(&self) -> Result<()> {
+        let keys = self.load_keys()?;
+        let clusters = keys.column("cluster")?.i32()?;
+        let titles = keys.column("title")?.str()?;
+        let authors = keys.column("author")?.str()?;
+
+        info!("comparing titles in {} keyed rows", keys.height());
+        let pb = item_progress(keys.height(), "titles");
+        let mut links = HashMap::new();
+        let mut block: Vec<(i32, &str)> = Vec::new();
+        let mut cur_block: Option<(&str, &str)> = None;
+        let mut n_blocks = 0;
+        let mut n_skipped = 0;
+        let rows = clusters.into_iter().zip(titles).zip(authors);
+        for ((c, t), a) in pb.wrap_iter(rows) {
+            let (c, t, a) = (c.unwrap(), t.unwrap(), a.unwrap());
+            let bk = (a, block_key(t, self.block_prefix));
+            if cur_block != Some(bk) {
+                if let Some((ca, _)) = cur_block {
+                    if block.len() > self.max_block {
+                        n_skipped += 1;
+                    } else {
+                        self.compare_block(ca, &block, &mut links);
+                    }
+                    n_blocks += 1;
+                }
+                block.clear();
+                cur_block = Some(bk);
+            }
+            block.push((c, t));
+        }
+        if let Some((ca, _)) = cur_block {
+            if block.len() > self.max_block {
+                n_skipped += 1;
+            } else {
+                self.compare_block(ca, &block, &mut links);
+            }
+            n_blocks += 1;
+        }
+        pb.finish_and_clear();
+
+        if n_skipped > 0 {
+            warn!(
+                "skipped {} of {} blocks with more than {} titles",
+                n_skipped, n_blocks, self.max_block
+            );
+        }
+        info!(
+            "found {} candidate links in {} blocks",
+            links.len(),
+            n_blocks
+        );
+
+        let mut links: Vec<_> = links.into_values().collect();
+        links.sort_by_key(|l| (l.cluster, l.other_cluster));
+        let mut writer = TableWriter::open(&self.output)?;
+        for link in links {
+            writer.write_object(link)?;
+        }
+        writer.finish()?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_block_key() {
+    assert_eq!(block_key("emma", 2), "em");
+    assert_eq!(block_key("e", 2), "e");
+    assert_eq!(block_key("été", 1), "é");
+}
+
+#[test]
+fn test_compare_volumes() {
+    let cmd = LinkTitles {
+        output: PathBuf::new(),
+        author_file: PathBuf::new(),
+        threshold: 0.9,
+        block_prefix: 2,
+        max_block: 1000,
+    };
+    let block = [
+        (1, "foundation 2"),
+        (2, "foundation 3"),
+        (3, "foundation 2"),
+        (4, "foundation"),
+    ];
+    let mut links = HashMap::new();
+    cmd.compare_block("isaac asimov", &block, &mut links);
+    let mut pairs: Vec<_> = links.keys().copied().collect();
+    pairs.sort();
+    assert_eq!(pairs, vec![(1, 3)]);
+}
@@ -3,3 +3,6 @@ pub mod author_gender;
 pub mod authors;
 pub mod books;
 pub mod hash;
+pub mod info;
+pub mod link_titles;
+pub mod records;
@@ -0,0 +1,180 @@
+//! Scan book record attributes for cluster-level processing.
+//!
+//! These functions read attributes of the individual book records (LOC records,
+//! OpenLibrary editions and works, and GoodReads books and works) from the
+//! extracted source data, keyed by book code so they can be joined with the
+//! cluster graph nodes.
+use crate::cleaning::names::name_key;
+use crate::cleaning::titles::normalize_title;
+use crate::ids::codes::*;
+use crate::prelude::*;
+use polars::prelude::*;
+
+/// Book record sources, in priority order for choosing cluster metadata.
+///
+/// Library of Congress records are curated by catalogers, so they come first,
+/// followed by OpenLibrary works and editions and then GoodReads works and books.
+/// Work records come before edition records since they describe the work as a
+/// whole rather than a particular printing.
+pub const SOURCE_PRIORITY: &[NS<'static>] =
+    &[NS_LOC_REC, NS_WORK, NS_EDITION, NS_GR_WORK, NS_GR_BOOK];
+
+/// Expression converting an ID column to book codes.
+pub fn book_code(id: &str, ns: NS<'_>) -> Expr {
+    col(id).cast(DataType::Int32) + lit(ns.base())
+}
+
+/// Expression for the source namespace name of the `book_code` column.
+pub fn source_name() -> Expr {
+    let nsc = col("book_code") / lit(NS_MULT_BASE);
+    SOURCE_PRIORITY
+        .iter()
+        .rev()
+        .fold(lit(NULL).cast(DataType::String), |expr, ns| {
+            when(nsc.clone().eq(lit(ns.code())))
+                .then(lit(ns.name()))
+                .otherwise(expr)
+        })
+}
+
+/// Expression for the source priority (lower is better) of the `book_code` column.
+pub fn source_priority() -> Expr {
+    let nsc = col("book_code") / lit(NS_MULT_BASE);
+    SOURCE_PRIORITY
+        .iter()
+        .enumerate()
+        .rev()
+        .fold(lit(u8::MAX), |expr, (i, ns)| {
+            when(nsc.clone().eq(lit(ns.code())))
+                .then(lit(i as u8))
+                .otherwise(expr)
+        })
+}
+
+/// Scan the cluster graph nodes for book code to cluster mappings.
+pub fn scan_cluster_nodes() -> Result<LazyFrame> {
+    let df = scan_df_parquet("book-links/cluster-graph-nodes.parquet")?;
+    Ok(df.select([col("book_code"), col("cluster")]))
+}
+
+/// Scan the fields of LOC book records with a particular tag and subfield code.
+fn scan_loc_fields(tag: i16, sf_code: u8) -> Result<LazyFrame> {
+    let df = scan_df_parquet("loc-mds/book-fields.parquet")?;
+    let df = df.filter(col("tag").eq(lit(tag)).and(col("sf_code").eq(lit(sf_code))));
+    Ok(df.select([
+        book_code("rec_id", NS_LOC_REC).alias("book_code"),
+        col("contents"),
+    ]))
+}
+
+/// Scan the LOC fixed-length data elements (MARC field 008).
+pub fn scan_loc_008() -> Result<LazyFrame> {
+    scan_loc_fields(8, 0)
+}
+
+/// Scan a title table, converting its ID column to book codes.
+fn scan_title_file(path: &str, id: &str, ns: NS<'_>) -> Result<LazyFrame> {
+    info!("scanning titles from {}", path);
+    let df = scan_df_parquet(path)?;
+    Ok(df.select([book_code(id, ns).alias("book_code"), col("title")]))
+}
+
+/// Scan the titles of all book records.
+pub fn scan_titles(cfg: &Config) -> Result<LazyFrame> {
+    let loc =
+        scan_loc_fields(245, b'a')?.select([col("book_code"), col("contents").alias("title")]);
+    let mut frames = vec![
+        loc,
+        scan_title_file("openlibrary/editions.parquet", "id", NS_EDITION)?,
+        scan_title_file("openlibrary/works.parquet", "id", NS_WORK)?,
+    ];
+    if cfg.goodreads.enabled {
+        frames.push(scan_title_file(
+            "goodreads/gr-book-info.parquet",
+            "book_id",
+            NS_GR_BOOK,
+        )?);
+        frames.push(scan_title_file(
+            "goodreads/gr-work-info.parquet",
+            "work_id",
+            NS_GR_WORK,
+        )?);
+    }
+    let df = concat(frames, UnionArgs::default())?;
+    Ok(df.filter(col("title").is_not_null()))
+}
+
+/// Scan the first author names of all book records.
+pub fn scan_first_authors(cfg: &Config) -> Result<LazyFrame> {
+    info!("scanning first authors");
+    let loc = scan_df_parquet("loc-mds/book-authors.parquet")?.select([
+        book_code("rec_id", NS_LOC_REC).alias("book_code"),
+        col("author_name"),
+    ]);
+
+    let ol_auth = scan_df_parquet("openlibrary/authors.parquet")?
+        .select([col("id"), col("name").alias("author_name")]);
+    let ol_ed = scan_df_parquet("openlibrary/edition-authors.parquet")?
+        .filter(col("pos").eq(lit(0i16)))
+        .join(
+            ol_auth.clone(),
+            [col("author")],
+            [col("id")],
+            JoinType::Inner.into(),
+        )
+        .select([
+            book_code("edition", NS_EDITION).alias("book_code"),
+            col("author_name"),
+        ]);
+    let ol_work = scan_df_parquet("openlibrary/work-authors.parquet")?
+        .filter(col("pos").eq(lit(0i16)))
+        .join(
+            ol_auth,
+            [col("author")],
+            [col("id")],
+            JoinType::Inner.into(),
+        )
+        .select([
+            book_code("id", NS_WORK).alias("book_code"),
+            col("author_name"),
+        ]);
+
+    let mut frames = vec![loc, ol_ed, ol_work];
+    if cfg.goodreads.enabled {
+        let gr_auth = scan_df_parquet("goodreads/gr-author-info.parquet")?
+            .select([col("author_id"), col("name").alias("author_name")]);
+        let gr = scan_df_parquet("goodreads/gr-book-authors.parquet")?
+            .filter(col("position").eq(lit(0i16)))
+            .join(
+                gr_auth,
+                [col("author_id")],
+                [col("author_id")],
+                JoinType::Inner.into(),
+            )
+            .select([
+                book_code("book_id", NS_GR_BOOK).alias("book_code"),
+                col("author_name"),
+            ]);
+        frames.push(gr);
+    }
+
+    let df = concat(frames, UnionArgs::default())?;
+    Ok(df.filter(col("author_name").is_not_null()))
+}
+
+/// Normalize a column of titles.
+pub fn udf_normalize_title(col: Series) -> PolarsResult<Option<Series>> {
+    let col = col.str()?;
+    let res: StringChunked = col
+        .into_iter()
+        .map(|t| t.map(normalize_title).filter(|t| !t.is_empty()))
+        .collect();
+    Ok(Some(res.into_series()))
+}
+
+/// Compute name keys for a column of author names.
+pub fn udf_name_key(col: Series) -> PolarsResult<Option<Series>> {
+    let col = col.str()?;
+    let res: StringChunked = col.into_iter().map(|n| n.and_then(name_key)).collect();
+    Ok(Some(res.into_series()))
+}
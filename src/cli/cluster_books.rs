@@ -1,24 +1,74 @@
 //! Book clustering command.
 use std::thread::{scope, Scope, ScopedJoinHandle};
 
+use parse_display::{Display, FromStr};
 use petgraph::algo::kosaraju_scc;
 
 use crate::graph::model::*;
 use crate::graph::*;
 use crate::prelude::*;
 
+const OVERRIDE_REPORT_PATH: &str = "book-links/cluster-override-report.parquet";
+
+/// Algorithm for computing the book clusters.
+#[derive(Display, FromStr, Debug, Clone, Copy, PartialEq, Eq)]
+#[display(style = "kebab-case")]
+pub enum Backend {
+    /// Build the full graph in memory and compute connected components.
+    Petgraph,
+    /// Stream edges through a disjoint-set structure, without materializing the graph.
+    UnionFind,
+}
+
 /// Run the book clustering algorithm.
 #[derive(Args, Debug)]
 #[command(name = "cluster-books")]
 pub struct ClusterBooks {
     #[arg(long = "save-graph")]
     save_graph: Option<PathBuf>,
+
+    /// Apply curation overrides from a YAML or CSV file.
+    #[arg(long = "overrides", name = "OVERRIDES")]
+    overrides: Option<PathBuf>,
+
+    /// Add candidate links (from `cluster link-titles`) as an edge source.
+    #[arg(long = "candidate-links", name = "LINKS")]
+    candidate_links: Option<PathBuf>,
+
+    /// Select the clustering backend (petgraph or union-find).
+    #[arg(long = "backend", name = "BACKEND", default_value = "petgraph")]
+    backend: Backend,
 }
 
 impl Command for ClusterBooks {
     fn exec(&self) -> Result<()> {
         let cfg = load_config()?;
-        let mut graph = construct_graph(&cfg)?;
+        let mut edges = edge_sources(&cfg);
+        if let Some(path) = &self.candidate_links {
+            info!("adding candidate links from {}", path.display());
+            let links = CandidateLinks::load(path, &node_sources(&cfg))?;
+            edges.push(Box::new(links));
+        }
+
+        if self.backend == Backend::UnionFind {
+            if self.save_graph.is_some() {
+                return Err(anyhow!("--save-graph requires the petgraph backend"));
+            }
+            if self.overrides.is_some() {
+                return Err(anyhow!("--overrides requires the petgraph backend"));
+            }
+            info!("clustering with streaming union-find");
+            return cluster_union_find(&cfg, &edges);
+        }
+
+        let mut overrides = match &self.overrides {
+            Some(path) => ClusterOverrides::load(path)?,
+            None => ClusterOverrides::default(),
+        };
+        let mut graph = construct_graph(&cfg, &edges, &mut overrides)?;
+        if self.overrides.is_some() {
+            overrides.save_report(OVERRIDE_REPORT_PATH)?;
+        }
 
         info!("computing connected components");
         let clusters = kosaraju_scc(&graph);
@@ -0,0 +1,313 @@
+//! Cluster ID crosswalk between integration releases.
+//!
+//! Cluster IDs are the smallest book code in each cluster, so they change whenever
+//! cluster membership changes.  This command compares the graph node tables from
+//! two runs of the integration and maps each old cluster to the new clusters that
+//! share its nodes, so results keyed by old cluster IDs can be translated.
+//!
+//! ISBN IDs are assigned afresh in each run, so ISBN nodes are matched by ISBN
+//! rather than by book code; other nodes are matched by book code.
+use crate::prelude::*;
+use polars::prelude::*;
+
+/// Map cluster IDs between two integration releases.
+#[derive(Args, Debug)]
+#[command(name = "cluster-crosswalk")]
+pub struct ClusterCrosswalk {
+    /// Write the crosswalk to FILE.
+    #[arg(short = 'o', long = "output", name = "FILE")]
+    output: PathBuf,
+
+    /// The graph node file from the old release.
+    #[arg(name = "OLD")]
+    old_nodes: PathBuf,
+
+    /// The graph node file from the new release.
+    #[arg(name = "NEW")]
+    new_nodes: PathBuf,
+}
+
+/// Scan a cluster graph node table, keying the nodes and renaming the cluster column.
+pub fn scan_nodes(path: &Path, name: &str) -> Result<LazyFrame> {
+    let df = scan_df_parquet(path)?;
+    let key = when(col("node_type").eq(lit("ISBN")))
+        .then(lit("ISBN:") + col("label"))
+        .otherwise(col("book_code").cast(DataType::String));
+    Ok(df.select([key.alias("node"), col("cluster").alias(name)]))
+}
+
+/// Count the nodes in each cluster.
+fn cluster_sizes(nodes: LazyFrame, name: &str, size: &str) -> LazyFrame {
+    nodes
+        .group_by([col(name)])
+        .agg([col("node").count().alias(size)])
+}
+
+/// Count the distinct (non-null) partners of each cluster.
+fn partner_counts(pairs: LazyFrame, name: &str, other: &str, count: &str) -> LazyFrame {
+    pairs
+        .filter(col(name).is_not_null().and(col(other).is_not_null()))
+        .group_by([col(name)])
+        .agg([col(other).count().alias(count)])
+}
+
+/// Classify the relationship between an old and new cluster.
+///
+/// Clusters that map one-to-one are `identical` (same ID and nodes), `renamed` (same
+/// nodes, different ID), or `changed` (nodes added or removed).  When an old cluster's
+/// nodes are spread over several new clusters it is `split`, when a new cluster draws
+/// on several old clusters it is `merged`, and `split-merged` when both happen.  Rows
+/// for nodes only in the old or new release are `removed` and `new`, respectively.
+fn relationship() -> Expr {
+    when(col("old_cluster").is_null())
+        .then(lit("new"))
+        .when(col("new_cluster").is_null())
+        .then(lit("removed"))
+        .when(col("n_new").gt(1).and(col("n_old").gt(1)))
+        .then(lit("split-merged"))
+        .when(col("n_new").gt(1))
+        .then(lit("split"))
+        .when(col("n_old").gt(1))
+        .then(lit("merged"))
+        .when(
+            col("n_shared")
+                .neq(col("old_size"))
+                .or(col("n_shared").neq(col("new_size"))),
+        )
+        .then(lit("changed"))
+        .when(col("old_cluster").eq(col("new_cluster")))
+        .then(lit("identical"))
+        .otherwise(lit("renamed"))
+}
+
+/// Build the crosswalk between old and new node tables from [scan_nodes].
+pub fn crosswalk(old: LazyFrame, new: LazyFrame) -> Result<LazyFrame> {
+    let joined = old.clone().join(
+        new.clone(),
+        [col("node")],
+        [col("node")],
+        JoinArgs::new(JoinType::Outer { coalesce: true }),
+    );
+    let pairs = joined
+        .group_by([col("old_cluster"), col("new_cluster")])
+        .agg([col("node").count().alias("n_shared")]);
+    // collect the pairs once, since we use them repeatedly
+    info!("computing cluster overlaps");
+    let pairs = pairs.collect()?.lazy();
+
+    let old_sizes = cluster_sizes(old, "old_cluster", "old_size");
+    let new_sizes = cluster_sizes(new, "new_cluster", "new_size");
+    let fan_out = partner_counts(pairs.clone(), "old_cluster", "new_cluster", "n_new");
+    let fan_in = partner_counts(pairs.clone(), "new_cluster", "old_cluster", "n_old");
+
+    let xw = pairs
+        .left_join(old_sizes, col("old_cluster"), col("old_cluster"))
+        .left_join(new_sizes, col("new_cluster"), col("new_cluster"))
+        .left_join(fan_out, col("old_cluster"), col("old_cluster"))
+        .left_join(fan_in, col("new_cluster"), col("new_cluster"));
+
+    // the best match for an old cluster is the new cluster with the most shared nodes
+    let best = xw
+        .clone()
+        .filter(
+            col("old_cluster")
+                .is_not_null()
+                .and(col("new_cluster").is_not_null()),
+        )
+        .sort_by_exprs(
+            [col("old_cluster"), col("n_shared"), col("new_cluster")],
+            [false, true, false],
+            false,
+            false,
+        )
+        .unique_stable(Some(vec!["old_cluster".into()]), UniqueKeepStrategy::First)
+        .select([
+            col("old_cluster"),
+            col("new_cluster"),
+            lit(true).alias("best_match"),
+        ]);
+    let xw = xw.join(
+        best,
+        [col("old_cluster"), col("new_cluster")],
+        [col("old_cluster"), col("new_cluster")],
+        JoinType::Left.into(),
+    );
+
+    let size_or_zero = |c: &str| col(c).fill_null(lit(0u32)).cast(DataType::UInt32);
+    let xw = xw.with_columns([
+        col("n_shared").cast(DataType::UInt32),
+        size_or_zero("old_size").alias("old_size"),
+        size_or_zero("new_size").alias("new_size"),
+    ]);
+    let union = (col("old_size") + col("new_size") - col("n_shared")).cast(DataType::Float64);
+    let xw = xw.select([
+        col("old_cluster"),
+        col("new_cluster"),
+        relationship().alias("relationship"),
+        col("n_shared"),
+        col("old_size"),
+        col("new_size"),
+        when(
+            col("old_cluster")
+                .is_null()
+                .or(col("new_cluster").is_null()),
+        )
+        .then(lit(NULL).cast(DataType::Float64))
+        .otherwise(col("n_shared").cast(DataType::Float64) / union)
+        .alias("jaccard"),
+        col("best_match").fill_null(lit(false)),
+    ]);
+    let xw = xw.sort_by_exprs(
+        [col("old_cluster"), col("new_cluster")],
+        [false, false],
+        true,
+        false,
+    );
+    Ok(xw)
+}
+
+impl Command for ClusterCrosswalk {
+    fn exec(&self) -> Result<()> {
+        let old = scan_nodes(&self.old_nodes, "old_cluster")?;
+        let new = scan_nodes(&self.new_nodes, "new_cluster")?;
+        let xw = crosswalk(old, new)?;
+
+        info!("building crosswalk");
+        let xw = xw.collect()?;
+
+        let summary = xw.column("relationship")?.value_counts(true, false)?;
+        let kinds = summary.column("relationship")?.str()?;
+        let counts = summary.column("count")?.u32()?;
+        for (kind, n) in kinds.into_iter().zip(counts) {
+            info!(
+                "{}: {} cluster pairs",
+                kind.unwrap_or_default(),
+                n.unwrap_or_default()
+            );
+        }
+
+        info!(
+            "saving {} crosswalk rows to {:?}",
+            xw.height(),
+            &self.output
+        );
+        save_df_parquet(xw, &self.output)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(super) fn save_test_nodes(path: &Path, nodes: &[(i32, &str, &str, i32)]) {
+    let df = df!(
+        "book_code" => nodes.iter().map(|n| n.0).collect::<Vec<_>>(),
+        "node_type" => nodes.iter().map(|n| n.1).collect::<Vec<_>>(),
+        "label" => nodes.iter().map(|n| n.2).collect::<Vec<_>>(),
+        "cluster" => nodes.iter().map(|n| n.3).collect::<Vec<_>>(),
+    )
+    .unwrap();
+    save_df_parquet(df, path).unwrap();
+}
+
+#[test]
+fn test_crosswalk_relationships() {
+    use std::collections::HashMap;
+
+    let dir = tempfile::tempdir().unwrap();
+    let old_path = dir.path().join("old.parquet");
+    let new_path = dir.path().join("new.parquet");
+    save_test_nodes(
+        &old_path,
+        &[
+            (1, "LOC", "", 1),
+            (2, "LOC", "", 1),
+            (10, "LOC", "", 10),
+            (11, "LOC", "", 10),
+            (20, "LOC", "", 20),
+            (21, "LOC", "", 20),
+            (30, "LOC", "", 30),
+            (31, "LOC", "", 30),
+            (40, "LOC", "", 40),
+            (41, "LOC", "", 41),
+            (50, "LOC", "", 50),
+            (51, "LOC", "", 50),
+            (52, "LOC", "", 52),
+            (60, "LOC", "", 60),
+            // the ISBN is renumbered in the new release
+            (80, "LOC", "", 80),
+            (900, "ISBN", "0123456789", 80),
+        ],
+    );
+    save_test_nodes(
+        &new_path,
+        &[
+            (1, "LOC", "", 1),
+            (2, "LOC", "", 1),
+            (10, "LOC", "", 12),
+            (11, "LOC", "", 12),
+            (20, "LOC", "", 20),
+            (21, "LOC", "", 20),
+            (22, "LOC", "", 20),
+            (30, "LOC", "", 30),
+            (31, "LOC", "", 31),
+            (40, "LOC", "", 40),
+            (41, "LOC", "", 40),
+            (50, "LOC", "", 50),
+            (51, "LOC", "", 51),
+            (52, "LOC", "", 51),
+            (70, "LOC", "", 70),
+            (80, "LOC", "", 80),
+            (905, "ISBN", "0123456789", 80),
+        ],
+    );
+
+    let old = scan_nodes(&old_path, "old_cluster").unwrap();
+    let new = scan_nodes(&new_path, "new_cluster").unwrap();
+    let xw = crosswalk(old, new).unwrap().collect().unwrap();
+
+    let olds = xw.column("old_cluster").unwrap().i32().unwrap();
+    let news = xw.column("new_cluster").unwrap().i32().unwrap();
+    let rels = xw.column("relationship").unwrap().str().unwrap();
+    let jaccards = xw.column("jaccard").unwrap().f64().unwrap();
+    let bests = xw.column("best_match").unwrap().bool().unwrap();
+    let mut rows = HashMap::new();
+    for i in 0..xw.height() {
+        rows.insert(
+            (olds.get(i), news.get(i)),
+            (
+                rels.get(i).unwrap().to_string(),
+                jaccards.get(i),
+                bests.get(i).unwrap(),
+            ),
+        );
+    }
+    let rel = |o: Option<i32>, n: Option<i32>| rows[&(o, n)].0.as_str();
+
+    assert_eq!(rel(Some(1), Some(1)), "identical");
+    assert_eq!(rel(Some(10), Some(12)), "renamed");
+    assert_eq!(rel(Some(20), Some(20)), "changed");
+    assert_eq!(rel(Some(30), Some(30)), "split");
+    assert_eq!(rel(Some(30), Some(31)), "split");
+    assert_eq!(rel(Some(40), Some(40)), "merged");
+    assert_eq!(rel(Some(41), Some(40)), "merged");
+    assert_eq!(rel(Some(50), Some(50)), "split");
+    assert_eq!(rel(Some(50), Some(51)), "split-merged");
+    assert_eq!(rel(Some(52), Some(51)), "merged");
+    assert_eq!(rel(Some(60), None), "removed");
+    assert_eq!(rel(None, Some(70)), "new");
+    assert_eq!(rel(None, Some(20)), "new");
+    // the ISBN is matched by its label, so the cluster is unchanged
+    assert_eq!(rel(Some(80), Some(80)), "identical");
+    assert_eq!(rows.len(), 14);
+
+    assert_eq!(rows[&(Some(1), Some(1))].1, Some(1.0));
+    assert_eq!(rows[&(Some(20), Some(20))].1, Some(2.0 / 3.0));
+    assert_eq!(rows[&(Some(30), Some(31))].1, Some(0.5));
+    assert_eq!(rows[&(Some(60), None)].1, None);
+
+    // ties go to the smallest new cluster
+    assert!(rows[&(Some(30), Some(30))].2);
+    assert!(!rows[&(Some(30), Some(31))].2);
+    assert!(rows[&(Some(20), Some(20))].2);
+    assert!(!rows[&(None, Some(70))].2);
+}
@@ -0,0 +1,457 @@
+//! Compare the clustering outputs of two integration runs.
+//!
+//! This builds on the [cluster crosswalk][super::cluster_crosswalk] to summarize
+//! what changed between two `book-links` directories: which clusters split, merged,
+//! or changed composition, and which ISBNs moved between clusters.
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+use serde::Serialize;
+
+use super::cluster_crosswalk::{crosswalk, scan_nodes};
+use crate::prelude::*;
+use polars::prelude::*;
+
+/// The per-namespace count columns in the cluster statistics.
+const NS_COUNTS: &[&str] = &[
+    "n_isbns",
+    "n_loc_recs",
+    "n_ol_editions",
+    "n_ol_works",
+    "n_gr_books",
+    "n_gr_works",
+];
+
+/// Relationships in which nodes moved between clusters.
+const MOVED: &[&str] = &["split", "merged", "split-merged", "new", "removed"];
+
+/// Compare clustering outputs between two integration runs.
+#[derive(Args, Debug)]
+#[command(name = "cluster-diff")]
+pub struct ClusterDiff {
+    /// Write the diff tables and summary to DIR.
+    #[arg(short = 'o', long = "output", name = "DIR")]
+    output: PathBuf,
+
+    /// The book-links directory from the old run.
+    #[arg(name = "OLD")]
+    old_dir: PathBuf,
+
+    /// The book-links directory from the new run.
+    #[arg(name = "NEW")]
+    new_dir: PathBuf,
+}
+
+/// Summary of the changes between two runs.
+#[derive(Serialize, Debug, Default)]
+struct DiffSummary {
+    old_clusters: usize,
+    new_clusters: usize,
+    unchanged_clusters: usize,
+    changed_clusters: usize,
+    composition_changed: usize,
+    split_clusters: usize,
+    merged_clusters: usize,
+    moved_isbns: usize,
+    added_isbns: usize,
+    removed_isbns: usize,
+    relationships: BTreeMap<String, usize>,
+}
+
+/// Scan a cluster statistics table, prefixing its count columns.
+fn scan_stats(dir: &Path, prefix: &str) -> Result<LazyFrame> {
+    let df = scan_df_parquet(dir.join("cluster-stats.parquet"))?;
+    let mut cols = vec![col("cluster").alias(&format!("{}_cluster", prefix))];
+    for c in NS_COUNTS {
+        cols.push(
+            col(c)
+                .cast(DataType::Int64)
+                .alias(&format!("{}_{}", prefix, c)),
+        );
+    }
+    Ok(df.select(cols))
+}
+
+/// Scan an ISBN cluster table, renaming the cluster column.
+fn scan_isbns(dir: &Path, name: &str) -> Result<LazyFrame> {
+    let df = scan_df_parquet(dir.join("isbn-clusters.parquet"))?;
+    Ok(df.select([col("isbn"), col("cluster").alias(name)]))
+}
+
+/// Expression for the number of nodes that differ between a pair's clusters.
+///
+/// For matched pairs this is the symmetric difference of the two clusters; for
+/// added or removed nodes, it is the number of nodes added or removed.
+fn change() -> Expr {
+    when(
+        col("old_cluster")
+            .is_null()
+            .or(col("new_cluster").is_null()),
+    )
+    .then(col("n_shared").cast(DataType::Int64))
+    .otherwise(
+        col("old_size").cast(DataType::Int64) + col("new_size").cast(DataType::Int64)
+            - lit(2i64) * col("n_shared").cast(DataType::Int64),
+    )
+}
+
+/// Expression selecting rows whose relationship is one of the given kinds.
+fn relationship_in(kinds: &[&str]) -> Expr {
+    kinds
+        .iter()
+        .map(|k| col("relationship").eq(lit(*k)))
+        .reduce(|a, b| a.or(b))
+        .unwrap()
+}
+
+/// Count the distinct non-null values of a column among rows matching a filter.
+fn count_distinct(df: &DataFrame, filter: Expr, column: &str) -> Result<usize> {
+    let res = df
+        .clone()
+        .lazy()
+        .filter(filter.and(col(column).is_not_null()))
+        .select([col(column).n_unique()])
+        .collect()?;
+    let n = res.column(column)?.cast(&DataType::UInt64)?;
+    Ok(n.u64()?.get(0).unwrap_or_default() as usize)
+}
+
+impl ClusterDiff {
+    /// Compute the cluster-level diff table.
+    fn cluster_diff(&self) -> Result<DataFrame> {
+        let old = scan_nodes(
+            &self.old_dir.join("cluster-graph-nodes.parquet"),
+            "old_cluster",
+        )?;
+        let new = scan_nodes(
+            &self.new_dir.join("cluster-graph-nodes.parquet"),
+            "new_cluster",
+        )?;
+        let xw = crosswalk(old, new)?;
+
+        let diff = xw
+            .left_join(
+                scan_stats(&self.old_dir, "old")?,
+                col("old_cluster"),
+                col("old_cluster"),
+            )
+            .left_join(
+                scan_stats(&self.new_dir, "new")?,
+                col("new_cluster"),
+                col("new_cluster"),
+            );
+
+        let mut cols = vec![
+            col("old_cluster"),
+            col("new_cluster"),
+            col("relationship"),
+            col("n_shared"),
+            col("old_size"),
+            col("new_size"),
+            col("jaccard"),
+            col("best_match"),
+            change().alias("change"),
+        ];
+        let mut comp_changed = lit(false);
+        for c in NS_COUNTS {
+            let old_c = format!("old_{}", c);
+            let new_c = format!("new_{}", c);
+            let delta = col(&new_c).fill_null(lit(0i64)) - col(&old_c).fill_null(lit(0i64));
+            comp_changed = comp_changed.or(delta.clone().neq(lit(0i64)));
+            cols.push(delta.alias(&format!("delta_{}", c)));
+        }
+        cols.push(comp_changed.alias("composition_changed"));
+
+        let diff = diff.select(cols).sort_by_exprs(
+            [col("change"), col("old_cluster"), col("new_cluster")],
+            [true, false, false],
+            true,
+            false,
+        );
+
+        info!("computing cluster diff");
+        Ok(diff.collect()?)
+    }
+
+    /// Compute the table of moved ISBNs.
+    ///
+    /// An ISBN has moved if it is new, removed, or its new cluster is not the best
+    /// match for its old cluster; the ISBNs that stay with the bulk of a split or
+    /// merged cluster are not counted.
+    fn moved_isbns(&self, diff: &DataFrame) -> Result<DataFrame> {
+        let old = scan_isbns(&self.old_dir, "old_cluster")?;
+        let new = scan_isbns(&self.new_dir, "new_cluster")?;
+        let isbns = old.join(
+            new,
+            [col("isbn")],
+            [col("isbn")],
+            JoinArgs::new(JoinType::Outer { coalesce: true }),
+        );
+
+        let pairs = diff.clone().lazy().select([
+            col("old_cluster"),
+            col("new_cluster"),
+            col("relationship"),
+            col("change"),
+            col("best_match"),
+        ]);
+        // added and removed ISBNs have a null cluster, so we need nulls to match
+        let mut args = JoinArgs::new(JoinType::Inner);
+        args.join_nulls = true;
+        let moved = isbns
+            .join(
+                pairs,
+                [col("old_cluster"), col("new_cluster")],
+                [col("old_cluster"), col("new_cluster")],
+                args,
+            )
+            .filter(relationship_in(MOVED).and(col("best_match").not()))
+            .select([
+                col("isbn"),
+                col("old_cluster"),
+                col("new_cluster"),
+                col("relationship"),
+                col("change"),
+            ])
+            .sort_by_exprs([col("change"), col("isbn")], [true, false], false, false);
+
+        info!("finding moved ISBNs");
+        Ok(moved.collect()?)
+    }
+}
+
+impl Command for ClusterDiff {
+    fn exec(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.output)?;
+
+        let diff = self.cluster_diff()?;
+        let moved = self.moved_isbns(&diff)?;
+
+        let mut summary = DiffSummary {
+            old_clusters: count_distinct(&diff, lit(true), "old_cluster")?,
+            new_clusters: count_distinct(&diff, lit(true), "new_cluster")?,
+            unchanged_clusters: count_distinct(
+                &diff,
+                relationship_in(&["identical", "renamed"]),
+                "old_cluster",
+            )?,
+            changed_clusters: count_distinct(&diff, relationship_in(&["changed"]), "old_cluster")?,
+            composition_changed: count_distinct(
+                &diff,
+                relationship_in(&["changed"]).and(col("composition_changed")),
+                "old_cluster",
+            )?,
+            split_clusters: count_distinct(
+                &diff,
+                relationship_in(&["split", "split-merged"]),
+                "old_cluster",
+            )?,
+            merged_clusters: count_distinct(
+                &diff,
+                relationship_in(&["merged", "split-merged"]),
+                "new_cluster",
+            )?,
+            moved_isbns: moved.height(),
+            ..DiffSummary::default()
+        };
+        let rels = moved.column("relationship")?.str()?;
+        summary.added_isbns = rels.into_iter().filter(|r| *r == Some("new")).count();
+        summary.removed_isbns = rels.into_iter().filter(|r| *r == Some("removed")).count();
+
+        let counts = diff.column("relationship")?.value_counts(true, false)?;
+        let kinds = counts.column("relationship")?.str()?;
+        let ns = counts.column("count")?.u32()?;
+        for (kind, n) in kinds.into_iter().zip(ns) {
+            if let (Some(kind), Some(n)) = (kind, n) {
+                summary.relationships.insert(kind.to_string(), n as usize);
+            }
+        }
+
+        info!(
+            "{} clusters split, {} merged, {} unchanged, {} ISBNs moved",
+            summary.split_clusters,
+            summary.merged_clusters,
+            summary.unchanged_clusters,
+            summary.moved_isbns
+        );
+
+        let path = self.output.join("cluster-diff.parquet");
+        info!("saving {} cluster diff rows to {:?}", diff.height(), path);
+        save_df_parquet(diff, &path)?;
+
+        let path = self.output.join("moved-isbns.parquet");
+        info!("saving {} moved ISBNs to {:?}", moved.height(), path);
+        save_df_parquet(moved, &path)?;
+
+        let path = self.output.join("summary.json");
+        info!("saving summary to {:?}", path);
+        let mut out = File::create(path)?;
+        serde_json::to_writer_pretty(&mut out, &summary)?;
+        out.write_all(b"\n")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn save_test_run(dir: &Path, nodes: &[(i32, &str, &str, i32)], stats: &[(i32, u32, u32)]) {
+    use super::cluster_crosswalk::save_test_nodes;
+
+    std::fs::create_dir_all(dir).unwrap();
+    save_test_nodes(&dir.join("cluster-graph-nodes.parquet"), nodes);
+
+    let isbns: Vec<_> = nodes.iter().filter(|n| n.1 == "ISBN").collect();
+    let df = df!(
+        "isbn" => isbns.iter().map(|n| n.2).collect::<Vec<_>>(),
+        "cluster" => isbns.iter().map(|n| n.3).collect::<Vec<_>>(),
+    )
+    .unwrap();
+    save_df_parquet(df, dir.join("isbn-clusters.parquet")).unwrap();
+
+    let zeros = vec![0u32; stats.len()];
+    let df = df!(
+        "cluster" => stats.iter().map(|s| s.0).collect::<Vec<_>>(),
+        "n_isbns" => stats.iter().map(|s| s.1).collect::<Vec<_>>(),
+        "n_loc_recs" => stats.iter().map(|s| s.2).collect::<Vec<_>>(),
+        "n_ol_editions" => &zeros,
+        "n_ol_works" => &zeros,
+        "n_gr_books" => &zeros,
+        "n_gr_works" => &zeros,
+    )
+    .unwrap();
+    save_df_parquet(df, dir.join("cluster-stats.parquet")).unwrap();
+}
+
+#[cfg(test)]
+fn test_diff() -> (tempfile::TempDir, ClusterDiff) {
+    let dir = tempfile::tempdir().unwrap();
+    let diff = ClusterDiff {
+        output: dir.path().join("diff"),
+        old_dir: dir.path().join("old"),
+        new_dir: dir.path().join("new"),
+    };
+    // cluster 1 splits, with most of it staying in cluster 1
+    save_test_run(
+        &diff.old_dir,
+        &[
+            (1, "LOC", "", 1),
+            (2, "LOC", "", 1),
+            (3, "LOC", "", 1),
+            (100, "ISBN", "0000000001", 1),
+            (101, "ISBN", "0000000002", 1),
+            (10, "LOC", "", 10),
+            (102, "ISBN", "0000000003", 10),
+        ],
+        &[(1, 2, 3), (10, 1, 1)],
+    );
+    // ISBN codes are renumbered in the new run
+    save_test_run(
+        &diff.new_dir,
+        &[
+            (1, "LOC", "", 1),
+            (2, "LOC", "", 1),
+            (200, "ISBN", "0000000001", 1),
+            (3, "LOC", "", 3),
+            (201, "ISBN", "0000000002", 3),
+            (10, "LOC", "", 10),
+            (202, "ISBN", "0000000003", 10),
+            (20, "LOC", "", 20),
+            (203, "ISBN", "0000000004", 20),
+        ],
+        &[(1, 1, 2), (3, 1, 1), (10, 1, 1), (20, 1, 1)],
+    );
+    (dir, diff)
+}
+
+#[test]
+fn test_cluster_diff() {
+    let (_dir, cd) = test_diff();
+    let diff = cd.cluster_diff().unwrap();
+
+    let olds: Vec<_> = diff
+        .column("old_cluster")
+        .unwrap()
+        .i32()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let news: Vec<_> = diff
+        .column("new_cluster")
+        .unwrap()
+        .i32()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let rels: Vec<_> = diff
+        .column("relationship")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let changes: Vec<_> = diff
+        .column("change")
+        .unwrap()
+        .i64()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let d_isbns: Vec<_> = diff
+        .column("delta_n_isbns")
+        .unwrap()
+        .i64()
+        .unwrap()
+        .into_iter()
+        .collect();
+    let comp: Vec<_> = diff
+        .column("composition_changed")
+        .unwrap()
+        .bool()
+        .unwrap()
+        .into_iter()
+        .collect();
+
+    assert_eq!(olds, vec![Some(1), Some(1), None, Some(10)]);
+    assert_eq!(news, vec![Some(3), Some(1), Some(20), Some(10)]);
+    assert_eq!(
+        rels,
+        vec![Some("split"), Some("split"), Some("new"), Some("identical")]
+    );
+    assert_eq!(changes, vec![Some(3), Some(2), Some(2), Some(0)]);
+    assert_eq!(d_isbns, vec![Some(-1), Some(-1), Some(1), Some(0)]);
+    assert_eq!(comp, vec![Some(true), Some(true), Some(true), Some(false)]);
+}
+
+#[test]
+fn test_moved_isbns() {
+    let (_dir, cd) = test_diff();
+    let diff = cd.cluster_diff().unwrap();
+    let moved = cd.moved_isbns(&diff).unwrap();
+
+    // the ISBN staying in the best match of its split cluster has not moved
+    let isbns: Vec<_> = moved
+        .column("isbn")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(isbns, vec![Some("0000000002"), Some("0000000004")]);
+    let rels: Vec<_> = moved
+        .column("relationship")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(rels, vec![Some("split"), Some("new")]);
+    let olds: Vec<_> = moved
+        .column("old_cluster")
+        .unwrap()
+        .i32()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(olds, vec![Some(1), None]);
+}
@@ -35,6 +35,7 @@ fn all_sources(cfg: &Config) -> Vec<ISBNSource> {
         ISBNSource::new("BX")
             .enabled(cfg.bx.enabled)
             .path("../bx/cleaned-ratings.csv")
+            .also("../bx/books.parquet", "isbn")
             .finish(),
         ISBNSource::new("AZ14")
             .enabled(cfg.az2014.enabled)
@@ -45,6 +46,13 @@ fn all_sources(cfg: &Config) -> Vec<ISBNSource> {
             .enabled(cfg.az2018.enabled)
             .path("../az2018/ratings.parquet")
             .column("asin")
+            .also("../az2018/isbn-links.parquet", "isbn")
+            .finish(),
+        ISBNSource::new("AZ23")
+            .enabled(cfg.az2023.enabled)
+            .path("../az2023/ratings.parquet")
+            .column("asin")
+            .also("../az2023/isbn-links.parquet", "isbn")
             .finish(),
     ]
 }
@@ -55,6 +63,8 @@ struct ISBNSource {
     enabled: bool,
     path: &'static str,
     columns: Vec<&'static str>,
+    /// Additional files and columns to read ISBNs from.
+    extra: Vec<(&'static str, &'static str)>,
 }
 
 impl ISBNSource {
@@ -64,6 +74,7 @@ impl ISBNSource {
             enabled: true,
             path: "",
             columns: vec![],
+            extra: vec![],
         }
     }
 
@@ -89,6 +100,11 @@ impl ISBNSource {
         }
     }
 
+    fn also(mut self, path: &'static str, col: &'static str) -> ISBNSource {
+        self.extra.push((path, col));
+        self
+    }
+
     fn finish(self) -> ISBNSource {
         ISBNSource {
             columns: if self.columns.len() > 0 {
@@ -113,10 +129,17 @@ fn scan_source(src: &ISBNSource) -> Result<LazyFrame> {
         scan_df_parquet(src.path)?
     };
 
+    let mut inputs: Vec<(LazyFrame, &str)> =
+        src.columns.iter().map(|c| (read.clone(), *c)).collect();
+    for (path, id_col) in &src.extra {
+        info!("scanning additional ISBNs from {}", path);
+        inputs.push((scan_df_parquet(*path)?, *id_col));
+    }
+
     let mut counted: Option<LazyFrame> = None;
-    for id_col in &src.columns {
+    for (read, id_col) in inputs {
         info!("counting column {}", id_col);
-        let df = read.clone().select(&[col(id_col).alias("isbn")]);
+        let df = read.select(&[col(id_col).alias("isbn")]);
         let df = df.drop_nulls(None);
         let df = df.group_by(["isbn"]).agg([len().alias("nrecs")]);
         if let Some(prev) = counted {
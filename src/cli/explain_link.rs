@@ -0,0 +1,257 @@
+//! Explain why two records are in the same cluster.
+use std::collections::{HashMap, HashSet};
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+
+use petgraph::algo::astar;
+use petgraph::graph::EdgeIndex;
+use polars::prelude::*;
+
+use crate::arrow::scan_df_batches;
+use crate::graph::*;
+use crate::ids::codes::{ns_of_book_code, NS_EDITION};
+use crate::prelude::*;
+
+const EDITION_PATH: &str = "openlibrary/editions.parquet";
+
+/// Number of editions to scan at a time when looking up edition keys.
+const EDITION_BATCH_SIZE: usize = 1024 * 1024;
+
+/// Explain the link between two book records.
+///
+/// Records are referenced as `NS:ID` (e.g. `GR-B:42`), `NS:KEY` for nodes with
+/// labels (e.g. `OL-W:/works/OL45883W`), or bare ISBNs.  This prints the shortest
+/// path between the two records and the minimal set of edges that would need to be
+/// cut to separate them.
+#[derive(Args, Debug)]
+#[command(name = "explain-link")]
+pub struct ExplainLink {
+    /// Load the graph from FILE.
+    #[arg(long = "graph-file", name = "FILE")]
+    graph_file: Option<PathBuf>,
+
+    /// Load the graph from the cluster node and edge tables instead of a saved graph.
+    #[arg(long = "tables")]
+    tables: bool,
+
+    /// The first record.
+    #[arg(name = "FIRST")]
+    first: String,
+
+    /// The second record.
+    #[arg(name = "SECOND")]
+    second: String,
+}
+
+/// Describe a node for display.
+///
+/// Nodes without labels in the graph (such as OpenLibrary editions) use the
+/// labels in `labels`, keyed by book code.
+fn describe(graph: &IdGraph, n: IdNode, labels: &HashMap<i32, String>) -> String {
+    let node = graph.node_weight(n).unwrap();
+    let ns = ns_of_book_code(node.code).unwrap();
+    let id = ns.from_code(node.code).unwrap();
+    match node.label.as_ref().or_else(|| labels.get(&node.code)) {
+        Some(l) => format!("{}:{} ({})", ns.name(), id, l),
+        None => format!("{}:{}", ns.name(), id),
+    }
+}
+
+/// Look up the OpenLibrary keys of edition nodes, keyed by book code.
+///
+/// The graph does not store edition keys, so we scan the edition table for the
+/// few editions being displayed.
+fn edition_keys(path: &Path, codes: &[i32]) -> Result<HashMap<i32, String>> {
+    let ids: HashSet<u32> = codes
+        .iter()
+        .filter_map(|c| NS_EDITION.from_code(*c))
+        .map(|id| id as u32)
+        .collect();
+    let mut keys = HashMap::new();
+    if ids.is_empty() {
+        return Ok(keys);
+    }
+
+    info!("looking up keys for {} editions", ids.len());
+    for batch in scan_df_batches(path, EDITION_BATCH_SIZE)? {
+        let df = batch?;
+        let eids = df.column("id")?.cast(&DataType::UInt32)?;
+        let ekeys = df.column("key")?;
+        for (id, key) in eids.u32()?.into_iter().zip(ekeys.str()?) {
+            if let (Some(id), Some(key)) = (id, key) {
+                if ids.contains(&id) {
+                    keys.insert(NS_EDITION.to_code(id as i32), key.to_string());
+                }
+            }
+        }
+        if keys.len() == ids.len() {
+            break;
+        }
+    }
+    Ok(keys)
+}
+
+/// Write the shortest path and minimum cut between two nodes.
+fn write_report<W: Write>(
+    out: &mut W,
+    graph: &IdGraph,
+    path: &[IdNode],
+    cut: &[EdgeIndex],
+    labels: &HashMap<i32, String>,
+) -> Result<()> {
+    writeln!(out, "shortest path ({} edges):", path.len() - 1)?;
+    writeln!(out, "  {}", describe(graph, path[0], labels))?;
+    for pair in path.windows(2) {
+        writeln!(out, "    via {}", describe_edge(graph, pair[0], pair[1]))?;
+        writeln!(out, "  {}", describe(graph, pair[1], labels))?;
+    }
+
+    writeln!(out, "minimum cut ({} edges):", cut.len())?;
+    for e in cut {
+        let (s, d) = graph.edge_endpoints(*e).unwrap();
+        writeln!(
+            out,
+            "  {} -- {} [{}]",
+            describe(graph, s, labels),
+            describe(graph, d, labels),
+            describe_edge(graph, s, d)
+        )?;
+    }
+    Ok(())
+}
+
+/// Describe the origin of the edge between two nodes.
+fn describe_edge(graph: &IdGraph, a: IdNode, b: IdNode) -> &'static str {
+    let ac = graph.node_weight(a).unwrap().code;
+    let bc = graph.node_weight(b).unwrap().code;
+    edge_origin(ac, bc).unwrap_or("unknown source")
+}
+
+impl ExplainLink {
+    fn load(&self) -> Result<IdGraph> {
+        if self.tables {
+            load_graph_tables(GraphTables {
+                nodes: "book-links/cluster-graph-nodes.parquet".into(),
+                edges: "book-links/cluster-graph-edges.parquet".into(),
+            })
+        } else {
+            let path = match &self.graph_file {
+                Some(p) => p.clone(),
+                None => PathBuf::from("book-links/book-graph.mp.zst"),
+            };
+            info!("loading graph from {}", path.to_string_lossy());
+            load_graph(path)
+        }
+    }
+}
+
+impl Command for ExplainLink {
+    fn exec(&self) -> Result<()> {
+        let first = NodeRef::parse(&self.first)?;
+        let second = NodeRef::parse(&self.second)?;
+        let graph = self.load()?;
+
+        let found = find_nodes(&graph, &[first.clone(), second.clone()]);
+        let a = *found
+            .get(&first)
+            .ok_or_else(|| anyhow!("{} not found in graph", self.first))?;
+        let b = *found
+            .get(&second)
+            .ok_or_else(|| anyhow!("{} not found in graph", self.second))?;
+
+        info!("searching for shortest path");
+        let path = astar(&graph, a, |n| n == b, |_| 1, |_| 0);
+        let mut out = stdout().lock();
+        let path = match path {
+            Some((_, path)) => path,
+            None => {
+                writeln!(out, "{} and {} are not connected", self.first, self.second)?;
+                return Ok(());
+            }
+        };
+
+        info!("computing minimum edge cut");
+        let cut = min_edge_cut(&graph, a, b);
+
+        let mut codes: Vec<i32> = path.iter().map(|n| graph[*n].code).collect();
+        for e in &cut {
+            let (s, d) = graph.edge_endpoints(*e).unwrap();
+            codes.push(graph[s].code);
+            codes.push(graph[d].code);
+        }
+        let labels = if Path::new(EDITION_PATH).exists() {
+            edition_keys(Path::new(EDITION_PATH), &codes)?
+        } else {
+            warn!("{} not found, editions will not be labeled", EDITION_PATH);
+            HashMap::new()
+        };
+
+        write_report(&mut out, &graph, &path, &cut, &labels)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn test_graph() -> (IdGraph, Vec<IdNode>) {
+    use crate::ids::codes::{NS_ISBN, NS_LOC_REC, NS_WORK};
+    let mut graph = IdGraph::new_undirected();
+    let mut add = |code: i32, label: Option<&str>| {
+        graph.add_node(BookID {
+            code,
+            label: label.map(|l| l.to_string()),
+            cluster: 0,
+        })
+    };
+    let loc = add(NS_LOC_REC.to_code(5), None);
+    let isbn = add(NS_ISBN.to_code(1), Some("0140449132"));
+    let ed = add(NS_EDITION.to_code(17), None);
+    let work = add(NS_WORK.to_code(3), Some("/works/OL3W"));
+    graph.add_edge(loc, isbn, ());
+    graph.add_edge(isbn, ed, ());
+    graph.add_edge(ed, work, ());
+    (graph, vec![loc, isbn, ed, work])
+}
+
+#[test]
+fn test_write_report() {
+    let (graph, nodes) = test_graph();
+    let (loc, work) = (nodes[0], nodes[3]);
+    let (_, path) = astar(&graph, loc, |n| n == work, |_| 1, |_| 0).unwrap();
+    let cut = min_edge_cut(&graph, loc, work);
+    assert_eq!(cut.len(), 1);
+    let labels = HashMap::from([(NS_EDITION.to_code(17), "/books/OL17M".to_string())]);
+
+    let mut out = Vec::new();
+    write_report(&mut out, &graph, &path, &cut, &labels).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "shortest path (3 edges):");
+    assert_eq!(lines[1], "  LOC:5");
+    assert_eq!(lines[2], "    via LOC record ISBN");
+    assert_eq!(lines[3], "  ISBN:1 (0140449132)");
+    assert_eq!(lines[4], "    via OL edition ISBN");
+    assert_eq!(lines[5], "  OL-E:17 (/books/OL17M)");
+    assert_eq!(lines[6], "    via OL edition work");
+    assert_eq!(lines[7], "  OL-W:3 (/works/OL3W)");
+    assert_eq!(lines[8], "minimum cut (1 edges):");
+    assert!(lines[9].contains(" -- "));
+    assert_eq!(lines.len(), 10);
+}
+
+#[test]
+fn test_edition_keys() {
+    use crate::ids::codes::NS_WORK;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("editions.parquet");
+    let df = df!(
+        "id" => [16u32, 17, 18],
+        "key" => ["/books/OL16M", "/books/OL17M", "/books/OL18M"],
+    )
+    .unwrap();
+    save_df_parquet(df, &path).unwrap();
+    let codes = [NS_EDITION.to_code(17), NS_WORK.to_code(16)];
+    let keys = edition_keys(&path, &codes).unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[&NS_EDITION.to_code(17)], "/books/OL17M");
+}
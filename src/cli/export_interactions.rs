@@ -0,0 +1,591 @@
+//! Export interaction files to recommender toolkit formats.
+//!
+//! This reads a deduplicated rating or action file (from the rating and action
+//! deduplicators, or the cluster-level action files), maps user and item IDs to
+//! dense 0-based indices, and writes the interactions in one of several formats.
+//! The index maps are always saved as `user-index.parquet` and
+//! `item-index.parquet`, so exported results can be mapped back to book data
+//! IDs; when item side information is requested, it is included in the item
+//! index.
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+
+use ::zip::write::FileOptions;
+use ::zip::{CompressionMethod, ZipWriter};
+use parse_display::{Display, FromStr};
+use polars::prelude::*;
+
+use crate::prelude::*;
+
+/// Candidate names for the user column.
+const USER_COLUMNS: &[&str] = &["user_id", "user"];
+/// Candidate names for the item column.
+const ITEM_COLUMNS: &[&str] = &["item_id", "item"];
+/// Candidate names for the time column.
+const TIME_COLUMNS: &[&str] = &["first_time", "timestamp"];
+/// Columns taken from the cluster info table.
+const INFO_COLUMNS: &[&str] = &["title", "first_year", "language"];
+
+/// Output formats for exported interactions.
+#[derive(Display, FromStr, Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    /// RecBole atomic files (`.inter` and `.item`).
+    #[display("recbole")]
+    RecBole,
+    /// MovieLens-style `ratings.csv` (and `items.csv`).
+    #[display("movielens")]
+    MovieLens,
+    /// Matrix Market coordinate matrix.
+    #[display("matrix-market")]
+    MatrixMarket,
+    /// SciPy-compatible compressed sparse row arrays in a `.npz` file.
+    #[display("csr")]
+    Csr,
+}
+
+#[derive(Args, Debug)]
+#[command(name = "export-interactions")]
+/// Export interactions to recommender toolkit formats.
+pub struct ExportInteractions {
+    /// The output format (recbole, movielens, matrix-market, or csr).
+    #[arg(short = 'f', long = "format", name = "FORMAT")]
+    format: ExportFormat,
+
+    /// The dataset name for RecBole atomic files.
+    #[arg(long = "name", default_value = "bookdata")]
+    name: String,
+
+    /// Add item gender from a cluster gender file.
+    #[arg(long = "item-genders", name = "GENDERS")]
+    item_genders: Option<PathBuf>,
+
+    /// Add item title, year, and language from a cluster info file.
+    #[arg(long = "item-info", name = "INFO")]
+    item_info: Option<PathBuf>,
+
+    /// The output directory.
+    #[arg(short = 'o', long = "output", name = "DIR")]
+    output: PathBuf,
+
+    /// The interaction file to export.
+    #[arg(name = "INPUT")]
+    input: PathBuf,
+}
+
+/// A single interaction with dense user and item indices.
+struct Row {
+    user: u32,
+    item: u32,
+    rating: Option<f32>,
+    time: Option<i64>,
+}
+
+/// Find the first of a list of candidate columns in a schema.
+fn find_column(schema: &Schema, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find(|n| schema.contains(n))
+        .map(|n| n.to_string())
+}
+
+/// Build a dense index for the distinct values of a column.
+fn dense_index(actions: &LazyFrame, column: &str, id: &str, idx: &str) -> LazyFrame {
+    actions
+        .clone()
+        .select([col(column).alias(id)])
+        .unique(None, UniqueKeepStrategy::Any)
+        .sort(id, SortOptions::default())
+        .with_row_index(idx, None)
+}
+
+/// Iterate over the rows of a densified interaction frame.
+fn interaction_rows(df: &DataFrame) -> Result<impl Iterator<Item = Row> + '_> {
+    let users = df.column("user_idx")?.idx()?;
+    let items = df.column("item_idx")?.idx()?;
+    let ratings: Box<dyn Iterator<Item = Option<f32>>> = match df.column("rating") {
+        Ok(c) => Box::new(c.f32()?.into_iter()),
+        Err(_) => Box::new(std::iter::repeat(None)),
+    };
+    let times: Box<dyn Iterator<Item = Option<i64>>> = match df.column("timestamp") {
+        Ok(c) => Box::new(c.i64()?.into_iter()),
+        Err(_) => Box::new(std::iter::repeat(None)),
+    };
+    Ok(users
+        .into_no_null_iter()
+        .zip(items.into_no_null_iter())
+        .zip(ratings.zip(times))
+        .map(|((user, item), (rating, time))| Row {
+            user,
+            item,
+            rating,
+            time,
+        }))
+}
+
+/// Write densified interactions as delimited text.
+///
+/// `names` gives the header names of the user, item, rating, and timestamp
+/// columns.  The rating and timestamp fields are written whenever their columns
+/// are present, and left empty for null values, so every row has the same
+/// number of fields.
+fn write_delimited<W: Write>(
+    out: &mut W,
+    df: &DataFrame,
+    names: [&str; 4],
+    sep: &str,
+) -> Result<()> {
+    let has_rating = df.column("rating").is_ok();
+    let has_time = df.column("timestamp").is_ok();
+    let [user_name, item_name, rating_name, time_name] = names;
+    let mut header = vec![user_name, item_name];
+    if has_rating {
+        header.push(rating_name);
+    }
+    if has_time {
+        header.push(time_name);
+    }
+    writeln!(out, "{}", header.join(sep))?;
+    for row in interaction_rows(df)? {
+        write!(out, "{}{}{}", row.user, sep, row.item)?;
+        if has_rating {
+            let rating = row.rating.map(|r| r.to_string()).unwrap_or_default();
+            write!(out, "{}{}", sep, rating)?;
+        }
+        if has_time {
+            let time = row.time.map(|t| t.to_string()).unwrap_or_default();
+            write!(out, "{}{}", sep, time)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Write densified interactions as a Matrix Market coordinate matrix.
+///
+/// Interactions are written as a `real` matrix if there is a rating column, and
+/// a `pattern` matrix otherwise.  As in the [CSR arrays][csr_arrays], actions
+/// without ratings have the value 1, so the two formats load the same matrix.
+fn write_mtx<W: Write>(out: &mut W, df: &DataFrame, n_users: usize, n_items: usize) -> Result<()> {
+    let rated = df.column("rating").is_ok();
+    writeln!(
+        out,
+        "%%MatrixMarket matrix coordinate {} general",
+        if rated { "real" } else { "pattern" }
+    )?;
+    writeln!(
+        out,
+        "% rows are users, columns are items; see user-index.parquet and item-index.parquet"
+    )?;
+    writeln!(out, "{} {} {}", n_users, n_items, df.height())?;
+    for row in interaction_rows(df)? {
+        // Matrix Market indices are 1-based
+        write!(out, "{} {}", row.user + 1, row.item + 1)?;
+        if rated {
+            write!(out, " {}", row.rating.unwrap_or(1.0))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Get the side information columns of the item index, as strings.
+fn item_side_columns(items: &DataFrame) -> Result<Vec<Series>> {
+    let mut cols = Vec::new();
+    for c in items.get_columns() {
+        if c.name() != "item_idx" && c.name() != "item_id" {
+            cols.push(c.cast(&DataType::String)?);
+        }
+    }
+    Ok(cols)
+}
+
+/// Clean a value for a tab-separated file without quoting.
+fn tsv_value(v: Option<&str>) -> String {
+    v.unwrap_or_default().replace(['\t', '\n', '\r'], " ")
+}
+
+/// Build CSR arrays (row pointers, column indices, and values) from interactions
+/// sorted by user.  Actions without ratings have the value 1.
+fn csr_arrays(df: &DataFrame, n_users: usize) -> Result<(Vec<i64>, Vec<i32>, Vec<f32>)> {
+    let mut indptr = vec![0i64; n_users + 1];
+    let mut indices = Vec::with_capacity(df.height());
+    let mut data = Vec::with_capacity(df.height());
+    for row in interaction_rows(df)? {
+        indptr[row.user as usize + 1] += 1;
+        indices.push(row.item as i32);
+        data.push(row.rating.unwrap_or(1.0));
+    }
+    for i in 1..indptr.len() {
+        indptr[i] += indptr[i - 1];
+    }
+    Ok((indptr, indices, data))
+}
+
+/// Encode an array in NumPy `.npy` format.
+fn npy_bytes(descr: &str, shape: &[usize], data: &[u8]) -> Vec<u8> {
+    let shape = match shape {
+        [] => "()".to_string(),
+        [n] => format!("({},)", n),
+        dims => {
+            let dims: Vec<String> = dims.iter().map(|d| d.to_string()).collect();
+            format!("({})", dims.join(", "))
+        }
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // the magic, version, and length take 10 bytes, and the header ends with a
+    // newline; the total must be a multiple of 64
+    let pad = (64 - (10 + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(pad));
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(10 + header.len() + data.len());
+    out.extend_from_slice(b"\x93NUMPY\x01\x00");
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+impl ExportInteractions {
+    /// Add the requested side information to the item index.
+    fn item_side_info(&self, mut items: LazyFrame) -> Result<LazyFrame> {
+        if let Some(path) = &self.item_info {
+            info!("reading item info from {:?}", path);
+            let info = scan_df_parquet(path)?;
+            let schema = info.schema()?;
+            let mut cols = vec![col("cluster")];
+            cols.extend(
+                INFO_COLUMNS
+                    .iter()
+                    .filter(|c| schema.contains(c))
+                    .map(|c| col(c)),
+            );
+            items = items.left_join(info.select(cols), col("item_id"), col("cluster"));
+        }
+        if let Some(path) = &self.item_genders {
+            info!("reading item genders from {:?}", path);
+            let genders = scan_df_parquet(path)?.select([col("cluster"), col("gender")]);
+            items = items.left_join(genders, col("item_id"), col("cluster"));
+        }
+        Ok(items)
+    }
+
+    fn write_recbole(&self, df: &DataFrame, items: &DataFrame) -> Result<()> {
+        let path = self.output.join(format!("{}.inter", self.name));
+        info!("writing RecBole interactions to {:?}", path);
+        let mut out = BufWriter::new(File::create(&path)?);
+        let names = [
+            "user_id:token",
+            "item_id:token",
+            "rating:float",
+            "timestamp:float",
+        ];
+        write_delimited(&mut out, df, names, "\t")?;
+        out.flush()?;
+
+        let side = item_side_columns(items)?;
+        if side.is_empty() {
+            return Ok(());
+        }
+        let path = self.output.join(format!("{}.item", self.name));
+        info!("writing RecBole item features to {:?}", path);
+        let mut out = BufWriter::new(File::create(&path)?);
+        let mut header = vec!["item_id:token".to_string()];
+        for (s, orig) in side.iter().zip(items.get_columns().iter().skip(2)) {
+            let kind = if s.name() == "title" {
+                "token_seq"
+            } else if orig.dtype().is_numeric() {
+                "float"
+            } else {
+                "token"
+            };
+            header.push(format!("{}:{}", s.name(), kind));
+        }
+        writeln!(out, "{}", header.join("\t"))?;
+        let idx = items.column("item_idx")?.idx()?;
+        let mut iters: Vec<_> = side
+            .iter()
+            .map(|s| s.str().map(|s| s.into_iter()))
+            .collect::<PolarsResult<_>>()?;
+        for i in idx.into_no_null_iter() {
+            write!(out, "{}", i)?;
+            for it in iters.iter_mut() {
+                write!(out, "\t{}", tsv_value(it.next().flatten()))?;
+            }
+            writeln!(out)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    fn write_movielens(&self, df: &DataFrame, items: &DataFrame) -> Result<()> {
+        let path = self.output.join("ratings.csv");
+        info!("writing MovieLens ratings to {:?}", path);
+        let mut out = BufWriter::new(File::create(&path)?);
+        let names = ["userId", "movieId", "rating", "timestamp"];
+        write_delimited(&mut out, df, names, ",")?;
+        out.flush()?;
+
+        let side = item_side_columns(items)?;
+        if side.is_empty() {
+            return Ok(());
+        }
+        let path = self.output.join("items.csv");
+        info!("writing MovieLens item features to {:?}", path);
+        let mut out = csv::Writer::from_path(&path)?;
+        let mut header = vec!["movieId"];
+        header.extend(side.iter().map(|s| s.name()));
+        out.write_record(&header)?;
+        let idx = items.column("item_idx")?.idx()?;
+        let mut iters: Vec<_> = side
+            .iter()
+            .map(|s| s.str().map(|s| s.into_iter()))
+            .collect::<PolarsResult<_>>()?;
+        for i in idx.into_no_null_iter() {
+            let mut rec = vec![i.to_string()];
+            for it in iters.iter_mut() {
+                rec.push(it.next().flatten().unwrap_or_default().to_string());
+            }
+            out.write_record(&rec)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    fn write_matrix_market(&self, df: &DataFrame, n_users: usize, n_items: usize) -> Result<()> {
+        let path = self.output.join("interactions.mtx");
+        info!("writing Matrix Market file {:?}", path);
+        let mut out = BufWriter::new(File::create(&path)?);
+        write_mtx(&mut out, df, n_users, n_items)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn write_csr(&self, df: &DataFrame, n_users: usize, n_items: usize) -> Result<()> {
+        let path = self.output.join("interactions.npz");
+        info!("writing CSR arrays to {:?}", path);
+        let (indptr, indices, data) = csr_arrays(df, n_users)?;
+
+        let arrays = [
+            (
+                "indptr.npy",
+                npy_bytes(
+                    "<i8",
+                    &[indptr.len()],
+                    &indptr
+                        .iter()
+                        .flat_map(|v| v.to_le_bytes())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (
+                "indices.npy",
+                npy_bytes(
+                    "<i4",
+                    &[indices.len()],
+                    &indices
+                        .iter()
+                        .flat_map(|v| v.to_le_bytes())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (
+                "data.npy",
+                npy_bytes(
+                    "<f4",
+                    &[data.len()],
+                    &data
+                        .iter()
+                        .flat_map(|v| v.to_le_bytes())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (
+                "shape.npy",
+                npy_bytes(
+                    "<i8",
+                    &[2],
+                    &[n_users as i64, n_items as i64]
+                        .iter()
+                        .flat_map(|v| v.to_le_bytes())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (
+                "format.npy",
+                npy_bytes(
+                    "<U3",
+                    &[],
+                    &"csr"
+                        .chars()
+                        .flat_map(|c| (c as u32).to_le_bytes())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+        ];
+
+        let mut zip = ZipWriter::new(File::create(&path)?);
+        for (name, bytes) in arrays {
+            let opts = FileOptions::default()
+                .compression_method(CompressionMethod::Stored)
+                .large_file(bytes.len() >= u32::MAX as usize);
+            zip.start_file(name, opts)?;
+            zip.write_all(&bytes)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+impl Command for ExportInteractions {
+    fn exec(&self) -> Result<()> {
+        let actions = scan_df_parquet(&self.input)?;
+        let schema = actions.schema()?;
+        let user_col = find_column(&schema, USER_COLUMNS)
+            .ok_or_else(|| anyhow!("{:?}: no user column", self.input))?;
+        let item_col = find_column(&schema, ITEM_COLUMNS)
+            .ok_or_else(|| anyhow!("{:?}: no item column", self.input))?;
+
+        let mut cols = vec![
+            col(&user_col).alias("user_id"),
+            col(&item_col).alias("item_id"),
+        ];
+        let mut out_cols = vec![col("user_idx"), col("item_idx")];
+        if schema.contains("rating") {
+            cols.push(col("rating").cast(DataType::Float32));
+            out_cols.push(col("rating"));
+        }
+        if let Some(tc) = find_column(&schema, TIME_COLUMNS) {
+            info!("using timestamps from {}", tc);
+            cols.push(col(&tc).cast(DataType::Int64).alias("timestamp"));
+            out_cols.push(col("timestamp"));
+        }
+
+        let users = dense_index(&actions, &user_col, "user_id", "user_idx");
+        let items = dense_index(&actions, &item_col, "item_id", "item_idx");
+        let actions = actions
+            .select(cols)
+            .inner_join(users.clone(), col("user_id"), col("user_id"))
+            .inner_join(items.clone(), col("item_id"), col("item_id"))
+            .select(out_cols)
+            .sort_by_exprs(
+                [col("user_idx"), col("item_idx")],
+                [false, false],
+                false,
+                false,
+            );
+
+        info!("collecting interactions from {:?}", self.input);
+        let actions = actions.collect()?;
+        let users = users.collect()?;
+        let items = self
+            .item_side_info(items)?
+            .sort("item_idx", SortOptions::default())
+            .collect()?;
+        info!(
+            "exporting {} interactions from {} users and {} items",
+            friendly::scalar(actions.height()),
+            friendly::scalar(users.height()),
+            friendly::scalar(items.height())
+        );
+
+        create_dir_all(&self.output)?;
+        match self.format {
+            ExportFormat::RecBole => self.write_recbole(&actions, &items)?,
+            ExportFormat::MovieLens => self.write_movielens(&actions, &items)?,
+            ExportFormat::MatrixMarket => {
+                self.write_matrix_market(&actions, users.height(), items.height())?
+            }
+            ExportFormat::Csr => self.write_csr(&actions, users.height(), items.height())?,
+        }
+
+        let (n_users, n_items) = (users.height(), items.height());
+        save_df_parquet(users, self.output.join("user-index.parquet"))?;
+        save_df_parquet(items, self.output.join("item-index.parquet"))?;
+        info!(
+            "wrote index maps for {} users and {} items",
+            n_users, n_items
+        );
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_format_names() {
+    for name in ["recbole", "movielens", "matrix-market", "csr"] {
+        let format: ExportFormat = name.parse().unwrap();
+        assert_eq!(format.to_string(), name);
+    }
+}
+
+#[test]
+fn test_npy_header() {
+    let bytes = npy_bytes("<i4", &[2], &[1, 0, 0, 0, 2, 0, 0, 0]);
+    let hlen = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    assert_eq!((10 + hlen) % 64, 0);
+    assert_eq!(bytes[10 + hlen - 1], b'\n');
+    assert!(std::str::from_utf8(&bytes[10..10 + hlen])
+        .unwrap()
+        .contains("'shape': (2,)"));
+    assert_eq!(bytes.len(), 10 + hlen + 8);
+}
+
+#[test]
+fn test_csr_arrays() {
+    let df = df!(
+        "user_idx" => [0 as IdxSize, 0, 2],
+        "item_idx" => [1 as IdxSize, 3, 0],
+        "rating" => [4.0f32, 3.5, 5.0]
+    )
+    .unwrap();
+    let (indptr, indices, data) = csr_arrays(&df, 3).unwrap();
+    assert_eq!(indptr, vec![0, 2, 2, 3]);
+    assert_eq!(indices, vec![1, 3, 0]);
+    assert_eq!(data, vec![4.0, 3.5, 5.0]);
+}
+
+#[test]
+fn test_write_null_rating() {
+    let df = df!(
+        "user_idx" => [0 as IdxSize, 1],
+        "item_idx" => [1 as IdxSize, 0],
+        "rating" => [Some(4.5f32), None],
+        "timestamp" => [100i64, 200]
+    )
+    .unwrap();
+    let mut out = Vec::new();
+    write_delimited(&mut out, &df, ["user", "item", "rating", "time"], ",").unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text, "user,item,rating,time\n0,1,4.5,100\n1,0,,200\n");
+}
+
+#[test]
+fn test_write_mtx() {
+    let df = df!(
+        "user_idx" => [0 as IdxSize, 1],
+        "item_idx" => [1 as IdxSize, 0],
+        "rating" => [Some(4.5f32), None]
+    )
+    .unwrap();
+    let mut out = Vec::new();
+    write_mtx(&mut out, &df, 2, 3).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines[0], "%%MatrixMarket matrix coordinate real general");
+    // the unrated action has the same value as in the CSR arrays
+    assert_eq!(&lines[2..], ["2 3 2", "1 2 4.5", "2 1 1"]);
+    let (_, _, data) = csr_arrays(&df, 2).unwrap();
+    assert_eq!(data, vec![4.5, 1.0]);
+
+    let df = df.drop("rating").unwrap();
+    let mut out = Vec::new();
+    write_mtx(&mut out, &df, 2, 3).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines[0], "%%MatrixMarket matrix coordinate pattern general");
+    assert_eq!(&lines[2..], ["2 3 2", "1 2", "2 1"]);
+}
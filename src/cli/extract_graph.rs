@@ -2,10 +2,15 @@
 use std::convert::From;
 use std::path::PathBuf;
 
-use crate::graph::{load_graph, save_gml};
+use crate::graph::{find_nodes, khop_subgraph, load_graph, save_graph_as, GraphFormat, NodeRef};
 use crate::prelude::*;
 
 /// Extract a subgraph.
+///
+/// The subgraph can be selected by cluster, or by the neighborhood of one or more
+/// seed nodes.  Seeds are specified as `NS:ID` (e.g. `GR-B:42`), `NS:KEY` for nodes
+/// with labels (e.g. `OL-W:/works/OL45883W`), or a bare ISBN; `--seed-code` accepts
+/// raw book codes.
 #[derive(Args, Debug)]
 #[command(name = "extract-graph")]
 pub struct ExtractGraph {
@@ -15,6 +20,23 @@ pub struct ExtractGraph {
     #[arg(short = 'c', long = "cluster")]
     cluster: Option<i32>,
 
+    /// Extract the neighborhood of SEED.
+    #[arg(short = 's', long = "seed", name = "SEED")]
+    seeds: Vec<String>,
+
+    /// Extract the neighborhood of the node with book code CODE.
+    #[arg(long = "seed-code", name = "CODE")]
+    seed_codes: Vec<i32>,
+
+    /// Include nodes within HOPS edges of the seeds.
+    #[arg(short = 'k', long = "hops", name = "HOPS", default_value = "2")]
+    hops: u32,
+
+    /// Output format (gml, graphml, dot, or parquet); defaults based on output extension.
+    #[arg(short = 'f', long = "format", name = "FORMAT")]
+    format: Option<GraphFormat>,
+
+    /// Write the graph to OUTPUT (a directory for Parquet output).
     #[arg(long = "output", short = 'o')]
     out_file: Option<PathBuf>,
 }
@@ -36,10 +58,42 @@ impl Command for ExtractGraph {
             });
         }
 
-        info!("filtered graph to {} nodes", graph.node_count());
+        if !self.seeds.is_empty() || !self.seed_codes.is_empty() {
+            let mut refs = self
+                .seeds
+                .iter()
+                .map(|s| NodeRef::parse(s.as_str()))
+                .collect::<Result<Vec<_>>>()?;
+            refs.extend(self.seed_codes.iter().map(|c| NodeRef::Code(*c)));
+            let found = find_nodes(&graph, &refs);
+            let mut seeds = Vec::with_capacity(refs.len());
+            for r in &refs {
+                if let Some(n) = found.get(r) {
+                    seeds.push(*n);
+                } else {
+                    warn!("seed {:?} not found in graph", r);
+                }
+            }
+            if seeds.is_empty() {
+                return Err(anyhow!("no seed nodes found in graph"));
+            }
+            info!(
+                "extracting {}-hop neighborhood of {} seeds",
+                self.hops,
+                seeds.len()
+            );
+            graph = khop_subgraph(&graph, &seeds, self.hops);
+        }
+
+        info!(
+            "filtered graph to {} nodes and {} edges",
+            graph.node_count(),
+            graph.edge_count()
+        );
 
         if let Some(outf) = &self.out_file {
-            save_gml(&graph, outf)?;
+            let format = self.format.unwrap_or_else(|| GraphFormat::from_path(outf));
+            save_graph_as(&graph, outf, format)?;
         }
 
         Ok(())
@@ -3,8 +3,8 @@ use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use arrow::array::StringBuilder;
 use arrow::array::UInt32Builder;
+use arrow::array::{ArrayRef, StringBuilder};
 use arrow::datatypes::{DataType, Field, Schema};
 use arrow::record_batch::RecordBatch;
 use friendly::scalar;
@@ -44,6 +44,10 @@ struct FilterSpec {
     #[arg(short = 'f', long = "subfield", name = "CODE")]
     subfield: Option<char>,
 
+    /// Record the contents of a following subfield (e.g. `2`) as each value's source.
+    #[arg(short = 's', long = "source-subfield", name = "SRC_CODE")]
+    source_subfield: Option<char>,
+
     /// Trim the contents before emitting.
     #[arg(short = 'T', long = "trim")]
     trim: bool,
@@ -65,6 +69,29 @@ struct OutputSpec {
     file: PathBuf,
 }
 
+/// A filtered field value, with its source if requested.
+struct FilteredField {
+    rec_id: u32,
+    contents: String,
+    source: Option<String>,
+}
+
+/// A matched field waiting for its source subfields.
+struct PendingField {
+    rec: FieldRecord,
+    last_fld_no: u32,
+    n_sources: usize,
+}
+
+impl PendingField {
+    /// Check whether a field record continues this field.
+    fn continued_by(&self, rec: &FieldRecord) -> bool {
+        rec.rec_id == self.rec.rec_id
+            && rec.tag == self.rec.tag
+            && rec.fld_no == self.last_fld_no + 1
+    }
+}
+
 impl FilterSpec {
     fn matches(&self, rec: &FieldRecord) -> bool {
         if let Some(t) = &self.tag {
@@ -100,28 +127,30 @@ impl FilterSpec {
 }
 
 struct FilterOutput<W: ObjectWriter<RecordBatch>> {
+    with_source: bool,
     schema: Arc<Schema>,
     writer: W,
 }
 
-impl<W: ObjectWriter<RecordBatch>> ObjectWriter<Vec<FieldRecord>> for FilterOutput<W> {
-    fn write_object(&mut self, object: Vec<FieldRecord>) -> Result<()> {
+impl<W: ObjectWriter<RecordBatch>> ObjectWriter<Vec<FilteredField>> for FilterOutput<W> {
+    fn write_object(&mut self, object: Vec<FilteredField>) -> Result<()> {
         let size = object.len();
 
         let mut id_col = UInt32Builder::with_capacity(size);
         let mut val_col = StringBuilder::with_capacity(size, size * 10);
+        let mut src_col = StringBuilder::with_capacity(size, size * 4);
 
         for rec in object {
             id_col.append_value(rec.rec_id);
             val_col.append_value(rec.contents);
+            src_col.append_option(rec.source);
         }
 
-        let id_col = id_col.finish();
-        let val_col = val_col.finish();
-        let batch = RecordBatch::try_new(
-            self.schema.clone(),
-            vec![Arc::new(id_col), Arc::new(val_col)],
-        )?;
+        let mut cols: Vec<ArrayRef> = vec![Arc::new(id_col.finish()), Arc::new(val_col.finish())];
+        if self.with_source {
+            cols.push(Arc::new(src_col.finish()));
+        }
+        let batch = RecordBatch::try_new(self.schema.clone(), cols)?;
 
         self.writer.write_object(batch)?;
         Ok(())
@@ -139,22 +168,70 @@ impl<W: ObjectWriter<RecordBatch>> ObjectWriter<Vec<FieldRecord>> for FilterOutp
 fn scan_records(
     path: &Path,
     filter: &FilterSpec,
-    out: impl ObjectWriter<FieldRecord> + Send,
+    out: impl ObjectWriter<FilteredField> + Send,
 ) -> Result<(usize, usize)> {
     info!("reading names from authority fields in {:?}", path);
     let scanner = scan_parquet_file(path)?;
     let mut out = out;
 
-    let scanner = scanner;
     let mut nr = 0;
     let mut nw = 0;
+    let mut pending: Option<PendingField> = None;
     for rec in scanner {
         nr += 1;
         let mut rec: FieldRecord = rec?;
+
+        // attach source subfields to the pending matched field
+        if let Some(mut p) = pending.take() {
+            if p.continued_by(&rec) && !filter.matches(&rec) {
+                p.last_fld_no = rec.fld_no;
+                if filter.source_subfield.map(|c| c as u8) == Some(rec.sf_code) {
+                    nw += 1;
+                    p.n_sources += 1;
+                    out.write_object(FilteredField {
+                        rec_id: p.rec.rec_id,
+                        contents: p.rec.contents.clone(),
+                        source: Some(rec.contents.trim().to_string()),
+                    })?;
+                }
+                pending = Some(p);
+                continue;
+            } else if p.n_sources == 0 {
+                nw += 1;
+                out.write_object(FilteredField {
+                    rec_id: p.rec.rec_id,
+                    contents: p.rec.contents,
+                    source: None,
+                })?;
+            }
+        }
+
         if filter.matches(&rec) {
-            nw += 1;
             rec.contents = filter.transform(rec.contents.as_str()).into();
-            out.write_object(rec)?;
+            if filter.source_subfield.is_some() {
+                pending = Some(PendingField {
+                    last_fld_no: rec.fld_no,
+                    rec,
+                    n_sources: 0,
+                });
+            } else {
+                nw += 1;
+                out.write_object(FilteredField {
+                    rec_id: rec.rec_id,
+                    contents: rec.contents,
+                    source: None,
+                })?;
+            }
+        }
+    }
+    if let Some(p) = pending {
+        if p.n_sources == 0 {
+            nw += 1;
+            out.write_object(FilteredField {
+                rec_id: p.rec.rec_id,
+                contents: p.rec.contents,
+                source: None,
+            })?;
         }
     }
     debug!("finished scanning parquet");
@@ -163,17 +240,24 @@ fn scan_records(
 }
 
 /// Create an output for the records.
-fn open_output(out: &OutputSpec) -> Result<impl ObjectWriter<FieldRecord> + Send> {
+fn open_output(
+    out: &OutputSpec,
+    with_source: bool,
+) -> Result<impl ObjectWriter<FilteredField> + Send> {
     info!("writing output to {:?}", out.file);
     let out_name = out
         .content_name
         .as_ref()
         .map(|s| s.clone())
         .unwrap_or("content".into());
-    let schema = Schema::new(vec![
+    let mut fields = vec![
         Field::new("rec_id", DataType::UInt32, false),
         Field::new(&out_name, DataType::Utf8, false),
-    ]);
+    ];
+    if with_source {
+        fields.push(Field::new("source", DataType::Utf8, true));
+    }
+    let schema = Schema::new(fields);
     let schema = Arc::new(schema);
 
     // we'll open the file early, so bg open failures are only in Parquet.
@@ -186,7 +270,11 @@ fn open_output(out: &OutputSpec) -> Result<impl ObjectWriter<FieldRecord> + Send
     let writer = ThreadObjectWriter::bg_open(move || {
         let props = parquet_writer_defaults().set_column_dictionary_enabled(out_name.into(), true);
         let writer = ArrowWriter::try_new(file, schema.clone(), Some(props.build()))?;
-        Ok(FilterOutput { schema, writer })
+        Ok(FilterOutput {
+            with_source,
+            schema,
+            writer,
+        })
     })
     .spawn();
     let writer = UnchunkWriter::with_size(writer, BATCH_SIZE);
@@ -196,7 +284,7 @@ fn open_output(out: &OutputSpec) -> Result<impl ObjectWriter<FieldRecord> + Send
 
 impl Command for FilterMARC {
     fn exec(&self) -> Result<()> {
-        let out = open_output(&self.output)?;
+        let out = open_output(&self.output, self.filter.source_subfield.is_some())?;
         let (nr, nw) = scan_records(self.field_file.as_path(), &self.filter, out)?;
 
         info!("wrote {} out of {} records", scalar(nw), scalar(nr));
@@ -4,6 +4,7 @@ use clap::Args;
 
 use crate::arrow::*;
 use crate::ids::codes::{NS_GR_BOOK, NS_GR_WORK};
+use crate::interactions::normalize_ratings;
 use crate::prelude::*;
 
 use polars::prelude::*;
@@ -102,6 +103,11 @@ impl ClusterOp {
             .agg(self.aggregates());
 
         let actions = self.maybe_integrate_ratings(actions, &interactions);
+        let actions = if self.actions == ActionType::Ratings {
+            normalize_ratings(actions, "goodreads")?
+        } else {
+            actions
+        };
         let actions = actions.sort("first_time", SortOptions::default());
 
         debug!("logical plan: {:?}", actions.describe_plan());
@@ -160,6 +166,7 @@ impl ClusterOp {
         frame.select(&[
             col("user_id"),
             self.id_col().alias("item_id"),
+            // older interaction files stored timestamps as f32, so cast to be safe
             (col("updated").cast(DataType::Int64)).alias("timestamp"),
             col("rating"),
         ])
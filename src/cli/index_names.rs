@@ -29,6 +29,14 @@ pub struct IndexNames {
     #[arg(long = "marc-authorities", name = "FILE")]
     marc_authorities: Option<PathBuf>,
 
+    /// Also index corporate names (MARC 710).
+    #[arg(long = "corporate")]
+    corporate: bool,
+
+    /// Also index surname-and-initials keys of personal names.
+    #[arg(long = "initials")]
+    initials: bool,
+
     /// Index output Parquet file.
     #[arg(name = "OUTFILE")]
     outfile: PathBuf,
@@ -36,6 +44,9 @@ pub struct IndexNames {
 
 type NameIndex = HashMap<String, HashSet<u32>>;
 
+/// A name to index, with its record ID and whether it is a corporate name.
+type NameMsg = (String, u32, bool);
+
 #[derive(ParquetRecordWriter, Serialize, Clone)]
 struct IndexEntry {
     rec_id: u32,
@@ -44,7 +55,8 @@ struct IndexEntry {
 
 fn scan_authority_names(
     path: &Path,
-    send: Sender<(String, u32)>,
+    corporate: bool,
+    send: Sender<NameMsg>,
 ) -> Result<JoinHandle<Result<usize>>> {
     info!("reading names from authority fields in {:?}", path);
     let scanner = scan_parquet_file(path)?;
@@ -56,7 +68,10 @@ fn scan_authority_names(
         for rec in pb.wrap_iter(scanner) {
             let rec: FieldRecord = rec?;
             if rec.tag == 700 && rec.sf_code == b'a' {
-                send.send((rec.contents, rec.rec_id))?;
+                send.send((rec.contents, rec.rec_id, false))?;
+                n += 1;
+            } else if corporate && rec.tag == 710 && rec.sf_code == b'a' {
+                send.send((rec.contents, rec.rec_id, true))?;
                 n += 1;
             }
         }
@@ -65,16 +80,36 @@ fn scan_authority_names(
     }))
 }
 
-fn process_names(recv: Receiver<(String, u32)>) -> Result<NameIndex> {
+fn process_names(recv: Receiver<NameMsg>, initials: bool) -> Result<NameIndex> {
     let mut index = NameIndex::new();
+    let mut scripts: HashMap<Script, usize> = HashMap::new();
 
-    // process results and add to list
-    for (src, rec_id) in recv {
-        for name in name_variants(&src)? {
+    // process results and add to list; variants include romanized and
+    // accent-folded forms, so names in other scripts match romanized names
+    for (src, rec_id, corporate) in recv {
+        if let Some(script) = detect_script(&src) {
+            *scripts.entry(script).or_default() += 1;
+        }
+        let variants = if corporate {
+            corporate_name_variants(&src)?
+        } else {
+            name_variants(&src)?
+        };
+        for name in variants {
             index.entry(name).or_default().insert(rec_id);
         }
+        if initials && !corporate {
+            if let Some(key) = initials_key(&src) {
+                index.entry(key).or_default().insert(rec_id);
+            }
+        }
     }
 
+    let mut scripts: Vec<_> = scripts.into_iter().collect();
+    scripts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    for (script, n) in scripts {
+        info!("found {} names in {} script", n, script);
+    }
     info!("index {} names", index.len());
     Ok(index)
 }
@@ -126,12 +161,12 @@ impl Command for IndexNames {
     fn exec(&self) -> Result<()> {
         let (send, recv) = bounded(4096);
         let h = if let Some(ref path) = self.marc_authorities {
-            scan_authority_names(path.as_path(), send)?
+            scan_authority_names(path.as_path(), self.corporate, send)?
         } else {
             return Err(anyhow!("no name source specified"));
         };
 
-        let names = process_names(recv)?;
+        let names = process_names(recv, self.initials)?;
         let nr = h.join().expect("thread join error")?;
         info!("scanned {} name records", nr);
 
@@ -140,3 +175,17 @@ impl Command for IndexNames {
         Ok(())
     }
 }
+
+#[test]
+fn test_process_initials() {
+    let (send, recv) = bounded(4);
+    send.send(("Tolkien, J. R. R.".to_string(), 1, false))
+        .unwrap();
+    send.send(("Tolkien, John Ronald Reuel".to_string(), 2, false))
+        .unwrap();
+    drop(send);
+    let index = process_names(recv, true).unwrap();
+    let key = initials_key("Tolkien, J. R. R.").unwrap();
+    assert_eq!(index[&key], HashSet::from([1, 2]));
+    assert!(index.contains_key("Tolkien, John Ronald Reuel"));
+}
@@ -1,12 +1,17 @@
 use std::fs::File;
+use std::io::Write;
 
 use chrono::NaiveDate;
 use clap::Args;
+use serde::Serialize;
 
 use crate::prelude::*;
 use polars::prelude::*;
 
 /// Compute k-cores of interaction records.
+///
+/// The user, item, and time columns are configurable, so this works on any of
+/// the interaction tables (e.g. `timestamp` for the raw Amazon ratings).
 #[derive(Debug, Clone, Args)]
 #[command(name = "kcore")]
 pub struct Kcore {
@@ -22,6 +27,25 @@ pub struct Kcore {
     #[arg(short = 'I', long = "item-k")]
     item_k: Option<u32>,
 
+    /// The user ID column.
+    #[arg(long = "user-col", default_value = "user_id")]
+    user_col: String,
+
+    /// The item ID column.
+    #[arg(long = "item-col", default_value = "item_id")]
+    item_col: String,
+
+    /// The timestamp column (UNIX seconds) for date filtering.
+    #[arg(long = "time-col", default_value = "first_time")]
+    time_col: String,
+
+    /// Only count interactions with an explicit rating in COL.
+    ///
+    /// Interactions without ratings are kept, but do not count towards the
+    /// core thresholds.
+    #[arg(long = "rating-col", name = "COL")]
+    rating_col: Option<String>,
+
     /// Limit to ratings in a particular year.
     #[arg(long = "year")]
     year: Option<i32>,
@@ -34,6 +58,10 @@ pub struct Kcore {
     #[arg(long = "end-date")]
     end: Option<NaiveDate>,
 
+    /// Write a JSON report of the filtering process to REPORT.
+    #[arg(long = "report", name = "REPORT")]
+    report: Option<PathBuf>,
+
     /// The output file.
     #[arg(short = 'o', long = "output", name = "FILE")]
     output: PathBuf,
@@ -43,6 +71,33 @@ pub struct Kcore {
     input: PathBuf,
 }
 
+/// Report of the k-core filtering process.
+#[derive(Debug, Serialize)]
+struct KcoreReport {
+    input: PathBuf,
+    user_k: u32,
+    item_k: u32,
+    rating_col: Option<String>,
+    /// Number of actions in the input file.
+    initial_actions: usize,
+    /// Number of actions after date filtering.
+    dated_actions: usize,
+    passes: Vec<PassReport>,
+    users: usize,
+    items: usize,
+    actions: usize,
+}
+
+/// Removals from a single filtering pass over users or items.
+#[derive(Debug, Serialize)]
+struct PassReport {
+    pass: usize,
+    column: String,
+    removed_ids: usize,
+    removed_actions: usize,
+    remaining_actions: usize,
+}
+
 impl Command for Kcore {
     fn exec(&self) -> Result<()> {
         let uk = self.user_k.unwrap_or(self.k);
@@ -53,10 +108,15 @@ impl Command for Kcore {
             ik,
             self.input.display()
         );
+        let rating = self.rating_col.as_deref();
+        if let Some(rc) = rating {
+            info!("counting only actions with ratings in {}", rc);
+        }
 
         let file = File::open(&self.input)?;
         let mut actions = ParquetReader::new(file).finish()?;
         info!("loaded {} actions", friendly::scalar(actions.height()));
+        let n_initial = actions.height();
 
         let start = self
             .start
@@ -69,8 +129,7 @@ impl Command for Kcore {
         if let Some(start) = start {
             info!("removing actions before {}", start);
             let start = start.and_hms_opt(0, 0, 0).unwrap().timestamp();
-            // currently hard-coded for goodreads
-            let col = actions.column("first_time")?;
+            let col = actions.column(&self.time_col)?;
             let mask = col.gt_eq(start)?;
             actions = actions.filter(&mask)?;
             info!("filtered to {} actions", friendly::scalar(actions.height()));
@@ -78,72 +137,127 @@ impl Command for Kcore {
         if let Some(end) = end {
             info!("removing actions after {}", end);
             let end = end.and_hms_opt(0, 0, 0).unwrap().timestamp();
-            // currently hard-coded for goodreads
-            let col = actions.column("first_time")?;
+            let col = actions.column(&self.time_col)?;
             let mask = col.lt(end)?;
             actions = actions.filter(&mask)?;
             info!("filtered to {} actions", friendly::scalar(actions.height()));
         }
 
-        let n_initial = actions.height();
+        let mut report = KcoreReport {
+            input: self.input.clone(),
+            user_k: uk,
+            item_k: ik,
+            rating_col: self.rating_col.clone(),
+            initial_actions: n_initial,
+            dated_actions: actions.height(),
+            passes: Vec::new(),
+            users: 0,
+            items: 0,
+            actions: 0,
+        };
+
         let mut n_last = 0;
         let mut iters = 0;
         // we proceed iteratively, alternating filtering users and items
         // stop when a pass has left it unchanged
         while actions.height() != n_last {
             n_last = actions.height();
+            iters += 1;
             info!(
                 "pass {}: checking items of {} actions",
-                iters + 1,
+                iters,
                 friendly::scalar(actions.height())
             );
-            actions = filter_counts(actions, "item_id", ik)?;
+            let (filtered, pass) = filter_counts(actions, &self.item_col, ik, rating)?;
+            actions = filtered;
+            report.passes.push(PassReport {
+                pass: iters,
+                ..pass
+            });
 
             info!(
                 "pass {}: checking users of {} actions",
-                iters + 1,
+                iters,
                 friendly::scalar(actions.height())
             );
-            actions = filter_counts(actions, "user_id", ik)?;
-
-            iters += 1;
+            let (filtered, pass) = filter_counts(actions, &self.user_col, uk, rating)?;
+            actions = filtered;
+            report.passes.push(PassReport {
+                pass: iters,
+                ..pass
+            });
         }
+
+        let counted = counted_actions(&actions, rating)?;
         info!(
-            "finished computing {}-core with {} of {} actions (imin: {}, umin: {})",
-            self.k,
+            "finished computing ({},{})-core with {} of {} actions (imin: {}, umin: {})",
+            uk,
+            ik,
             friendly::scalar(actions.height()),
             friendly::scalar(n_initial),
             // re-compute this in case it changed
-            actions
-                .column("item_id")?
-                .value_counts(true, true)?
-                .column("count")?
-                .min::<u32>()?
-                .unwrap(),
-            actions
-                .column("user_id")?
-                .value_counts(true, true)?
-                .column("count")?
-                .min::<u32>()?
-                .unwrap(),
+            smallest_count(&counted, &self.item_col)?.unwrap_or_default(),
+            smallest_count(&counted, &self.user_col)?.unwrap_or_default(),
         );
 
+        report.users = actions.column(&self.user_col)?.n_unique()?;
+        report.items = actions.column(&self.item_col)?.n_unique()?;
+        report.actions = actions.height();
+
         save_df_parquet(actions, &self.output)?;
 
+        if let Some(path) = &self.report {
+            info!("saving report to {:?}", path);
+            let mut out = File::create(path)?;
+            serde_json::to_writer_pretty(&mut out, &report)?;
+            out.write_all(b"\n")?;
+        }
+
         Ok(())
     }
 }
 
-fn filter_counts(actions: DataFrame, column: &'static str, k: u32) -> Result<DataFrame> {
-    let nstart = actions.height();
+/// Get the actions that count towards the core thresholds.
+fn counted_actions(actions: &DataFrame, rating: Option<&str>) -> Result<DataFrame> {
+    match rating {
+        Some(rc) => {
+            let mask = actions.column(rc)?.is_not_null();
+            Ok(actions.filter(&mask)?)
+        }
+        None => Ok(actions.clone()),
+    }
+}
+
+/// Get the smallest count of a column's values, or `None` if it is empty.
+fn smallest_count(actions: &DataFrame, column: &str) -> Result<Option<u32>> {
     let counts = actions.column(column)?.value_counts(true, true)?;
+    Ok(counts.column("count")?.min()?)
+}
+
+/// Remove actions whose value in `column` has fewer than `k` counted actions.
+fn filter_counts(
+    actions: DataFrame,
+    column: &str,
+    k: u32,
+    rating: Option<&str>,
+) -> Result<(DataFrame, PassReport)> {
+    let nstart = actions.height();
+    let ids_start = actions.column(column)?.n_unique()?;
+    let counted = counted_actions(&actions, rating)?;
+    let counts = counted.column(column)?.value_counts(true, true)?;
     debug!("value count schema: {:?}", counts.schema());
-    let min_count: u32 = counts
-        .column("count")?
-        .min()?
-        .ok_or_else(|| anyhow!("data frame is empty"))?;
-    if min_count < k {
-        info!("filtering {}s (smallest count: {})", column, min_count);
+    // there are no counts if every action was removed, or none were counted
+    let min_count: Option<u32> = counts.column("count")?.min()?;
+
+    // with rating counts, some IDs may have no counted actions at all
+    let actions = if nstart == 0 {
+        actions
+    } else if min_count.map_or(true, |m| m < k) || counts.height() < ids_start {
+        info!(
+            "filtering {}s (smallest count: {})",
+            column,
+            min_count.unwrap_or_default()
+        );
         let ifilt = counts
             .lazy()
             .filter(col("count").gt_eq(lit(k)))
@@ -155,8 +269,123 @@ fn filter_counts(actions: DataFrame, column: &'static str, k: u32) -> Result<Dat
             friendly::scalar(actions.height()),
             nstart - actions.height()
         );
-        Ok(actions)
+        actions
     } else {
-        Ok(actions)
-    }
+        actions
+    };
+
+    let pass = PassReport {
+        pass: 0,
+        column: column.to_string(),
+        removed_ids: ids_start - actions.column(column)?.n_unique()?,
+        removed_actions: nstart - actions.height(),
+        remaining_actions: actions.height(),
+    };
+    Ok((actions, pass))
+}
+
+#[test]
+fn test_filter_counts() {
+    let actions = df!(
+        "user_id" => [1, 1, 2, 3, 3, 3],
+        "item_id" => [10, 11, 10, 10, 11, 12]
+    )
+    .unwrap();
+    let (actions, pass) = filter_counts(actions, "user_id", 2, None).unwrap();
+    assert_eq!(actions.height(), 5);
+    assert_eq!(pass.removed_ids, 1);
+    assert_eq!(pass.removed_actions, 1);
+    assert_eq!(pass.remaining_actions, 5);
+}
+
+#[test]
+fn test_filter_rating_counts() {
+    let actions = df!(
+        "user_id" => [1, 1, 2, 2, 3],
+        "item_id" => [10, 11, 10, 11, 10],
+        "rating" => [Some(4.0), Some(3.0), Some(5.0), None, None]
+    )
+    .unwrap();
+    // user 2 has only one rating, and user 3 has none
+    let (actions, pass) = filter_counts(actions, "user_id", 2, Some("rating")).unwrap();
+    assert_eq!(actions.height(), 2);
+    assert_eq!(pass.removed_ids, 2);
+    assert_eq!(pass.removed_actions, 3);
+
+    // with k = 1, unrated actions of a rating user are kept
+    let actions = df!(
+        "user_id" => [1, 1, 2],
+        "item_id" => [10, 11, 10],
+        "rating" => [Some(4.0), None, None]
+    )
+    .unwrap();
+    let (actions, _pass) = filter_counts(actions, "user_id", 1, Some("rating")).unwrap();
+    assert_eq!(actions.height(), 2);
+}
+
+#[test]
+fn test_filter_counts_empty() {
+    let actions = df!(
+        "user_id" => [1, 2],
+        "item_id" => [10, 11],
+        "rating" => [None::<f64>, None]
+    )
+    .unwrap();
+    // no actions are counted, so every user is removed
+    let (actions, pass) = filter_counts(actions, "user_id", 1, Some("rating")).unwrap();
+    assert_eq!(actions.height(), 0);
+    assert_eq!(pass.removed_ids, 2);
+
+    // and filtering the empty frame leaves it empty
+    let (actions, pass) = filter_counts(actions, "item_id", 1, Some("rating")).unwrap();
+    assert_eq!(actions.height(), 0);
+    assert_eq!(pass.removed_ids, 0);
+    assert_eq!(pass.removed_actions, 0);
+}
+
+#[test]
+fn test_kcore_empty() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("actions.parquet");
+    // no user has two items, so the 2-core is empty
+    let actions = df!(
+        "user_id" => [1, 2, 3],
+        "item_id" => [10, 10, 11],
+        "first_time" => [0i64, 0, 0]
+    )
+    .unwrap();
+    save_df_parquet(actions, &input).unwrap();
+
+    let kcore = Kcore {
+        k: 2,
+        user_k: None,
+        item_k: None,
+        user_col: "user_id".into(),
+        item_col: "item_id".into(),
+        time_col: "first_time".into(),
+        rating_col: None,
+        year: None,
+        start: None,
+        end: None,
+        report: Some(dir.path().join("report.json")),
+        output: dir.path().join("core.parquet"),
+        input,
+    };
+    kcore.exec().unwrap();
+
+    let core = ParquetReader::new(File::open(&kcore.output).unwrap())
+        .finish()
+        .unwrap();
+    assert_eq!(core.height(), 0);
+    assert_eq!(
+        core.get_column_names(),
+        vec!["user_id", "item_id", "first_time"]
+    );
+
+    let report: serde_json::Value =
+        serde_json::from_reader(File::open(dir.path().join("report.json")).unwrap()).unwrap();
+    assert_eq!(report["initial_actions"], 3);
+    assert_eq!(report["users"], 0);
+    assert_eq!(report["items"], 0);
+    assert_eq!(report["actions"], 0);
 }
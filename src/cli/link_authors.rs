@@ -0,0 +1,592 @@
+//! Author entity clustering.
+//!
+//! Author records are otherwise linked only by cleaned name strings, so a name
+//! like “Smith, John” matches every John Smith in VIAF.  This command builds an
+//! author graph analogous to the book graph.  Its nodes are OpenLibrary authors,
+//! VIAF authority records, and GoodReads authors, along with Wikidata items (when
+//! Wikidata has been scanned) that link them much as ISBNs link book records.
+//! Edges come from two kinds of evidence:
+//!
+//! - Explicit identifiers: OpenLibrary author `remote_ids`, VIAF 024 (other
+//!   standard identifier) fields, and Wikidata external identifiers.
+//! - Co-occurrence: author records attached to books in the same book cluster
+//!   whose names have the same name key.
+//!
+//! VIAF records are not attached to books, so they are only linked by explicit
+//! identifiers.  Each author cluster is identified by the smallest author code
+//! among its nodes.
+use std::collections::HashMap;
+use std::path::Path;
+
+use parquet_derive::ParquetRecordWriter;
+use polars::prelude::*;
+
+use crate::arrow::*;
+use crate::cli::cluster::records::{book_code, scan_cluster_nodes, udf_name_key};
+use crate::graph::DisjointSet;
+use crate::ids::codes::*;
+use crate::openlib::key::{parse_ol_key, KS_AUTHOR};
+use crate::prelude::*;
+use crate::wikidata::parse_qid;
+
+/// The author cluster of each author graph node.
+pub const CLUSTER_PATH: &str = "book-links/author-clusters.parquet";
+const EDGE_PATH: &str = "book-links/author-graph-edges.parquet";
+const BOOK_AUTHOR_PATH: &str = "book-links/book-author-clusters.parquet";
+const WD_EXT_ID_PATH: &str = "wikidata/external-ids.parquet";
+
+/// Multiplier separating author code namespaces.
+pub const AUTHOR_NS_MULT: i64 = 10_000_000_000;
+
+/// Namespaces of author graph nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorNS {
+    OlAuthor = 1,
+    Viaf = 2,
+    GrAuthor = 3,
+    Wikidata = 4,
+}
+
+const AUTHOR_NAMESPACES: &[AuthorNS] = &[
+    AuthorNS::OlAuthor,
+    AuthorNS::Viaf,
+    AuthorNS::GrAuthor,
+    AuthorNS::Wikidata,
+];
+
+impl AuthorNS {
+    /// Get the name of this namespace.
+    pub fn name(self) -> &'static str {
+        match self {
+            AuthorNS::OlAuthor => "OL-A",
+            AuthorNS::Viaf => "VIAF-rec",
+            AuthorNS::GrAuthor => "GR-A",
+            AuthorNS::Wikidata => "WD",
+        }
+    }
+
+    /// Convert an ID in this namespace to an author code.
+    pub fn code(self, id: u64) -> i64 {
+        self as i64 * AUTHOR_NS_MULT + id as i64
+    }
+
+    /// Format an author code with its namespace (e.g. `VIAF-rec:17`).
+    ///
+    /// VIAF nodes are numbered by their record number in `viaf.parquet`, not by VIAF
+    /// ID, so their labels say so.
+    pub fn label(code: i64) -> String {
+        match AuthorNS::of_code(code) {
+            Some(ns) => format!("{}:{}", ns.name(), code - ns.code(0)),
+            None => code.to_string(),
+        }
+    }
+
+    /// Look up the namespace of an author code.
+    pub fn of_code(code: i64) -> Option<AuthorNS> {
+        let nsc = code / AUTHOR_NS_MULT;
+        AUTHOR_NAMESPACES
+            .iter()
+            .copied()
+            .find(|ns| *ns as i64 == nsc)
+    }
+}
+
+/// Build the author entity clusters.
+#[derive(Args, Debug)]
+#[command(name = "link-authors")]
+pub struct LinkAuthors {
+    /// Do not link authors by co-occurrence on book clusters.
+    #[arg(long = "no-cooccurrence")]
+    no_cooccurrence: bool,
+}
+
+/// An author node and its cluster.
+#[derive(ParquetRecordWriter, Debug)]
+struct AuthorClusterRec {
+    author_code: i64,
+    node_type: String,
+    author_cluster: i64,
+}
+
+/// An edge in the author graph.
+#[derive(ParquetRecordWriter, Debug)]
+struct AuthorEdgeRec {
+    src: i64,
+    dst: i64,
+    origin: String,
+}
+
+/// An edge in the author graph, before saving.
+struct AuthorEdge {
+    src: i64,
+    dst: i64,
+    origin: &'static str,
+}
+
+/// The author graph under construction.
+#[derive(Default)]
+struct AuthorGraph {
+    nodes: Vec<i64>,
+    edges: Vec<AuthorEdge>,
+}
+
+impl AuthorGraph {
+    fn add_edge(&mut self, src: i64, dst: i64, origin: &'static str) {
+        if src != dst {
+            self.edges.push(AuthorEdge { src, dst, origin });
+        }
+    }
+}
+
+/// Parse the VIAF ID from a VIAF cluster record's control number (e.g. `viaf102333412`).
+fn parse_viaf_control(cn: &str) -> Option<u64> {
+    let cn = cn.trim();
+    cn.strip_prefix("viaf").unwrap_or(cn).parse().ok()
+}
+
+/// Resolve an identifier from another authority file to an author code.
+///
+/// `viaf` maps VIAF IDs to the record numbers of the scanned VIAF records; VIAF
+/// IDs without a scanned record do not resolve.
+fn resolve_ref(scheme: &str, value: &str, viaf: &HashMap<u64, u32>) -> Option<i64> {
+    let value = value.trim();
+    match scheme.trim().to_lowercase().as_str() {
+        "viaf" => {
+            let id: u64 = value.parse().ok()?;
+            viaf.get(&id).map(|r| AuthorNS::Viaf.code(*r as u64))
+        }
+        "wikidata" => parse_qid(value)
+            .ok()
+            .map(|q| AuthorNS::Wikidata.code(q as u64)),
+        "goodreads" => value.parse().ok().map(|id| AuthorNS::GrAuthor.code(id)),
+        "openlibrary" => {
+            let key = if value.starts_with('/') {
+                value.to_string()
+            } else {
+                format!("/authors/{}", value)
+            };
+            parse_ol_key(&key, KS_AUTHOR)
+                .ok()
+                .map(|id| AuthorNS::OlAuthor.code(id as u64))
+        }
+        _ => None,
+    }
+}
+
+/// Collect an integer column as author codes in a namespace.
+fn column_codes(df: &DataFrame, name: &str, ns: AuthorNS) -> Result<Vec<i64>> {
+    let ids = df.column(name)?.cast(&DataType::Int64)?;
+    Ok(ids
+        .i64()?
+        .into_iter()
+        .flatten()
+        .map(|id| ns.code(id as u64))
+        .collect())
+}
+
+/// Add the OpenLibrary author nodes.
+fn add_ol_nodes(graph: &mut AuthorGraph) -> Result<()> {
+    info!("scanning OpenLibrary authors");
+    let df = scan_df_parquet("openlibrary/authors.parquet")?
+        .select([col("id")])
+        .collect()?;
+    graph
+        .nodes
+        .extend(column_codes(&df, "id", AuthorNS::OlAuthor)?);
+    Ok(())
+}
+
+/// Add the GoodReads author nodes.
+fn add_gr_nodes(graph: &mut AuthorGraph) -> Result<()> {
+    info!("scanning GoodReads authors");
+    let df = scan_df_parquet("goodreads/gr-author-info.parquet")?
+        .select([col("author_id")])
+        .collect()?;
+    graph
+        .nodes
+        .extend(column_codes(&df, "author_id", AuthorNS::GrAuthor)?);
+    Ok(())
+}
+
+/// Add the VIAF record nodes, returning the map of VIAF IDs to record numbers.
+fn add_viaf_nodes(graph: &mut AuthorGraph) -> Result<HashMap<u64, u32>> {
+    info!("scanning VIAF control numbers");
+    let df = scan_df_parquet("viaf/viaf.parquet")?
+        .filter(col("tag").eq(lit(1i16)))
+        .select([col("rec_id"), col("contents")])
+        .collect()?;
+    let recs = df.column("rec_id")?.u32()?;
+    let cns = df.column("contents")?.str()?;
+
+    let mut map = HashMap::with_capacity(df.height());
+    for (rec, cn) in recs.into_iter().zip(cns) {
+        if let Some(rec) = rec {
+            graph.nodes.push(AuthorNS::Viaf.code(rec as u64));
+            if let Some(id) = cn.and_then(parse_viaf_control) {
+                map.insert(id, rec);
+            }
+        }
+    }
+    info!("found {} VIAF records", map.len());
+    Ok(map)
+}
+
+/// Add edges from a table of identifiers in other authority files.
+fn add_ref_edges(
+    graph: &mut AuthorGraph,
+    viaf: &HashMap<u64, u32>,
+    df: &DataFrame,
+    ns: AuthorNS,
+    cols: [&str; 3],
+    origin: &'static str,
+) -> Result<()> {
+    let [id_col, scheme_col, value_col] = cols;
+    let srcs = column_codes(df, id_col, ns)?;
+    let schemes = df.column(scheme_col)?.str()?;
+    let values = df.column(value_col)?.str()?;
+    let n = graph.edges.len();
+    for ((src, scheme), value) in srcs.into_iter().zip(schemes).zip(values) {
+        if let (Some(scheme), Some(value)) = (scheme, value) {
+            if let Some(dst) = resolve_ref(scheme, value, viaf) {
+                graph.add_edge(src, dst, origin);
+            }
+        }
+    }
+    info!("added {} edges from {}", graph.edges.len() - n, origin);
+    Ok(())
+}
+
+/// Add edges from OpenLibrary author remote IDs.
+fn add_ol_remote_edges(graph: &mut AuthorGraph, viaf: &HashMap<u64, u32>) -> Result<()> {
+    info!("scanning OpenLibrary author remote IDs");
+    let df = scan_df_parquet("openlibrary/author-remote-ids.parquet")?.collect()?;
+    add_ref_edges(
+        graph,
+        viaf,
+        &df,
+        AuthorNS::OlAuthor,
+        ["id", "source", "remote_id"],
+        "ol-remote-id",
+    )
+}
+
+/// Add edges from Wikidata external identifiers.
+fn add_wikidata_edges(graph: &mut AuthorGraph, viaf: &HashMap<u64, u32>) -> Result<()> {
+    info!("scanning Wikidata external IDs");
+    let df = scan_df_parquet(WD_EXT_ID_PATH)?.collect()?;
+    add_ref_edges(
+        graph,
+        viaf,
+        &df,
+        AuthorNS::Wikidata,
+        ["id", "source", "ext_id"],
+        "wikidata",
+    )
+}
+
+/// Add edges from VIAF 024 fields.
+///
+/// Each subfield is a separate row, so we pair each `$2` (source) subfield with
+/// the most recent `$a` (identifier) subfield in the same record.
+fn add_viaf_024_edges(graph: &mut AuthorGraph, viaf: &HashMap<u64, u32>) -> Result<()> {
+    info!("scanning VIAF 024 fields");
+    let df = scan_df_parquet("viaf/viaf.parquet")?
+        .filter(
+            col("tag").eq(lit(24i16)).and(
+                col("sf_code")
+                    .eq(lit(b'a'))
+                    .or(col("sf_code").eq(lit(b'2'))),
+            ),
+        )
+        .select([
+            col("rec_id"),
+            col("fld_no"),
+            col("sf_code"),
+            col("contents"),
+        ])
+        .sort_by_exprs([col("rec_id"), col("fld_no")], [false, false], false, false)
+        .collect()?;
+    let recs = df.column("rec_id")?.u32()?;
+    let codes = df.column("sf_code")?.u8()?;
+    let contents = df.column("contents")?.str()?;
+
+    let n = graph.edges.len();
+    let mut pending: Option<(u32, &str)> = None;
+    for ((rec, code), val) in recs.into_iter().zip(codes).zip(contents) {
+        let (Some(rec), Some(code), Some(val)) = (rec, code, val) else {
+            continue;
+        };
+        if code == b'a' {
+            pending = Some((rec, val));
+        } else if let Some((prec, pval)) = pending.take() {
+            if prec == rec {
+                if let Some(dst) = resolve_ref(val, pval, viaf) {
+                    graph.add_edge(AuthorNS::Viaf.code(rec as u64), dst, "viaf-024");
+                }
+            }
+        }
+    }
+    info!("added {} edges from viaf-024", graph.edges.len() - n);
+    Ok(())
+}
+
+/// Scan the author records attached to each book cluster, with their positions.
+fn scan_book_authors(cfg: &Config) -> Result<LazyFrame> {
+    let nodes = scan_cluster_nodes()?;
+    let ol_code = col("author").cast(DataType::Int64) + lit(AuthorNS::OlAuthor.code(0));
+
+    let ol_ed = scan_df_parquet("openlibrary/edition-authors.parquet")?.select([
+        book_code("edition", NS_EDITION).alias("book_code"),
+        ol_code.clone().alias("author_code"),
+        col("pos").alias("position"),
+    ]);
+    let ol_work = scan_df_parquet("openlibrary/work-authors.parquet")?.select([
+        book_code("id", NS_WORK).alias("book_code"),
+        ol_code.alias("author_code"),
+        col("pos").alias("position"),
+    ]);
+    let mut frames = vec![ol_ed, ol_work];
+    if cfg.goodreads.enabled {
+        let gr = scan_df_parquet("goodreads/gr-book-authors.parquet")?.select([
+            book_code("book_id", NS_GR_BOOK).alias("book_code"),
+            (col("author_id").cast(DataType::Int64) + lit(AuthorNS::GrAuthor.code(0)))
+                .alias("author_code"),
+            col("position"),
+        ]);
+        frames.push(gr);
+    }
+
+    let df = concat(frames, UnionArgs::default())?;
+    Ok(df
+        .join(
+            nodes,
+            [col("book_code")],
+            [col("book_code")],
+            JoinType::Inner.into(),
+        )
+        .select([col("cluster"), col("author_code"), col("position")]))
+}
+
+/// Scan the names of the author records attached to books.
+fn scan_author_names(cfg: &Config) -> Result<LazyFrame> {
+    let ol = scan_df_parquet("openlibrary/authors.parquet")?.select([
+        (col("id").cast(DataType::Int64) + lit(AuthorNS::OlAuthor.code(0))).alias("author_code"),
+        col("name"),
+    ]);
+    let mut frames = vec![ol];
+    if cfg.goodreads.enabled {
+        let gr = scan_df_parquet("goodreads/gr-author-info.parquet")?.select([
+            (col("author_id").cast(DataType::Int64) + lit(AuthorNS::GrAuthor.code(0)))
+                .alias("author_code"),
+            col("name"),
+        ]);
+        frames.push(gr);
+    }
+    let df = concat(frames, UnionArgs::default())?;
+    Ok(df.filter(col("name").is_not_null()))
+}
+
+/// Add edges between authors with the same name key on the same book cluster.
+fn add_cooccurrence_edges(
+    graph: &mut AuthorGraph,
+    book_authors: LazyFrame,
+    cfg: &Config,
+) -> Result<()> {
+    info!("linking authors by co-occurrence on book clusters");
+    let keys = scan_author_names(cfg)?.select([
+        col("author_code"),
+        col("name")
+            .map(udf_name_key, GetOutput::from_type(DataType::String))
+            .alias("key"),
+    ]);
+    let df = book_authors
+        .select([col("cluster"), col("author_code")])
+        .unique(None, UniqueKeepStrategy::First)
+        .join(
+            keys,
+            [col("author_code")],
+            [col("author_code")],
+            JoinType::Inner.into(),
+        )
+        .filter(col("key").is_not_null())
+        .unique(None, UniqueKeepStrategy::First)
+        .sort_by_exprs(
+            [col("cluster"), col("key"), col("author_code")],
+            [false, false, false],
+            false,
+            false,
+        )
+        .collect()?;
+
+    let clusters = df.column("cluster")?.i32()?;
+    let keys = df.column("key")?.str()?;
+    let codes = df.column("author_code")?.i64()?;
+
+    // link each author to the first author with the same cluster and key
+    let n = graph.edges.len();
+    let mut group: Option<(i32, &str, i64)> = None;
+    for ((cluster, key), code) in clusters.into_iter().zip(keys).zip(codes) {
+        let (Some(cluster), Some(key), Some(code)) = (cluster, key, code) else {
+            continue;
+        };
+        match group {
+            Some((gc, gk, first)) if gc == cluster && gk == key => {
+                graph.add_edge(first, code, "same-name-on-book");
+            }
+            _ => group = Some((cluster, key, code)),
+        }
+    }
+    info!(
+        "added {} edges from same-name-on-book",
+        graph.edges.len() - n
+    );
+    Ok(())
+}
+
+/// Cluster the author graph and save the node and edge tables.
+fn save_author_clusters(mut graph: AuthorGraph) -> Result<DataFrame> {
+    for e in &graph.edges {
+        graph.nodes.push(e.src);
+        graph.nodes.push(e.dst);
+    }
+    graph.nodes.sort_unstable();
+    graph.nodes.dedup();
+    let nodes = graph.nodes;
+    info!(
+        "clustering {} author nodes with {} edges",
+        nodes.len(),
+        graph.edges.len()
+    );
+
+    let index = |code: i64| nodes.binary_search(&code).expect("missing node") as u32;
+    let mut sets = DisjointSet::new(nodes.len());
+    let mut writer = TableWriter::open(EDGE_PATH)?;
+    for e in graph.edges {
+        sets.union(index(e.src), index(e.dst));
+        writer.write_object(AuthorEdgeRec {
+            src: e.src,
+            dst: e.dst,
+            origin: e.origin.to_string(),
+        })?;
+    }
+    writer.finish()?;
+
+    // nodes are sorted, so the first node seen in each set has its smallest code
+    let mut roots: HashMap<u32, i64> = HashMap::new();
+    let mut author_codes = Vec::with_capacity(nodes.len());
+    let mut author_clusters = Vec::with_capacity(nodes.len());
+    let mut writer = TableWriter::open(CLUSTER_PATH)?;
+    for (i, code) in nodes.iter().enumerate() {
+        let root = sets.find(i as u32);
+        let cluster = *roots.entry(root).or_insert(*code);
+        let ns = AuthorNS::of_code(*code).ok_or_else(|| anyhow!("invalid author code {}", code))?;
+        writer.write_object(AuthorClusterRec {
+            author_code: *code,
+            node_type: ns.name().to_string(),
+            author_cluster: cluster,
+        })?;
+        author_codes.push(*code);
+        author_clusters.push(cluster);
+    }
+    writer.finish()?;
+    info!(
+        "found {} author clusters for {} nodes",
+        roots.len(),
+        nodes.len()
+    );
+
+    let df = df!(
+        "author_code" => author_codes,
+        "author_cluster" => author_clusters,
+    )?;
+    Ok(df)
+}
+
+impl Command for LinkAuthors {
+    fn exec(&self) -> Result<()> {
+        let cfg = load_config()?;
+        let mut graph = AuthorGraph::default();
+
+        add_ol_nodes(&mut graph)?;
+        if cfg.goodreads.enabled {
+            add_gr_nodes(&mut graph)?;
+        }
+        let viaf = add_viaf_nodes(&mut graph)?;
+
+        add_ol_remote_edges(&mut graph, &viaf)?;
+        add_viaf_024_edges(&mut graph, &viaf)?;
+        if Path::new(WD_EXT_ID_PATH).exists() {
+            add_wikidata_edges(&mut graph, &viaf)?;
+        } else {
+            info!("{} not found, skipping Wikidata links", WD_EXT_ID_PATH);
+        }
+
+        let book_authors = scan_book_authors(&cfg)?;
+        if !self.no_cooccurrence {
+            add_cooccurrence_edges(&mut graph, book_authors.clone(), &cfg)?;
+        }
+
+        let clusters = save_author_clusters(graph)?;
+
+        info!("mapping book clusters to author clusters");
+        let book_clusters = book_authors
+            .join(
+                clusters.lazy(),
+                [col("author_code")],
+                [col("author_code")],
+                JoinType::Inner.into(),
+            )
+            .group_by([col("cluster"), col("author_cluster")])
+            .agg([col("position").min()])
+            .sort_by_exprs(
+                [col("cluster"), col("position"), col("author_cluster")],
+                [false, false, false],
+                false,
+                false,
+            )
+            .collect()?;
+        info!(
+            "saving {} book-author links to {}",
+            book_clusters.height(),
+            BOOK_AUTHOR_PATH
+        );
+        save_df_parquet(book_clusters, BOOK_AUTHOR_PATH)?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_author_codes() {
+    let code = AuthorNS::Viaf.code(102333412);
+    assert_eq!(code, 20_102_333_412);
+    assert_eq!(AuthorNS::of_code(code), Some(AuthorNS::Viaf));
+    assert_eq!(AuthorNS::of_code(7 * AUTHOR_NS_MULT), None);
+    assert_eq!(AuthorNS::label(code), "VIAF-rec:102333412");
+    assert_eq!(AuthorNS::label(AuthorNS::OlAuthor.code(5)), "OL-A:5");
+    assert_eq!(parse_viaf_control("viaf102333412"), Some(102333412));
+    assert_eq!(parse_viaf_control("bob"), None);
+}
+
+#[test]
+fn test_resolve_ref() {
+    let viaf = HashMap::from([(102333412u64, 17u32)]);
+    assert_eq!(
+        resolve_ref("viaf", "102333412", &viaf),
+        Some(AuthorNS::Viaf.code(17))
+    );
+    assert_eq!(resolve_ref("viaf", "5", &viaf), None);
+    assert_eq!(
+        resolve_ref("WIKIDATA", "Q36322", &viaf),
+        Some(AuthorNS::Wikidata.code(36322))
+    );
+    assert_eq!(
+        resolve_ref("openlibrary", "OL21594A", &viaf),
+        Some(AuthorNS::OlAuthor.code(21594))
+    );
+    assert_eq!(
+        resolve_ref("goodreads", "1265", &viaf),
+        Some(AuthorNS::GrAuthor.code(1265))
+    );
+    assert_eq!(resolve_ref("isni", "0000000121323049", &viaf), None);
+}
@@ -8,16 +8,23 @@ pub mod amazon;
 pub mod bx;
 pub mod cluster;
 pub mod cluster_books;
+pub mod cluster_crosswalk;
+pub mod cluster_diff;
 pub mod collect_isbns;
+pub mod explain_link;
+pub mod export_interactions;
 pub mod extract_graph;
 pub mod filter_marc;
 pub mod goodreads;
 pub mod index_names;
 pub mod kcore;
+pub mod link_authors;
 pub mod link_isbns;
 pub mod openlib;
 pub mod pqinfo;
 pub mod scan_marc;
+pub mod scan_wikidata;
+pub mod split;
 pub mod stats;
 
 use anyhow::Result;
@@ -66,9 +73,14 @@ pub trait Command {
 pub enum RootCommand {
     ScanMARC(scan_marc::ScanMARC),
     FilterMARC(filter_marc::FilterMARC),
+    ScanWikidata(scan_wikidata::ScanWikidata),
     ClusterBooks(cluster_books::ClusterBooks),
+    LinkAuthors(link_authors::LinkAuthors),
+    ClusterCrosswalk(cluster_crosswalk::ClusterCrosswalk),
+    ClusterDiff(cluster_diff::ClusterDiff),
     IndexNames(index_names::IndexNames),
     ExtractGraph(extract_graph::ExtractGraph),
+    ExplainLink(explain_link::ExplainLink),
     CollectISBNS(collect_isbns::CollectISBNs),
     LinkISBNIds(link_isbns::LinkISBNIds),
     /// Commands for processing Amazon data.
@@ -82,6 +94,8 @@ pub enum RootCommand {
     /// Commands for working with clusters.
     Cluster(ClusterCommandWrapper),
     Kcore(kcore::Kcore),
+    Split(split::Split),
+    ExportInteractions(export_interactions::ExportInteractions),
     PQInfo(pqinfo::PQInfo),
     IntegrationStats(stats::IntegrationStats),
 }
@@ -95,6 +109,9 @@ wrap_subcommands!(ClusterCommand);
 enum AmazonCommand {
     ScanRatings(amazon::ScanRatings),
     ScanReviews(amazon::ScanReviews),
+    ScanMeta(amazon::ScanMeta),
+    FindDuplicates(amazon::FindDuplicates),
+    UserOverlap(amazon::UserOverlap),
     ClusterRatings(amazon::ClusterRatings),
 }
 
@@ -114,6 +131,8 @@ pub enum ClusterCommand {
     ExtractBooks(cluster::books::ExtractBooks),
     ExtractAuthors(cluster::authors::ClusterAuthors),
     ExtractAuthorGender(cluster::author_gender::AuthorGender),
+    ExtractInfo(cluster::info::ExtractInfo),
+    LinkTitles(cluster::link_titles::LinkTitles),
 }
 
 /// Entry point for the Book Data Tools.
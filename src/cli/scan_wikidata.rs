@@ -0,0 +1,66 @@
+//! Scan human entities from a Wikidata JSON dump.
+use crate::io::object::{ChunkWriter, ThreadObjectWriter, UnchunkWriter};
+use crate::prelude::*;
+use crate::util::logging::data_progress;
+use crate::wikidata::{EntityProcessor, RawEntity, HUMAN};
+
+/// Scan human entities (authors) from a Wikidata JSON dump.
+#[derive(Args, Debug)]
+#[command(name = "scan-wikidata")]
+pub struct ScanWikidata {
+    /// The Wikidata JSON dump file (e.g. `latest-all.json.gz`).
+    #[arg(name = "INPUT")]
+    infile: PathBuf,
+}
+
+impl Command for ScanWikidata {
+    fn exec(&self) -> Result<()> {
+        let proc = EntityProcessor::new()?;
+        let outs = proc.output_files();
+
+        info!("reading entities from {}", self.infile.display());
+        let pb = data_progress(0);
+        let read = LineProcessor::open_gzip(&self.infile, pb.clone())?;
+        let proc = ChunkWriter::new(proc);
+        let writer = ThreadObjectWriter::wrap(proc).with_name("output").spawn();
+        let mut writer = UnchunkWriter::new(writer);
+
+        // the dump is one big JSON array, with one entity per line
+        let human_ref = format!("\"{}\"", HUMAN);
+        let mut n_lines = 0;
+        let mut n_humans = 0;
+        for line in read.lines() {
+            n_lines += 1;
+            let line = line?;
+            let line = line.trim_end().trim_end_matches(',');
+            if line == "[" || line == "]" || line.is_empty() {
+                continue;
+            }
+            // skip parsing entities that cannot be humans
+            if !line.contains(&human_ref) {
+                continue;
+            }
+            let ent: RawEntity = serde_json::from_str(line).map_err(|e| {
+                error!("error parsing line {}: {:?}", n_lines, e);
+                e
+            })?;
+            if ent.is_human() {
+                writer.write_object(ent)?;
+                n_humans += 1;
+            }
+        }
+        pb.finish_and_clear();
+        writer.finish()?;
+
+        info!("found {} humans in {} lines", n_humans, n_lines);
+        for out in outs {
+            info!(
+                "output {} is {}",
+                out.display(),
+                friendly::bytes(file_size(&out)?)
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+//! Split interaction files for recommender evaluation.
+//!
+//! This reads a cluster-level rating or action file (or any other interaction
+//! file with user, item, and time columns) and splits it into training and test
+//! sets with one of several strategies.  Random strategies use a seeded RNG, so
+//! a split is reproducible from its seed, and the split parameters and sizes are
+//! recorded in a `split.json` sidecar file next to the outputs.
+use std::collections::HashMap;
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+
+use chrono::NaiveDate;
+use parse_display::{Display, FromStr};
+use polars::prelude::*;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// Marker for rows that are in the training set of every partition.
+const TRAIN: i32 = -1;
+
+/// Strategies for splitting interactions.
+#[derive(Display, FromStr, Debug, Clone, Copy, PartialEq, Eq)]
+#[display(style = "kebab-case")]
+enum SplitStrategy {
+    /// Test on all interactions at or after a global cutoff date.
+    Temporal,
+    /// Test on each user's last N interactions.
+    LeaveLast,
+    /// Test on N random interactions from each of a random sample of users.
+    UserHoldout,
+    /// Partition users into K folds, testing on N random interactions from each.
+    #[display("k-fold")]
+    KFold,
+}
+
+#[derive(Args, Debug)]
+#[command(name = "split")]
+/// Split interaction records into training and test sets.
+pub struct Split {
+    /// The splitting strategy (temporal, leave-last, user-holdout, or k-fold).
+    #[arg(short = 's', long = "strategy", name = "STRATEGY")]
+    strategy: SplitStrategy,
+
+    /// The cutoff date for temporal splits.
+    #[arg(long = "cutoff", name = "DATE")]
+    cutoff: Option<NaiveDate>,
+
+    /// The number of interactions to hold out for each test user.
+    #[arg(short = 'n', long = "holdout", default_value = "1")]
+    holdout: usize,
+
+    /// The number of test users for user holdout splits.
+    #[arg(long = "test-users", name = "USERS")]
+    test_users: Option<usize>,
+
+    /// The number of folds for k-fold splits.
+    #[arg(short = 'k', long = "folds", default_value = "5")]
+    folds: usize,
+
+    /// The random seed.
+    #[arg(long = "seed", default_value = "42")]
+    seed: u64,
+
+    /// The user ID column.
+    #[arg(long = "user-col", default_value = "user_id")]
+    user_col: String,
+
+    /// The timestamp column.
+    #[arg(long = "time-col", default_value = "first_time")]
+    time_col: String,
+
+    /// The output directory.
+    #[arg(short = 'o', long = "output", name = "DIR")]
+    output: PathBuf,
+
+    /// The interaction file to split.
+    #[arg(name = "INPUT")]
+    input: PathBuf,
+}
+
+/// Split metadata saved to the sidecar file.
+#[derive(Debug, Serialize)]
+struct SplitInfo {
+    input: PathBuf,
+    strategy: String,
+    seed: u64,
+    cutoff: Option<String>,
+    holdout: Option<usize>,
+    test_users: Option<usize>,
+    folds: Option<usize>,
+    n_users: usize,
+    n_actions: usize,
+    partitions: Vec<PartitionInfo>,
+}
+
+/// Metadata for a single train-test partition.
+#[derive(Debug, Serialize)]
+struct PartitionInfo {
+    train_file: String,
+    test_file: String,
+    train_actions: usize,
+    test_actions: usize,
+    test_users: usize,
+}
+
+/// Group row numbers by user, in order of first appearance.
+fn user_rows(users: &[Option<i64>]) -> Vec<Vec<usize>> {
+    let mut index = HashMap::new();
+    let mut rows: Vec<Vec<usize>> = Vec::new();
+    for (i, u) in users.iter().enumerate() {
+        let pos = *index.entry(*u).or_insert_with(|| {
+            rows.push(Vec::new());
+            rows.len() - 1
+        });
+        rows[pos].push(i);
+    }
+    rows
+}
+
+/// Select the test rows of a user's profile.
+///
+/// Users with no more than `n` interactions have all their interactions kept
+/// for training, so every test user has a training profile.
+fn select_holdout<R: Rng>(
+    rows: &[usize],
+    n: usize,
+    times: Option<&[Option<i64>]>,
+    rng: &mut R,
+) -> Vec<usize> {
+    if rows.len() <= n {
+        return Vec::new();
+    }
+    let mut rows = rows.to_vec();
+    match times {
+        Some(times) => {
+            // stable sort, so ties are broken by row order
+            rows.sort_by_key(|r| times[*r]);
+            rows.split_off(rows.len() - n)
+        }
+        None => {
+            let (test, _) = rows.partial_shuffle(rng, n);
+            test.to_vec()
+        }
+    }
+}
+
+impl Split {
+    /// Compute the partition assignment of each row.
+    ///
+    /// Each row is assigned the partition in which it is a test row, or
+    /// [TRAIN] if it is always a training row.
+    fn assign(&self, actions: &DataFrame) -> Result<(Vec<i32>, usize)> {
+        let users = actions.column(&self.user_col)?.cast(&DataType::Int64)?;
+        let users: Vec<Option<i64>> = users.i64()?.into_iter().collect();
+        let by_user = user_rows(&users);
+        let n_users = by_user.len();
+        info!("splitting actions from {} users", friendly::scalar(n_users));
+
+        let mut assign = vec![TRAIN; actions.height()];
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        match self.strategy {
+            SplitStrategy::Temporal => {
+                let cutoff = self
+                    .cutoff
+                    .ok_or_else(|| anyhow!("temporal split requires --cutoff"))?;
+                info!("testing on actions on or after {}", cutoff);
+                let cutoff = cutoff.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+                let times = self.times(actions)?;
+                for (a, t) in assign.iter_mut().zip(times) {
+                    if t.map(|t| t >= cutoff).unwrap_or(false) {
+                        *a = 0;
+                    }
+                }
+            }
+            SplitStrategy::LeaveLast => {
+                info!("testing on last {} actions of each user", self.holdout);
+                let times = self.times(actions)?;
+                for rows in &by_user {
+                    for r in select_holdout(rows, self.holdout, Some(&times), &mut rng) {
+                        assign[r] = 0;
+                    }
+                }
+            }
+            SplitStrategy::UserHoldout => {
+                let n = self
+                    .test_users
+                    .ok_or_else(|| anyhow!("user holdout split requires --test-users"))?;
+                if n > n_users {
+                    return Err(anyhow!("requested {} test users, only have {}", n, n_users));
+                }
+                info!(
+                    "testing on {} actions from each of {} users",
+                    self.holdout, n
+                );
+                let test: Vec<&Vec<usize>> = by_user.choose_multiple(&mut rng, n).collect();
+                for rows in test {
+                    for r in select_holdout(rows, self.holdout, None, &mut rng) {
+                        assign[r] = 0;
+                    }
+                }
+            }
+            SplitStrategy::KFold => {
+                if self.folds < 2 {
+                    return Err(anyhow!("k-fold split requires at least 2 folds"));
+                }
+                info!(
+                    "testing on {} actions from users in {} folds",
+                    self.holdout, self.folds
+                );
+                let mut order: Vec<usize> = (0..n_users).collect();
+                order.shuffle(&mut rng);
+                for (i, u) in order.into_iter().enumerate() {
+                    let fold = (i % self.folds) as i32;
+                    for r in select_holdout(&by_user[u], self.holdout, None, &mut rng) {
+                        assign[r] = fold;
+                    }
+                }
+            }
+        }
+
+        Ok((assign, n_users))
+    }
+
+    /// Get the timestamps of the actions.
+    fn times(&self, actions: &DataFrame) -> Result<Vec<Option<i64>>> {
+        let times = actions.column(&self.time_col)?.cast(&DataType::Int64)?;
+        let times = times.i64()?.into_iter().collect();
+        Ok(times)
+    }
+
+    /// Get the number of partitions the split produces.
+    fn n_partitions(&self) -> usize {
+        match self.strategy {
+            SplitStrategy::KFold => self.folds,
+            _ => 1,
+        }
+    }
+}
+
+impl Command for Split {
+    fn exec(&self) -> Result<()> {
+        info!("reading actions from {:?}", self.input);
+        let file = File::open(&self.input)?;
+        let actions = ParquetReader::new(file).finish()?;
+        info!("loaded {} actions", friendly::scalar(actions.height()));
+
+        let (assign, n_users) = self.assign(&actions)?;
+        let assign = Series::new("partition", assign);
+
+        create_dir_all(&self.output)?;
+        let n_parts = self.n_partitions();
+        let mut partitions = Vec::with_capacity(n_parts);
+        for part in 0..n_parts {
+            let (train_file, test_file) = if n_parts > 1 {
+                (
+                    format!("train-{}.parquet", part + 1),
+                    format!("test-{}.parquet", part + 1),
+                )
+            } else {
+                ("train.parquet".to_string(), "test.parquet".to_string())
+            };
+
+            let mask = assign.equal(part as i32)?;
+            let test = actions.filter(&mask)?;
+            let train = actions.filter(&!mask)?;
+            let info = PartitionInfo {
+                train_file,
+                test_file,
+                train_actions: train.height(),
+                test_actions: test.height(),
+                test_users: test.column(&self.user_col)?.n_unique()?,
+            };
+            info!(
+                "partition {}: {} training and {} test actions ({} test users)",
+                part + 1,
+                friendly::scalar(info.train_actions),
+                friendly::scalar(info.test_actions),
+                friendly::scalar(info.test_users),
+            );
+            save_df_parquet(train, self.output.join(&info.train_file))?;
+            save_df_parquet(test, self.output.join(&info.test_file))?;
+            partitions.push(info);
+        }
+
+        let temporal = self.strategy == SplitStrategy::Temporal;
+        let split = SplitInfo {
+            input: self.input.clone(),
+            strategy: self.strategy.to_string(),
+            seed: self.seed,
+            cutoff: self.cutoff.filter(|_| temporal).map(|d| d.to_string()),
+            holdout: if temporal { None } else { Some(self.holdout) },
+            test_users: if self.strategy == SplitStrategy::UserHoldout {
+                self.test_users
+            } else {
+                None
+            },
+            folds: if self.strategy == SplitStrategy::KFold {
+                Some(self.folds)
+            } else {
+                None
+            },
+            n_users,
+            n_actions: actions.height(),
+            partitions,
+        };
+        let path = self.output.join("split.json");
+        info!("saving split metadata to {:?}", path);
+        let mut out = File::create(path)?;
+        serde_json::to_writer_pretty(&mut out, &split)?;
+        out.write_all(b"\n")?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_user_rows() {
+    let users = [Some(5), Some(3), Some(5), Some(7), Some(3)];
+    let rows = user_rows(&users);
+    assert_eq!(rows, vec![vec![0, 2], vec![1, 4], vec![3]]);
+}
+
+#[test]
+fn test_select_holdout_last() {
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    let times = [Some(30), Some(10), Some(20), Some(40)];
+    let mut test = select_holdout(&[0, 1, 2, 3], 2, Some(&times), &mut rng);
+    test.sort();
+    assert_eq!(test, vec![0, 3]);
+    // small profiles are kept for training
+    assert!(select_holdout(&[0, 1], 2, Some(&times), &mut rng).is_empty());
+}
+
+#[test]
+fn test_select_holdout_random_seeded() {
+    let rows: Vec<usize> = (0..20).collect();
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    let t1 = select_holdout(&rows, 5, None, &mut rng);
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+    let t2 = select_holdout(&rows, 5, None, &mut rng);
+    assert_eq!(t1.len(), 5);
+    assert_eq!(t1, t2);
+}
+
+#[test]
+fn test_strategy_names() {
+    for name in ["temporal", "leave-last", "user-holdout", "k-fold"] {
+        let strategy: SplitStrategy = name.parse().unwrap();
+        assert_eq!(strategy.to_string(), name);
+    }
+}
@@ -15,6 +15,7 @@ static ACTION_FILES: &[(&str, &str)] = &[
     ("BX-E", "bx/bx-cluster-ratings.parquet"),
     ("AZ14", "az2014/az-cluster-ratings.parquet"),
     ("AZ18", "az2018/az-cluster-ratings.parquet"),
+    ("AZ23", "az2023/az-cluster-ratings.parquet"),
     ("GR-I", "goodreads/gr-cluster-actions.parquet"),
     ("GR-E", "goodreads/gr-cluster-ratings.parquet"),
 ];
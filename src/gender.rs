@@ -1,6 +1,7 @@
 //! Code for working with genders.
 use std::fmt;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use thiserror::Error;
 
@@ -21,12 +22,25 @@ pub enum Gender {
     Open(String),
 }
 
+/// The number of times a source asserted a gender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceGender {
+    /// The asserting source (e.g. a VIAF member file such as `LC`), if known.
+    pub source: Option<Arc<str>>,
+    pub gender: Gender,
+    pub count: u32,
+}
+
 /// A collection of genders.
+///
+/// Besides the merged gender, the bag keeps how many times each source asserted
+/// each gender, so conflicts can be inspected and resolved by source preference.
 #[derive(Default, Debug)]
 pub struct GenderBag {
     size: usize,
     mask: u32,
     resolved: Gender,
+    sources: Vec<SourceGender>,
 }
 
 impl Default for Gender {
@@ -109,10 +123,34 @@ impl Gender {
 
 impl GenderBag {
     /// Add a gender to this bag.
+    #[allow(dead_code)]
     pub fn add(&mut self, gender: Gender) {
+        self.add_from(None, gender);
+    }
+
+    /// Add a gender asserted by a source to this bag.
+    pub fn add_from(&mut self, source: Option<Arc<str>>, gender: Gender) {
         self.size += 1;
         self.mask |= gender.mask_val();
         self.resolved = self.resolved.merge(&gender);
+        self.record(source, gender, 1);
+    }
+
+    /// Record source assertions.
+    fn record(&mut self, source: Option<Arc<str>>, gender: Gender, count: u32) {
+        if let Some(sg) = self
+            .sources
+            .iter_mut()
+            .find(|sg| sg.source == source && sg.gender == gender)
+        {
+            sg.count += count;
+        } else {
+            self.sources.push(SourceGender {
+                source,
+                gender,
+                count,
+            });
+        }
     }
 
     /// Merge another gender bag into this one.
@@ -120,6 +158,56 @@ impl GenderBag {
         self.size += bag.size;
         self.mask |= bag.mask;
         self.resolved = self.resolved.merge(&bag.resolved);
+        for sg in &bag.sources {
+            self.record(sg.source.clone(), sg.gender.clone(), sg.count);
+        }
+    }
+
+    /// Get the source assertions in this bag.
+    pub fn sources(&self) -> &[SourceGender] {
+        &self.sources
+    }
+
+    /// Get the fraction of assertions that agree with the most common gender,
+    /// returning [None] if no genders are seen.
+    pub fn agreement(&self) -> Option<f32> {
+        let mut totals: Vec<(&Gender, u32)> = Vec::new();
+        for sg in &self.sources {
+            match totals.iter_mut().find(|(g, _)| *g == &sg.gender) {
+                Some((_, n)) => *n += sg.count,
+                None => totals.push((&sg.gender, sg.count)),
+            }
+        }
+        let max = totals.iter().map(|(_, n)| *n).max()?;
+        Some(max as f32 / self.size as f32)
+    }
+
+    /// Get the gender, resolving ambiguity by source preference.
+    ///
+    /// If the sources disagree, the first source in `prefs` (compared without
+    /// regard to case) whose own assertions agree determines the gender; if no
+    /// preferred source settles it, the gender is [Gender::Ambiguous].
+    pub fn resolve<S: AsRef<str>>(&self, prefs: &[S]) -> Gender {
+        if self.resolved != Gender::Ambiguous {
+            return self.resolved.clone();
+        }
+        for pref in prefs {
+            let pref = pref.as_ref();
+            let gender = self
+                .sources
+                .iter()
+                .filter(|sg| {
+                    sg.source
+                        .as_deref()
+                        .map(|s| s.eq_ignore_ascii_case(pref))
+                        .unwrap_or(false)
+                })
+                .fold(Gender::Unknown, |g, sg| g.merge(&sg.gender));
+            if gender != Gender::Unknown && gender != Gender::Ambiguous {
+                return gender;
+            }
+        }
+        Gender::Ambiguous
     }
 
     /// Get the number of gender entries recorded to make this gender record.
@@ -144,6 +232,7 @@ impl GenderBag {
     }
 
     /// Get the gender, returning [Gender::Unknown] if no genders are seen.
+    #[allow(dead_code)]
     pub fn to_gender(&self) -> &Gender {
         &self.resolved
     }
@@ -184,3 +273,27 @@ pub fn test_bag_ff() {
     bag.add("female".into());
     assert_eq!(bag.to_gender(), &Gender::Female);
 }
+
+#[test]
+pub fn test_bag_sources() {
+    let lc: Arc<str> = "LC".into();
+    let dnb: Arc<str> = "DNB".into();
+    let mut bag = GenderBag::default();
+    bag.add_from(Some(lc.clone()), "female".into());
+    bag.add_from(Some(lc.clone()), "female".into());
+    bag.add_from(Some(dnb.clone()), "male".into());
+    assert_eq!(bag.maybe_gender(), Some(&Gender::Ambiguous));
+    assert_eq!(bag.sources().len(), 2);
+    assert_eq!(bag.sources()[0].count, 2);
+    assert_eq!(bag.agreement(), Some(2.0 / 3.0));
+    assert_eq!(bag.resolve(&["lc"]), Gender::Female);
+    assert_eq!(bag.resolve(&["BNF", "DNB"]), Gender::Male);
+    assert_eq!(bag.resolve::<&str>(&[]), Gender::Ambiguous);
+
+    let mut merged = GenderBag::default();
+    merged.add_from(Some(lc), "female".into());
+    merged.merge_from(&bag);
+    assert_eq!(merged.len(), 4);
+    assert_eq!(merged.sources()[0].count, 3);
+    assert_eq!(GenderBag::default().agreement(), None);
+}
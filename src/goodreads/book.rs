@@ -35,6 +35,10 @@ pub struct RawBook {
     pub publication_day: String,
     #[serde(default)]
     pub series: Vec<String>,
+    #[serde(default)]
+    pub language_code: String,
+    #[serde(default)]
+    pub format: String,
 }
 
 /// The raw author records from JSON.
@@ -66,6 +70,8 @@ pub struct BookRecord {
     pub title: Option<String>,
     pub pub_year: Option<u16>,
     pub pub_month: Option<u8>,
+    pub language_code: Option<String>,
+    pub format: Option<String>,
 }
 
 /// book series linking records
@@ -146,6 +152,8 @@ impl ObjectWriter<RawBook> for BookWriter {
             title: trim_owned(&row.title),
             pub_year,
             pub_month,
+            language_code: trim_owned(&row.language_code),
+            format: trim_owned(&row.format),
         })?;
 
         for (i, author) in row.authors.into_iter().enumerate() {
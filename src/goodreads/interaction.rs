@@ -32,6 +32,8 @@ pub struct RawInteraction {
 /// This struct is written to `gr-interactions.parquet` and records actual interaction data.
 /// Timestamps are UNIX timestamps recorded as 64-bit integers; they do not use a Parquet
 /// timestamp time, due to out-of-range values causing problems when loaded into Python.
+/// Book Data 3.0 and earlier stored them as 32-bit floats, which only resolve about two
+/// minutes at current timestamps.
 #[derive(ParquetRecordWriter)]
 pub struct IntRecord {
     pub rec_id: u32,
@@ -46,10 +48,10 @@ pub struct IntRecord {
     pub book_id: i32,
     pub is_read: u8,
     pub rating: Option<f32>,
-    pub added: f32,
-    pub updated: f32,
-    pub read_started: Option<f32>,
-    pub read_finished: Option<f32>,
+    pub added: i64,
+    pub updated: i64,
+    pub read_started: Option<i64>,
+    pub read_finished: Option<i64>,
 }
 
 /// Object writer to transform and write GoodReads interactions
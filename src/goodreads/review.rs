@@ -54,10 +54,10 @@ pub struct ReviewRecord {
     pub review: String,
     /// Number of votes this review has received.
     pub n_votes: i32,
-    /// Date review was added.
-    pub added: f32,
-    /// Date review was updated.
-    pub updated: f32,
+    /// Date review was added (UNIX timestamp in seconds).
+    pub added: i64,
+    /// Date review was updated (UNIX timestamp in seconds).
+    pub updated: i64,
 }
 
 // Object writer to transform and write GoodReads reviews
@@ -0,0 +1,140 @@
+//! Minimum edge cuts between book graph nodes.
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use petgraph::graph::EdgeIndex;
+use petgraph::visit::EdgeRef;
+
+use super::{IdGraph, IdNode};
+
+/// Flow along each edge, positive in the direction of its stored endpoints.
+type FlowMap = HashMap<EdgeIndex, i32>;
+
+/// Get the residual capacity for traversing an edge from `from`.
+fn residual(graph: &IdGraph, flow: &FlowMap, e: EdgeIndex, from: IdNode) -> i32 {
+    let (a, _b) = graph.edge_endpoints(e).unwrap();
+    let f = flow.get(&e).copied().unwrap_or_default();
+    if from == a {
+        1 - f
+    } else {
+        1 + f
+    }
+}
+
+/// Get the other endpoint of an edge.
+fn other_end(graph: &IdGraph, e: EdgeIndex, n: IdNode) -> IdNode {
+    let (a, b) = graph.edge_endpoints(e).unwrap();
+    if a == n {
+        b
+    } else {
+        a
+    }
+}
+
+/// Breadth-first search over the residual graph, returning the parent edge of each
+/// reached node.
+fn residual_bfs(
+    graph: &IdGraph,
+    flow: &FlowMap,
+    src: IdNode,
+    dst: Option<IdNode>,
+) -> HashMap<IdNode, Option<EdgeIndex>> {
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert(src, None);
+    queue.push_back(src);
+    while let Some(n) = queue.pop_front() {
+        if Some(n) == dst {
+            break;
+        }
+        for er in graph.edges(n) {
+            let e = er.id();
+            let o = other_end(graph, e, n);
+            if o != n && !parents.contains_key(&o) && residual(graph, flow, e, n) > 0 {
+                parents.insert(o, Some(e));
+                queue.push_back(o);
+            }
+        }
+    }
+    parents
+}
+
+/// Compute a minimum set of edges whose removal disconnects two nodes.
+///
+/// This treats each undirected edge as having unit capacity and runs Edmonds-Karp
+/// to find a maximum flow; the cut is the set of edges leaving the nodes reachable
+/// from `src` in the final residual graph.  Parallel edges are each counted, so the
+/// cut contains all of them.  If the nodes are not connected, the cut is empty.
+pub fn min_edge_cut(graph: &IdGraph, src: IdNode, dst: IdNode) -> Vec<EdgeIndex> {
+    if src == dst {
+        return Vec::new();
+    }
+
+    let mut flow = FlowMap::new();
+    loop {
+        let parents = residual_bfs(graph, &flow, src, Some(dst));
+        if !parents.contains_key(&dst) {
+            break;
+        }
+        // push one unit of flow back along the path
+        let mut n = dst;
+        while let Some(Some(e)) = parents.get(&n) {
+            let prev = other_end(graph, *e, n);
+            let (a, _b) = graph.edge_endpoints(*e).unwrap();
+            *flow.entry(*e).or_default() += if prev == a { 1 } else { -1 };
+            n = prev;
+        }
+    }
+
+    let reached: HashSet<IdNode> = residual_bfs(graph, &flow, src, None).into_keys().collect();
+    let mut cut = Vec::new();
+    for n in &reached {
+        for er in graph.edges(*n) {
+            if !reached.contains(&other_end(graph, er.id(), *n)) {
+                cut.push(er.id());
+            }
+        }
+    }
+    cut
+}
+
+#[cfg(test)]
+fn test_graph(n: usize, edges: &[(usize, usize)]) -> (IdGraph, Vec<IdNode>) {
+    use super::BookID;
+    let mut g = IdGraph::new_undirected();
+    let nodes: Vec<_> = (0..n)
+        .map(|i| {
+            g.add_node(BookID {
+                code: i as i32,
+                label: None,
+                cluster: 0,
+            })
+        })
+        .collect();
+    for (a, b) in edges {
+        g.add_edge(nodes[*a], nodes[*b], ());
+    }
+    (g, nodes)
+}
+
+#[test]
+fn test_cut_bridge() {
+    // two triangles joined by a single edge
+    let (g, ns) = test_graph(6, &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3)]);
+    let cut = min_edge_cut(&g, ns[0], ns[5]);
+    assert_eq!(cut.len(), 1);
+    assert_eq!(g.edge_endpoints(cut[0]), Some((ns[2], ns[3])));
+}
+
+#[test]
+fn test_cut_two_paths() {
+    let (g, ns) = test_graph(4, &[(0, 1), (1, 3), (0, 2), (2, 3)]);
+    let cut = min_edge_cut(&g, ns[0], ns[3]);
+    assert_eq!(cut.len(), 2);
+}
+
+#[test]
+fn test_cut_disconnected() {
+    let (g, ns) = test_graph(4, &[(0, 1), (2, 3)]);
+    let cut = min_edge_cut(&g, ns[0], ns[3]);
+    assert!(cut.is_empty());
+}
@@ -0,0 +1,197 @@
+//! Graph export in GraphML, DOT, and Parquet formats.
+use std::fs::{create_dir_all, File};
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::Result;
+use log::*;
+use parse_display::{Display, FromStr};
+use petgraph::visit::*;
+
+use super::model::{ClusterCode, GraphEdge};
+use super::{save_gml, IdGraph};
+use crate::arrow::TableWriter;
+use crate::ids::codes::ns_of_book_code;
+use crate::io::ObjectWriter;
+
+/// File formats for exporting graphs.
+#[derive(Display, FromStr, Debug, Clone, Copy, PartialEq, Eq)]
+#[display(style = "lowercase")]
+pub enum GraphFormat {
+    Gml,
+    GraphML,
+    Dot,
+    Parquet,
+}
+
+impl GraphFormat {
+    /// Guess the graph format from a file name, defaulting to GML.
+    pub fn from_path(path: &Path) -> GraphFormat {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("graphml") => GraphFormat::GraphML,
+            Some("dot") | Some("gv") => GraphFormat::Dot,
+            Some("parquet") => GraphFormat::Parquet,
+            _ => GraphFormat::Gml,
+        }
+    }
+}
+
+/// Get the namespace name of a node's book code.
+fn node_ns(code: i32) -> &'static str {
+    ns_of_book_code(code).unwrap().name
+}
+
+/// Escape a string for XML text or attribute content.
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape a string for a DOT quoted string.
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Save a graph to a GraphML file.
+pub fn save_graphml<P: AsRef<Path>>(graph: &IdGraph, path: P) -> Result<()> {
+    info!("saving graph to {}", path.as_ref().to_string_lossy());
+    let out = File::create(path)?;
+    let mut w = BufWriter::new(out);
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    writeln!(
+        w,
+        r#"  <key id="namespace" for="node" attr.name="namespace" attr.type="string"/>"#
+    )?;
+    writeln!(
+        w,
+        r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+    )?;
+    writeln!(
+        w,
+        r#"  <key id="cluster" for="node" attr.name="cluster" attr.type="int"/>"#
+    )?;
+    writeln!(w, r#"  <graph id="books" edgedefault="undirected">"#)?;
+    for node in graph.node_weights() {
+        writeln!(w, r#"    <node id="n{}">"#, node.code)?;
+        writeln!(
+            w,
+            r#"      <data key="namespace">{}</data>"#,
+            node_ns(node.code)
+        )?;
+        if let Some(ref l) = node.label {
+            writeln!(w, r#"      <data key="label">{}</data>"#, xml_escape(l))?;
+        }
+        writeln!(w, r#"      <data key="cluster">{}</data>"#, node.cluster)?;
+        writeln!(w, "    </node>")?;
+    }
+    for e in graph.edge_references() {
+        let src = graph.node_weight(e.source()).unwrap();
+        let dst = graph.node_weight(e.target()).unwrap();
+        writeln!(
+            w,
+            r#"    <edge source="n{}" target="n{}"/>"#,
+            src.code, dst.code
+        )?;
+    }
+    writeln!(w, "  </graph>")?;
+    writeln!(w, "</graphml>")?;
+    Ok(())
+}
+
+/// Save a graph to a GraphViz DOT file.
+pub fn save_dot<P: AsRef<Path>>(graph: &IdGraph, path: P) -> Result<()> {
+    info!("saving graph to {}", path.as_ref().to_string_lossy());
+    let out = File::create(path)?;
+    let mut w = BufWriter::new(out);
+    writeln!(w, "graph books {{")?;
+    for node in graph.node_weights() {
+        let ns = node_ns(node.code);
+        let label = match &node.label {
+            Some(l) => format!("{}\\n{}", ns, dot_escape(l)),
+            None => format!("{}\\n{}", ns, node.code),
+        };
+        writeln!(
+            w,
+            "  {} [label=\"{}\", namespace=\"{}\", cluster={}];",
+            node.code, label, ns, node.cluster
+        )?;
+    }
+    for e in graph.edge_references() {
+        let src = graph.node_weight(e.source()).unwrap();
+        let dst = graph.node_weight(e.target()).unwrap();
+        writeln!(w, "  {} -- {};", src.code, dst.code)?;
+    }
+    writeln!(w, "}}")?;
+    Ok(())
+}
+
+/// Save a graph as Parquet node and edge lists in a directory.
+///
+/// The node and edge files have the same layout as the cluster graph node and
+/// edge tables.
+pub fn save_parquet<P: AsRef<Path>>(graph: &IdGraph, dir: P) -> Result<()> {
+    let dir = dir.as_ref();
+    info!("saving graph to {}", dir.to_string_lossy());
+    create_dir_all(dir)?;
+
+    let mut n_w = TableWriter::open(dir.join("nodes.parquet"))?;
+    for node in graph.node_weights() {
+        n_w.write_object(ClusterCode {
+            book_code: node.code,
+            cluster: node.cluster,
+            node_type: node_ns(node.code).to_string(),
+            label: node.label.clone(),
+        })?;
+    }
+    n_w.finish()?;
+
+    let mut e_w = TableWriter::open(dir.join("edges.parquet"))?;
+    for e in graph.edge_references() {
+        let src = graph.node_weight(e.source()).unwrap().code;
+        let dst = graph.node_weight(e.target()).unwrap().code;
+        e_w.write_object(GraphEdge { src, dst })?;
+    }
+    e_w.finish()?;
+
+    Ok(())
+}
+
+/// Save a graph in the specified format.
+pub fn save_graph_as<P: AsRef<Path>>(graph: &IdGraph, path: P, format: GraphFormat) -> Result<()> {
+    match format {
+        GraphFormat::Gml => save_gml(graph, path),
+        GraphFormat::GraphML => save_graphml(graph, path),
+        GraphFormat::Dot => save_dot(graph, path),
+        GraphFormat::Parquet => save_parquet(graph, path),
+    }
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(
+        GraphFormat::from_path(Path::new("g.graphml")),
+        GraphFormat::GraphML
+    );
+    assert_eq!(GraphFormat::from_path(Path::new("g.gv")), GraphFormat::Dot);
+    assert_eq!(GraphFormat::from_path(Path::new("g.gml")), GraphFormat::Gml);
+    assert_eq!(
+        GraphFormat::from_path(Path::new("g.parquet")),
+        GraphFormat::Parquet
+    );
+    assert_eq!(
+        "graphml".parse::<GraphFormat>().unwrap(),
+        GraphFormat::GraphML
+    );
+}
+
+#[test]
+fn test_xml_escape() {
+    assert_eq!(xml_escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+}
@@ -11,6 +11,14 @@ use crate::ids::codes::ns_of_book_code;
 
 use super::{IdGraph, IdNode};
 
+/// Escape a string for a GML string literal.
+///
+/// GML strings cannot contain double quotes, and use HTML-style character entities
+/// for special characters.
+fn gml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('"', "&quot;")
+}
+
 fn gml_begin<W: Write>(w: &mut W) -> Result<()> {
     writeln!(w, "graph [")?;
     Ok(())
@@ -28,7 +36,7 @@ fn gml_node<W: Write>(w: &mut W, graph: &IdGraph, v: IdNode) -> Result<()> {
     let ns = ns_of_book_code(node.code).unwrap();
     writeln!(w, "    namespace \"{}\"", ns.name())?;
     if let Some(ref l) = node.label {
-        writeln!(w, "    label \"{}\"", l)?;
+        writeln!(w, "    label \"{}\"", gml_escape(l))?;
     }
     writeln!(w, "  ]")?;
     Ok(())
@@ -59,3 +67,9 @@ pub fn save_gml<P: AsRef<Path>>(graph: &IdGraph, path: P) -> Result<()> {
     gml_end(&mut out)?;
     Ok(())
 }
+
+#[test]
+fn test_gml_escape() {
+    assert_eq!(gml_escape("plain"), "plain");
+    assert_eq!(gml_escape("a \"b\" & c"), "a &quot;b&quot; &amp; c");
+}
@@ -1,10 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::{anyhow, Result};
 use log::*;
 
 use crate::layout::Config;
 
+use super::overrides::ClusterOverrides;
 use super::sources::*;
 use super::{BookID, IdGraph, IdNode};
 use polars::prelude::*;
@@ -14,10 +15,11 @@ type NodeMap = HashMap<i32, IdNode>;
 struct GraphBuilder {
     graph: IdGraph,
     nodes: NodeMap,
+    dropped: HashSet<i32>,
 }
 
 impl GraphBuilder {
-    fn add_vertices<R: NodeRead>(&mut self, src: R) -> Result<()> {
+    fn add_vertices(&mut self, src: &dyn NodeRead) -> Result<()> {
         info!("scanning vertices from {:?}", src);
         let node_df = src.read_node_ids()?;
         debug!("node schema: {:?}", node_df.schema());
@@ -52,7 +54,7 @@ impl GraphBuilder {
         Ok(())
     }
 
-    fn add_edges<R: EdgeRead>(&mut self, src: R) -> Result<()> {
+    fn add_edges(&mut self, src: &dyn EdgeRead) -> Result<()> {
         info!("scanning edges from {:?}", src);
         let edge_df = src.read_edges()?;
         debug!("edge schema: {:?}", edge_df.schema());
@@ -67,6 +69,9 @@ impl GraphBuilder {
 
         for pair in iter {
             if let (Some(sn), Some(dn)) = pair {
+                if self.dropped.contains(&sn) || self.dropped.contains(&dn) {
+                    continue;
+                }
                 let sid = self
                     .nodes
                     .get(&sn)
@@ -86,28 +91,39 @@ impl GraphBuilder {
     }
 }
 
-pub fn construct_graph(cfg: &Config) -> Result<IdGraph> {
+/// Construct the book graph from the configured node sources and the given edge
+/// sources, applying any curation overrides.
+pub fn construct_graph(
+    cfg: &Config,
+    edges: &[Box<dyn EdgeRead>],
+    overrides: &mut ClusterOverrides,
+) -> Result<IdGraph> {
     let graph = IdGraph::new_undirected();
     let nodes = NodeMap::new();
-    let mut gb = GraphBuilder { graph, nodes };
+    let mut gb = GraphBuilder {
+        graph,
+        nodes,
+        dropped: HashSet::new(),
+    };
 
     info!("loading nodes");
-    gb.add_vertices(ISBN)?;
-    gb.add_vertices(LOC)?;
-    gb.add_vertices(OLEditions)?;
-    gb.add_vertices(OLWorks)?;
-    if cfg.goodreads.enabled {
-        gb.add_vertices(GRBooks)?;
-        gb.add_vertices(GRWorks)?;
+    for src in node_sources(cfg) {
+        gb.add_vertices(src.as_ref())?;
+    }
+
+    if !overrides.is_empty() {
+        info!("applying node overrides");
+        gb.dropped = overrides.apply_drops(&mut gb.graph, &mut gb.nodes)?;
     }
 
     info!("loading edges");
-    gb.add_edges(LOC)?;
-    gb.add_edges(OLEditions)?;
-    gb.add_edges(OLWorks)?;
-    if cfg.goodreads.enabled {
-        gb.add_edges(GRBooks)?;
-        gb.add_edges(GRWorks)?;
+    for src in edges {
+        gb.add_edges(src.as_ref())?;
+    }
+
+    if !overrides.is_empty() {
+        info!("applying edge overrides");
+        overrides.apply_edges(&mut gb.graph, &gb.nodes)?;
     }
 
     let graph = gb.graph;
@@ -118,3 +134,22 @@ pub fn construct_graph(cfg: &Config) -> Result<IdGraph> {
     );
     Ok(graph)
 }
+
+/// Load a graph from the node and edge tables saved by clustering.
+///
+/// The nodes have no cluster annotations.
+pub fn load_graph_tables(tables: GraphTables) -> Result<IdGraph> {
+    let mut gb = GraphBuilder {
+        graph: IdGraph::new_undirected(),
+        nodes: NodeMap::new(),
+        dropped: HashSet::new(),
+    };
+    gb.add_vertices(&tables)?;
+    gb.add_edges(&tables)?;
+    info!(
+        "graph has {} nodes, {} edges",
+        gb.graph.node_count(),
+        gb.graph.edge_count()
+    );
+    Ok(gb.graph)
+}
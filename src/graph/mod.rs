@@ -21,13 +21,25 @@ pub struct BookID {
 pub type IdGraph = Graph<BookID, (), Undirected>;
 pub type IdNode = NodeIndex<DefaultIx>;
 
+mod cut;
+mod export;
 mod gml;
 mod load;
 pub mod model;
+mod neighborhood;
+mod overrides;
 mod sources;
+mod stats;
+mod unionfind;
 
+pub use cut::min_edge_cut;
+pub use export::{save_graph_as, GraphFormat};
 pub use gml::save_gml;
-pub use load::construct_graph;
+pub use load::{construct_graph, load_graph_tables};
+pub use neighborhood::khop_subgraph;
+pub use overrides::{find_nodes, ClusterOverrides, NodeRef};
+pub use sources::{edge_origin, edge_sources, node_sources, CandidateLinks, GraphTables};
+pub use unionfind::{cluster_union_find, DisjointSet};
 
 /// Save a graph to a compressed, encoded file.
 pub fn save_graph<P: AsRef<Path>>(graph: &IdGraph, path: P) -> Result<()> {
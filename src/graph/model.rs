@@ -6,16 +6,17 @@ use log::*;
 use parquet_derive::{ParquetRecordReader, ParquetRecordWriter};
 use serde::Serialize;
 
+use super::stats::save_detailed_stats;
 use super::{BookID, IdGraph, IdNode};
 use crate::arrow::TableWriter;
 use crate::ids::codes::{ns_of_book_code, NS_ISBN};
 use crate::io::object::ObjectWriter;
 use crate::util::logging::item_progress;
 
-const ISBN_CLUSTER_PATH: &str = "book-links/isbn-clusters.parquet";
-const GRAPH_NODE_PATH: &str = "book-links/cluster-graph-nodes.parquet";
-const GRAPH_EDGE_PATH: &str = "book-links/cluster-graph-edges.parquet";
-const CLUSTER_STATS_PATH: &str = "book-links/cluster-stats.parquet";
+pub(super) const ISBN_CLUSTER_PATH: &str = "book-links/isbn-clusters.parquet";
+pub(super) const GRAPH_NODE_PATH: &str = "book-links/cluster-graph-nodes.parquet";
+pub(super) const GRAPH_EDGE_PATH: &str = "book-links/cluster-graph-edges.parquet";
+pub(super) const CLUSTER_STATS_PATH: &str = "book-links/cluster-stats.parquet";
 const CLUSTER_METRICS_PATH: &str = "book-links/cluster-metrics.json";
 
 #[derive(ParquetRecordWriter, ParquetRecordReader, Debug)]
@@ -52,10 +53,10 @@ pub struct ClusterStat {
 }
 
 #[derive(Serialize, Debug)]
-struct ClusteringStatistics {
-    clusters: usize,
-    largest: usize,
-    max_isbns: usize,
+pub(super) struct ClusteringStatistics {
+    pub clusters: usize,
+    pub largest: usize,
+    pub max_isbns: usize,
 }
 
 impl ClusterStat {
@@ -63,23 +64,37 @@ impl ClusterStat {
     pub fn create(cluster: i32, nodes: &Vec<&BookID>) -> ClusterStat {
         let mut cs = ClusterStat::default();
         cs.cluster = cluster;
-        cs.n_nodes = nodes.len() as u32;
         for node in nodes {
-            if let Some(ns) = ns_of_book_code(node.code) {
-                match ns.name {
-                    "ISBN" => cs.n_isbns += 1,
-                    "LOC" => cs.n_loc_recs += 1,
-                    "OL-W" => cs.n_ol_works += 1,
-                    "OL-E" => cs.n_ol_editions += 1,
-                    "GR-W" => cs.n_gr_works += 1,
-                    "GR-B" => cs.n_gr_books += 1,
-                    _ => (),
-                }
-            }
+            cs.add_code(node.code);
         }
 
         cs
     }
+
+    /// Count a node in this cluster's statistics.
+    pub fn add_code(&mut self, code: i32) {
+        self.n_nodes += 1;
+        if let Some(ns) = ns_of_book_code(code) {
+            match ns.name {
+                "ISBN" => self.n_isbns += 1,
+                "LOC" => self.n_loc_recs += 1,
+                "OL-W" => self.n_ol_works += 1,
+                "OL-E" => self.n_ol_editions += 1,
+                "GR-W" => self.n_gr_works += 1,
+                "GR-B" => self.n_gr_books += 1,
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Save the clustering metrics file.
+pub(super) fn save_metrics(stats: &ClusteringStatistics) -> Result<()> {
+    info!("saving statistics");
+    let mut statf = File::create(CLUSTER_METRICS_PATH)?;
+    serde_json::to_writer(&mut statf, stats)?;
+    statf.write_all(b"\n")?;
+    Ok(())
 }
 
 pub fn save_graph_cluster_data(graph: &IdGraph, clusters: Vec<Vec<IdNode>>) -> Result<()> {
@@ -149,15 +164,10 @@ pub fn save_graph_cluster_data(graph: &IdGraph, clusters: Vec<Vec<IdNode>>) -> R
     }
     e_w.finish()?;
 
-    info!("saving statistics");
-    let stats = ClusteringStatistics {
+    save_metrics(&ClusteringStatistics {
         clusters: clusters.len(),
         largest: m_size,
         max_isbns: m_isbns,
-    };
-    let mut statf = File::create(CLUSTER_METRICS_PATH)?;
-    serde_json::to_writer(&mut statf, &stats)?;
-    statf.write(b"\n")?;
-
-    Ok(())
+    })?;
+    save_detailed_stats()
 }
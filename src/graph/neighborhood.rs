@@ -0,0 +1,74 @@
+//! Neighborhood subgraph extraction.
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+use super::{IdGraph, IdNode};
+
+/// Find the nodes within `hops` edges of any of the seed nodes, with their distances.
+pub fn khop_nodes(graph: &IdGraph, seeds: &[IdNode], hops: u32) -> HashMap<IdNode, u32> {
+    let mut dist = HashMap::new();
+    let mut queue = VecDeque::new();
+    for s in seeds {
+        if dist.insert(*s, 0).is_none() {
+            queue.push_back(*s);
+        }
+    }
+    while let Some(n) = queue.pop_front() {
+        let d = dist[&n];
+        if d >= hops {
+            continue;
+        }
+        for nbr in graph.neighbors(n) {
+            if let Entry::Vacant(e) = dist.entry(nbr) {
+                e.insert(d + 1);
+                queue.push_back(nbr);
+            }
+        }
+    }
+    dist
+}
+
+/// Extract the subgraph induced by the nodes within `hops` edges of the seed nodes.
+pub fn khop_subgraph(graph: &IdGraph, seeds: &[IdNode], hops: u32) -> IdGraph {
+    let keep = khop_nodes(graph, seeds, hops);
+    graph.filter_map(
+        |n, w| {
+            if keep.contains_key(&n) {
+                Some(w.clone())
+            } else {
+                None
+            }
+        },
+        |_e, _w| Some(()),
+    )
+}
+
+#[test]
+fn test_khop_path() {
+    use super::BookID;
+    let mut g = IdGraph::new_undirected();
+    let ns: Vec<_> = (0..5)
+        .map(|i| {
+            g.add_node(BookID {
+                code: i,
+                label: None,
+                cluster: 0,
+            })
+        })
+        .collect();
+    for i in 0..4 {
+        g.add_edge(ns[i], ns[i + 1], ());
+    }
+
+    let sub = khop_subgraph(&g, &[ns[0]], 2);
+    assert_eq!(sub.node_count(), 3);
+    assert_eq!(sub.edge_count(), 2);
+
+    let sub = khop_subgraph(&g, &[ns[2]], 1);
+    let mut codes: Vec<_> = sub.node_weights().map(|b| b.code).collect();
+    codes.sort();
+    assert_eq!(codes, vec![1, 2, 3]);
+
+    let sub = khop_subgraph(&g, &[ns[4]], 0);
+    assert_eq!(sub.node_count(), 1);
+}
@@ -0,0 +1,382 @@
+//! Manual curation overrides for the book graph.
+//!
+//! Clustering is driven entirely by shared identifiers, so a bad ISBN or a
+//! spurious record link can merge unrelated books.  The overrides file lets us
+//! correct such problems without patching the source data.  It can be written
+//! in YAML:
+//!
+//! ```yaml
+//! drop-isbns:
+//!   - "9780000000000"
+//! cut-edges:
+//!   - ["9780000000000", "OL-E:1234567"]
+//! force-merge:
+//!   - ["9780000000001", "GR-B:456"]
+//! force-separate:
+//!   - ["9780000000002", "9780000000003"]
+//! ```
+//!
+//! or as a CSV file with columns `action`, `node`, and `other`, where `action`
+//! is one of `drop-isbn`, `cut-edge`, `force-merge`, or `force-separate`.
+//!
+//! Nodes are referenced as `NS:ID`, where `NS` is a book code namespace name
+//! (e.g. `LOC`, `OL-E`, `GR-B`) and `ID` is the numeric identifier within that
+//! namespace.  Non-numeric identifiers are matched against node labels, so OpenLibrary
+//! works can be referenced by key (`OL-W:/works/OL45883W`).  References without a
+//! namespace are ISBNs.
+//!
+//! Overrides are applied in order: ISBNs are dropped before edges are loaded; after
+//! loading, edges are cut, then merges are added, and finally forced separations cut
+//! the minimum set of edges needed to disconnect their nodes.  Separations therefore
+//! take precedence over merges.
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use parquet_derive::ParquetRecordWriter;
+use petgraph::visit::EdgeRef;
+use serde::Deserialize;
+
+use super::cut::min_edge_cut;
+use super::{IdGraph, IdNode};
+use crate::arrow::TableWriter;
+use crate::cleaning::isbns::clean_isbn_chars;
+use crate::ids::codes::{ns_of_book_code, NS, NS_ISBN};
+use crate::io::ObjectWriter;
+
+/// A reference to a graph node in an overrides file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NodeRef {
+    /// A node identified by its book code.
+    Code(i32),
+    /// A node identified by its namespace code and label.
+    Label(i32, String),
+}
+
+impl NodeRef {
+    /// Parse a node reference.
+    pub fn parse(s: &str) -> Result<NodeRef> {
+        let s = s.trim();
+        if let Some((ns, id)) = s.split_once(':') {
+            let ns = NS::by_name(ns).ok_or_else(|| anyhow!("unknown namespace in {}", s))?;
+            if ns.code() == NS_ISBN.code() {
+                Ok(NodeRef::Label(ns.code(), clean_isbn_chars(id)))
+            } else if let Ok(n) = id.parse() {
+                Ok(NodeRef::Code(ns.to_code(n)))
+            } else {
+                Ok(NodeRef::Label(ns.code(), id.to_string()))
+            }
+        } else {
+            Ok(NodeRef::Label(NS_ISBN.code(), clean_isbn_chars(s)))
+        }
+    }
+}
+
+/// The override actions, as read from a YAML file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct OverrideSpec {
+    #[serde(default)]
+    drop_isbns: Vec<String>,
+    #[serde(default)]
+    cut_edges: Vec<(String, String)>,
+    #[serde(default)]
+    force_merge: Vec<(String, String)>,
+    #[serde(default)]
+    force_separate: Vec<(String, String)>,
+}
+
+/// A single row of a CSV overrides file.
+#[derive(Debug, Deserialize)]
+struct OverrideRow {
+    action: String,
+    node: String,
+    other: Option<String>,
+}
+
+/// Report row recording the outcome of a single override.
+#[derive(ParquetRecordWriter, Debug)]
+pub struct OverrideOutcome {
+    pub action: String,
+    pub node: String,
+    pub other: Option<String>,
+    pub status: String,
+    pub n_edges: u32,
+}
+
+/// A set of curation overrides to apply while building the graph.
+#[derive(Debug, Default)]
+pub struct ClusterOverrides {
+    drops: Vec<String>,
+    cuts: Vec<(String, String)>,
+    merges: Vec<(String, String)>,
+    separations: Vec<(String, String)>,
+    outcomes: Vec<OverrideOutcome>,
+}
+
+impl ClusterOverrides {
+    /// Load overrides from a YAML or CSV file, based on its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ClusterOverrides> {
+        let path = path.as_ref();
+        info!("reading cluster overrides from {}", path.display());
+        let spec = match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => load_csv(path)?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&read_to_string(path)?)?,
+            _ => return Err(anyhow!("unsupported override file {}", path.display())),
+        };
+        let ovr = ClusterOverrides {
+            drops: spec.drop_isbns,
+            cuts: spec.cut_edges,
+            merges: spec.force_merge,
+            separations: spec.force_separate,
+            outcomes: Vec::new(),
+        };
+        info!(
+            "loaded {} drops, {} cuts, {} merges, and {} separations",
+            ovr.drops.len(),
+            ovr.cuts.len(),
+            ovr.merges.len(),
+            ovr.separations.len()
+        );
+        Ok(ovr)
+    }
+
+    /// Check whether there are any overrides to apply.
+    pub fn is_empty(&self) -> bool {
+        self.drops.is_empty()
+            && self.cuts.is_empty()
+            && self.merges.is_empty()
+            && self.separations.is_empty()
+    }
+
+    fn record(&mut self, action: &str, node: &str, other: Option<&str>, status: &str, n: u32) {
+        match status {
+            "applied" => debug!("{} {} {:?}: applied to {} edges", action, node, other, n),
+            _ => warn!("{} {} {:?}: {}", action, node, other, status),
+        }
+        self.outcomes.push(OverrideOutcome {
+            action: action.to_string(),
+            node: node.to_string(),
+            other: other.map(|s| s.to_string()),
+            status: status.to_string(),
+            n_edges: n,
+        });
+    }
+
+    /// Remove dropped ISBNs from a graph.  This must be called before any edges are
+    /// added, and keeps the node map up to date as nodes are removed.  It returns the
+    /// book codes of the dropped nodes, so edges to them can be skipped.
+    pub fn apply_drops(
+        &mut self,
+        graph: &mut IdGraph,
+        nodes: &mut HashMap<i32, IdNode>,
+    ) -> Result<HashSet<i32>> {
+        let mut dropped = HashSet::new();
+        if self.drops.is_empty() {
+            return Ok(dropped);
+        }
+        assert_eq!(graph.edge_count(), 0, "drops must be applied before edges");
+        let refs = self
+            .drops
+            .iter()
+            .map(|s| NodeRef::parse(&format!("ISBN:{}", s)))
+            .collect::<Result<Vec<_>>>()?;
+        let resolved = resolve_refs(graph, nodes, &refs);
+
+        for (name, r) in self.drops.clone().iter().zip(refs) {
+            if let Some(node) = resolved.get(&r).and_then(|c| nodes.remove(c)) {
+                dropped.insert(graph.node_weight(node).unwrap().code);
+                let last = IdNode::new(graph.node_count() - 1);
+                graph.remove_node(node);
+                // removal moves the last node into the vacated index
+                if last != node {
+                    let moved = graph.node_weight(node).expect("moved node missing").code;
+                    nodes.insert(moved, node);
+                }
+                self.record("drop-isbn", name, None, "applied", 0);
+            } else {
+                self.record("drop-isbn", name, None, "missing-node", 0);
+            }
+        }
+
+        Ok(dropped)
+    }
+
+    /// Apply edge cuts, merges, and separations to a fully-loaded graph.
+    pub fn apply_edges(&mut self, graph: &mut IdGraph, nodes: &HashMap<i32, IdNode>) -> Result<()> {
+        let pairs: Vec<_> = self
+            .cuts
+            .iter()
+            .chain(self.merges.iter())
+            .chain(self.separations.iter())
+            .cloned()
+            .collect();
+        let mut refs = Vec::with_capacity(pairs.len() * 2);
+        for (a, b) in &pairs {
+            refs.push(NodeRef::parse(a)?);
+            refs.push(NodeRef::parse(b)?);
+        }
+        let resolved = resolve_refs(graph, nodes, &refs);
+        let lookup = |s: &str| -> Result<Option<IdNode>> {
+            let r = NodeRef::parse(s)?;
+            Ok(resolved.get(&r).and_then(|c| nodes.get(c)).copied())
+        };
+
+        for (a, b) in self.cuts.clone() {
+            if let (Some(an), Some(bn)) = (lookup(&a)?, lookup(&b)?) {
+                let mut edges: Vec<_> = graph.edges_connecting(an, bn).map(|e| e.id()).collect();
+                // remove in descending order so removal doesn't invalidate indices
+                edges.sort_unstable();
+                let n = edges.len() as u32;
+                for e in edges.into_iter().rev() {
+                    graph.remove_edge(e);
+                }
+                let status = if n > 0 { "applied" } else { "no-edge" };
+                self.record("cut-edge", &a, Some(&b), status, n);
+            } else {
+                self.record("cut-edge", &a, Some(&b), "missing-node", 0);
+            }
+        }
+
+        for (a, b) in self.merges.clone() {
+            if let (Some(an), Some(bn)) = (lookup(&a)?, lookup(&b)?) {
+                graph.add_edge(an, bn, ());
+                self.record("force-merge", &a, Some(&b), "applied", 1);
+            } else {
+                self.record("force-merge", &a, Some(&b), "missing-node", 0);
+            }
+        }
+
+        for (a, b) in self.separations.clone() {
+            if let (Some(an), Some(bn)) = (lookup(&a)?, lookup(&b)?) {
+                let mut edges = min_edge_cut(graph, an, bn);
+                edges.sort_unstable();
+                let n = edges.len() as u32;
+                for e in edges.into_iter().rev() {
+                    graph.remove_edge(e);
+                }
+                let status = if n > 0 { "applied" } else { "not-connected" };
+                self.record("force-separate", &a, Some(&b), status, n);
+            } else {
+                self.record("force-separate", &a, Some(&b), "missing-node", 0);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Save the override report to a Parquet file.
+    pub fn save_report<P: AsRef<Path>>(self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let n_missing = self
+            .outcomes
+            .iter()
+            .filter(|o| o.status == "missing-node")
+            .count();
+        if n_missing > 0 {
+            warn!("{} overrides reference missing nodes", n_missing);
+        }
+        info!(
+            "saving {} override outcomes to {}",
+            self.outcomes.len(),
+            path.display()
+        );
+        let mut writer = TableWriter::open(path)?;
+        for o in self.outcomes {
+            writer.write_object(o)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Read overrides from a CSV file.
+fn load_csv(path: &Path) -> Result<OverrideSpec> {
+    let mut spec = OverrideSpec::default();
+    let rdr = csv::Reader::from_path(path)?;
+    for row in rdr.into_deserialize() {
+        let row: OverrideRow = row?;
+        let pair = || {
+            row.other
+                .clone()
+                .map(|o| (row.node.clone(), o))
+                .ok_or_else(|| anyhow!("{} requires two nodes", row.action))
+        };
+        match row.action.as_str() {
+            "drop-isbn" => spec.drop_isbns.push(row.node.clone()),
+            "cut-edge" => spec.cut_edges.push(pair()?),
+            "force-merge" => spec.force_merge.push(pair()?),
+            "force-separate" => spec.force_separate.push(pair()?),
+            a => return Err(anyhow!("unknown override action {}", a)),
+        }
+    }
+    Ok(spec)
+}
+
+/// Resolve node references to book codes, omitting references to nodes not in the graph.
+fn resolve_refs(
+    graph: &IdGraph,
+    nodes: &HashMap<i32, IdNode>,
+    refs: &[NodeRef],
+) -> HashMap<NodeRef, i32> {
+    let mut resolved = HashMap::new();
+    let mut labels = HashSet::new();
+    for r in refs {
+        match r {
+            NodeRef::Code(c) if nodes.contains_key(c) => {
+                resolved.insert(r.clone(), *c);
+            }
+            NodeRef::Label(ns, l) => {
+                labels.insert((*ns, l.as_str()));
+            }
+            _ => (),
+        }
+    }
+
+    if !labels.is_empty() {
+        // scan the graph for labeled nodes
+        for node in graph.node_weights() {
+            if let (Some(l), Some(ns)) = (&node.label, ns_of_book_code(node.code)) {
+                if labels.contains(&(ns.code(), l.as_str())) {
+                    resolved.insert(NodeRef::Label(ns.code(), l.clone()), node.code);
+                }
+            }
+        }
+    }
+
+    resolved
+}
+
+/// Look up node references in a loaded graph, omitting references to missing nodes.
+pub fn find_nodes(graph: &IdGraph, refs: &[NodeRef]) -> HashMap<NodeRef, IdNode> {
+    let nodes: HashMap<i32, IdNode> = graph
+        .node_indices()
+        .map(|n| (graph.node_weight(n).unwrap().code, n))
+        .collect();
+    resolve_refs(graph, &nodes, refs)
+        .into_iter()
+        .map(|(r, c)| (r, nodes[&c]))
+        .collect()
+}
+
+#[test]
+fn test_parse_isbn_ref() {
+    let r = NodeRef::parse("978-0-12-345678-9").expect("parse failed");
+    assert_eq!(r, NodeRef::Label(NS_ISBN.code(), "9780123456789".into()));
+}
+
+#[test]
+fn test_parse_code_ref() {
+    use crate::ids::codes::NS_GR_BOOK;
+    let r = NodeRef::parse("GR-B:42").expect("parse failed");
+    assert_eq!(r, NodeRef::Code(NS_GR_BOOK.to_code(42)));
+}
+
+#[test]
+fn test_parse_key_ref() {
+    use crate::ids::codes::NS_WORK;
+    let r = NodeRef::parse("OL-W:/works/OL45883W").expect("parse failed");
+    assert_eq!(r, NodeRef::Label(NS_WORK.code(), "/works/OL45883W".into()));
+}
@@ -1,14 +1,27 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use log::*;
 
 use polars::prelude::*;
 
 use crate::ids::codes::*;
+use crate::layout::Config;
 use crate::util::default;
 
 pub trait EdgeRead: Debug {
-    fn read_edges(&self) -> Result<LazyFrame>;
+    /// Get the Parquet file the edges are read from.
+    fn edge_file(&self) -> PathBuf;
+
+    /// Select the edges (`src` and `dst` columns) from rows of the edge file.
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame>;
+
+    /// Scan all the edges.
+    fn read_edges(&self) -> Result<LazyFrame> {
+        let df = LazyFrame::scan_parquet(self.edge_file(), default())?;
+        self.select_edges(df)
+    }
 }
 
 pub trait NodeRead: Debug {
@@ -28,6 +41,122 @@ pub struct GRBooks;
 #[derive(Debug)]
 pub struct GRWorks;
 
+/// Candidate links between clusters, as proposed by `cluster link-titles`.
+///
+/// Cluster IDs are book codes, so these link the clusters' minimum nodes.  The links
+/// may come from an older clustering run, so only links whose endpoints are still
+/// nodes are used.
+pub struct CandidateLinks {
+    pub path: PathBuf,
+    /// The link endpoints that are current node codes.
+    valid: DataFrame,
+}
+
+impl CandidateLinks {
+    /// Load candidate links, checking their endpoints against the current nodes.
+    pub fn load(path: &Path, nodes: &[Box<dyn NodeRead>]) -> Result<CandidateLinks> {
+        let links = LazyFrame::scan_parquet(path, default())?;
+        let ends = concat(
+            [
+                links.clone().select([col("cluster").alias("code")]),
+                links.clone().select([col("other_cluster").alias("code")]),
+            ],
+            UnionArgs::default(),
+        )?
+        .unique(None, UniqueKeepStrategy::Any);
+
+        let mut codes = Vec::with_capacity(nodes.len());
+        for src in nodes {
+            codes.push(src.read_node_ids()?.select([col("code")]));
+        }
+        let codes = concat(codes, UnionArgs::default())?.unique(None, UniqueKeepStrategy::Any);
+
+        info!("checking candidate link endpoints");
+        let valid = ends.inner_join(codes, col("code"), col("code")).collect()?;
+        let cl = CandidateLinks {
+            path: path.to_path_buf(),
+            valid,
+        };
+
+        let n_links = links.select([col("cluster")]).collect()?.height();
+        let n_kept = cl.read_edges()?.collect()?.height();
+        if n_kept < n_links {
+            warn!(
+                "skipping {} of {} candidate links with stale cluster IDs",
+                n_links - n_kept,
+                n_links
+            );
+        }
+        Ok(cl)
+    }
+}
+
+impl Debug for CandidateLinks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CandidateLinks")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Node and edge tables saved by a previous clustering run.
+#[derive(Debug)]
+pub struct GraphTables {
+    pub nodes: PathBuf,
+    pub edges: PathBuf,
+}
+
+/// Get the node sources enabled in the configuration.
+pub fn node_sources(cfg: &Config) -> Vec<Box<dyn NodeRead>> {
+    let mut sources: Vec<Box<dyn NodeRead>> = vec![
+        Box::new(ISBN),
+        Box::new(LOC),
+        Box::new(OLEditions),
+        Box::new(OLWorks),
+    ];
+    if cfg.goodreads.enabled {
+        sources.push(Box::new(GRBooks));
+        sources.push(Box::new(GRWorks));
+    }
+    sources
+}
+
+/// Get the edge sources enabled in the configuration.
+pub fn edge_sources(cfg: &Config) -> Vec<Box<dyn EdgeRead>> {
+    let mut sources: Vec<Box<dyn EdgeRead>> =
+        vec![Box::new(LOC), Box::new(OLEditions), Box::new(OLWorks)];
+    if cfg.goodreads.enabled {
+        sources.push(Box::new(GRBooks));
+        sources.push(Box::new(GRWorks));
+    }
+    sources
+}
+
+/// Describe the source of an edge from the namespaces of its endpoints.
+///
+/// Each edge source links a distinct pair of namespaces, so the namespaces identify
+/// where an edge came from.  Returns `None` for edges no source produces, such as
+/// those added by curation overrides.
+pub fn edge_origin(a: i32, b: i32) -> Option<&'static str> {
+    let an = ns_of_book_code(a)?.code();
+    let bn = ns_of_book_code(b)?.code();
+    let (lo, hi) = if an <= bn { (an, bn) } else { (bn, an) };
+    let isbn = NS_ISBN.code();
+    if (lo, hi) == (NS_LOC_REC.code(), isbn) {
+        Some("LOC record ISBN")
+    } else if (lo, hi) == (NS_EDITION.code(), isbn) {
+        Some("OL edition ISBN")
+    } else if (lo, hi) == (NS_WORK.code(), NS_EDITION.code()) {
+        Some("OL edition work")
+    } else if (lo, hi) == (NS_GR_BOOK.code(), isbn) {
+        Some("GR book ISBN")
+    } else if (lo, hi) == (NS_GR_WORK.code(), NS_GR_BOOK.code()) {
+        Some("GR book work")
+    } else {
+        None
+    }
+}
+
 /// Get an ID column and apply the appropriate namespace adjustment.
 fn id_col(name: &str, ns: NS<'_>) -> Expr {
     col(name) + lit(ns.base())
@@ -53,8 +182,11 @@ impl NodeRead for LOC {
 }
 
 impl EdgeRead for LOC {
-    fn read_edges(&self) -> Result<LazyFrame> {
-        let df = LazyFrame::scan_parquet("loc-mds/book-isbn-ids.parquet", default())?;
+    fn edge_file(&self) -> PathBuf {
+        PathBuf::from("loc-mds/book-isbn-ids.parquet")
+    }
+
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame> {
         let df = df.select([
             id_col("isbn_id", NS_ISBN).alias("src"),
             id_col("rec_id", NS_LOC_REC).alias("dst"),
@@ -72,8 +204,11 @@ impl NodeRead for OLEditions {
 }
 
 impl EdgeRead for OLEditions {
-    fn read_edges(&self) -> Result<LazyFrame> {
-        let df = LazyFrame::scan_parquet("openlibrary/edition-isbn-ids.parquet", default())?;
+    fn edge_file(&self) -> PathBuf {
+        PathBuf::from("openlibrary/edition-isbn-ids.parquet")
+    }
+
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame> {
         let df = df.select([
             id_col("isbn_id", NS_ISBN).alias("src"),
             id_col("edition", NS_EDITION).alias("dst"),
@@ -102,8 +237,11 @@ impl NodeRead for OLWorks {
 }
 
 impl EdgeRead for OLWorks {
-    fn read_edges(&self) -> Result<LazyFrame> {
-        let df = LazyFrame::scan_parquet("openlibrary/edition-works.parquet", default())?;
+    fn edge_file(&self) -> PathBuf {
+        PathBuf::from("openlibrary/edition-works.parquet")
+    }
+
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame> {
         let df = df.select([
             id_col("edition", NS_EDITION).alias("src"),
             id_col("work", NS_WORK).alias("dst"),
@@ -121,8 +259,11 @@ impl NodeRead for GRBooks {
 }
 
 impl EdgeRead for GRBooks {
-    fn read_edges(&self) -> Result<LazyFrame> {
-        let df = LazyFrame::scan_parquet("goodreads/book-isbn-ids.parquet", default())?;
+    fn edge_file(&self) -> PathBuf {
+        PathBuf::from("goodreads/book-isbn-ids.parquet")
+    }
+
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame> {
         let df = df.select([
             id_col("isbn_id", NS_ISBN).alias("src"),
             id_col("book_id", NS_GR_BOOK).alias("dst"),
@@ -141,8 +282,11 @@ impl NodeRead for GRWorks {
 }
 
 impl EdgeRead for GRWorks {
-    fn read_edges(&self) -> Result<LazyFrame> {
-        let df = LazyFrame::scan_parquet("goodreads/gr-book-ids.parquet", default())?;
+    fn edge_file(&self) -> PathBuf {
+        PathBuf::from("goodreads/gr-book-ids.parquet")
+    }
+
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame> {
         let df = df.filter(col("work_id").is_not_null());
         let df = df.select([
             id_col("book_id", NS_GR_BOOK).alias("src"),
@@ -151,3 +295,86 @@ impl EdgeRead for GRWorks {
         Ok(df)
     }
 }
+
+impl EdgeRead for CandidateLinks {
+    fn edge_file(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame> {
+        let valid = self.valid.clone().lazy();
+        let df = df
+            .select([
+                col("cluster").alias("src"),
+                col("other_cluster").alias("dst"),
+            ])
+            .inner_join(valid.clone(), col("src"), col("code"))
+            .inner_join(valid, col("dst"), col("code"));
+        Ok(df.select([col("src"), col("dst")]))
+    }
+}
+
+impl NodeRead for GraphTables {
+    fn read_node_ids(&self) -> Result<LazyFrame> {
+        let df = LazyFrame::scan_parquet(&self.nodes, default())?;
+        let df = df.select([col("book_code").alias("code"), col("label")]);
+        Ok(df)
+    }
+}
+
+impl EdgeRead for GraphTables {
+    fn edge_file(&self) -> PathBuf {
+        self.edges.clone()
+    }
+
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame> {
+        Ok(df.select([col("src"), col("dst")]))
+    }
+}
+
+#[test]
+fn test_edge_origin() {
+    let isbn = NS_ISBN.to_code(10);
+    let ed = NS_EDITION.to_code(20);
+    let work = NS_WORK.to_code(30);
+    assert_eq!(edge_origin(isbn, ed), Some("OL edition ISBN"));
+    assert_eq!(edge_origin(ed, isbn), Some("OL edition ISBN"));
+    assert_eq!(edge_origin(work, ed), Some("OL edition work"));
+    assert_eq!(edge_origin(isbn, work), None);
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct TestNodes(Vec<i32>);
+
+#[cfg(test)]
+impl NodeRead for TestNodes {
+    fn read_node_ids(&self) -> Result<LazyFrame> {
+        Ok(df!("code" => &self.0)?.lazy())
+    }
+}
+
+#[test]
+fn test_candidate_links_stale() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("links.parquet");
+    // cluster 15 no longer exists
+    let df = df!(
+        "cluster" => [10, 15, 20],
+        "other_cluster" => [20, 30, 15],
+    )
+    .unwrap();
+    crate::arrow::save_df_parquet(df, &path).unwrap();
+
+    let nodes: Vec<Box<dyn NodeRead>> = vec![
+        Box::new(TestNodes(vec![10, 11])),
+        Box::new(TestNodes(vec![20, 30])),
+    ];
+    let links = CandidateLinks::load(&path, &nodes).unwrap();
+    let edges = links.read_edges().unwrap().collect().unwrap();
+    assert_eq!(edges.height(), 1);
+    let src = edges.column("src").unwrap().i32().unwrap();
+    let dst = edges.column("dst").unwrap().i32().unwrap();
+    assert_eq!(src.get(0), Some(10));
+    assert_eq!(dst.get(0), Some(20));
+}
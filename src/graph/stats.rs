@@ -0,0 +1,205 @@
+//! Detailed statistics of the book graph and its clusters.
+//!
+//! These are computed from the node, edge, and cluster statistics tables after
+//! they are written, so both clustering backends produce the same statistics.
+use anyhow::Result;
+use log::*;
+use polars::prelude::*;
+
+use super::model::{CLUSTER_STATS_PATH, GRAPH_EDGE_PATH, GRAPH_NODE_PATH};
+use crate::arrow::{save_df_parquet, scan_df_parquet};
+
+const SIZE_DIST_PATH: &str = "book-links/cluster-size-dist.parquet";
+const DEGREE_DIST_PATH: &str = "book-links/node-degree-dist.parquet";
+const NS_COMBO_PATH: &str = "book-links/cluster-ns-combos.parquet";
+const NS_STATS_PATH: &str = "book-links/cluster-ns-stats.parquet";
+
+/// Namespaces and their count columns in the cluster statistics table.
+const NS_COUNTS: &[(&str, &str)] = &[
+    ("ISBN", "n_isbns"),
+    ("LOC", "n_loc_recs"),
+    ("OL-W", "n_ol_works"),
+    ("OL-E", "n_ol_editions"),
+    ("GR-W", "n_gr_works"),
+    ("GR-B", "n_gr_books"),
+];
+
+/// The namespaces of each record source (LOC, OpenLibrary, and GoodReads).
+const SOURCES: &[&[&str]] = &[&["LOC"], &["OL-W", "OL-E"], &["GR-W", "GR-B"]];
+
+/// Expression for whether a cluster has any nodes in the given namespaces.
+fn has_any(namespaces: &[&str]) -> Expr {
+    NS_COUNTS
+        .iter()
+        .filter(|(ns, _)| namespaces.contains(ns))
+        .map(|(_, c)| col(c).gt(lit(0u32)))
+        .reduce(|a, b| a.or(b))
+        .unwrap()
+}
+
+/// Name the combination of namespaces in a mask of [NS_COUNTS] positions.
+fn combo_name(mask: u32) -> String {
+    let names: Vec<_> = NS_COUNTS
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| mask & (1 << i) != 0)
+        .map(|(_, (ns, _))| *ns)
+        .collect();
+    names.join("+")
+}
+
+/// Compute the distribution of cluster sizes.
+fn size_distribution(stats: LazyFrame) -> LazyFrame {
+    stats
+        .group_by([col("n_nodes").alias("size")])
+        .agg([col("cluster").count().alias("n_clusters")])
+        .sort("size", SortOptions::default())
+}
+
+/// Compute the degree distribution of each namespace's nodes.
+fn degree_distribution(nodes: LazyFrame, edges: LazyFrame) -> Result<LazyFrame> {
+    let ends = concat(
+        [
+            edges.clone().select([col("src").alias("book_code")]),
+            edges.select([col("dst").alias("book_code")]),
+        ],
+        UnionArgs::default(),
+    )?;
+    let degrees = ends
+        .group_by([col("book_code")])
+        .agg([col("book_code").count().alias("degree")]);
+    let dist = nodes
+        .select([col("book_code"), col("node_type").alias("namespace")])
+        .left_join(degrees, col("book_code"), col("book_code"))
+        .select([col("namespace"), col("degree").fill_null(lit(0u32))])
+        .group_by([col("namespace"), col("degree")])
+        .agg([col("degree").count().alias("n_nodes")])
+        .sort_by_exprs(
+            [col("namespace"), col("degree")],
+            [false, false],
+            false,
+            false,
+        );
+    Ok(dist)
+}
+
+/// Compute the histogram of namespace combinations in clusters.
+fn namespace_combinations(stats: LazyFrame) -> Result<DataFrame> {
+    let mask = NS_COUNTS
+        .iter()
+        .enumerate()
+        .map(|(i, (_, c))| {
+            when(col(c).gt(lit(0u32)))
+                .then(lit(1u32 << i))
+                .otherwise(lit(0u32))
+        })
+        .reduce(|a, b| a + b)
+        .unwrap();
+    let combos = stats
+        .group_by([mask.alias("mask")])
+        .agg([
+            col("cluster").count().alias("n_clusters"),
+            col("n_nodes").sum().alias("n_nodes"),
+        ])
+        .sort_by_exprs(
+            [col("n_clusters"), col("mask")],
+            [true, false],
+            false,
+            false,
+        )
+        .collect()?;
+
+    let names: StringChunked = combos
+        .column("mask")?
+        .u32()?
+        .into_iter()
+        .map(|m| m.map(combo_name))
+        .collect();
+    let names = names.into_series().with_name("namespaces");
+    let mut combos = combos.drop("mask")?;
+    combos.insert_column(0, names)?;
+    Ok(combos)
+}
+
+/// Compute per-namespace singleton and cross-source link statistics.
+fn namespace_stats(nodes: LazyFrame, stats: LazyFrame) -> LazyFrame {
+    // ISBNs are not records of any source, so they have no cross-source status
+    let mut cross = lit(NULL).cast(DataType::Boolean);
+    for (i, nss) in SOURCES.iter().enumerate().rev() {
+        let others: Vec<&str> = SOURCES
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .flat_map(|(_, o)| o.iter().copied())
+            .collect();
+        let is_src = nss
+            .iter()
+            .map(|ns| col("node_type").eq(lit(*ns)))
+            .reduce(|a, b| a.or(b))
+            .unwrap();
+        cross = when(is_src).then(has_any(&others)).otherwise(cross);
+    }
+
+    nodes
+        .select([col("book_code"), col("cluster"), col("node_type")])
+        .join(
+            stats,
+            [col("cluster")],
+            [col("cluster")],
+            JoinType::Inner.into(),
+        )
+        .select([
+            col("node_type").alias("namespace"),
+            col("n_nodes").eq(lit(1u32)).alias("singleton"),
+            cross.alias("cross_source"),
+        ])
+        .group_by([col("namespace")])
+        .agg([
+            col("singleton").count().alias("n_nodes"),
+            col("singleton")
+                .cast(DataType::UInt32)
+                .sum()
+                .alias("n_singletons"),
+            col("cross_source")
+                .cast(DataType::UInt32)
+                .sum()
+                .alias("n_cross_source"),
+            col("cross_source")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("cross_source_share"),
+        ])
+        .sort("namespace", SortOptions::default())
+}
+
+/// Compute and save the detailed graph and cluster statistics tables.
+pub(super) fn save_detailed_stats() -> Result<()> {
+    let nodes = scan_df_parquet(GRAPH_NODE_PATH)?;
+    let edges = scan_df_parquet(GRAPH_EDGE_PATH)?;
+    let stats = scan_df_parquet(CLUSTER_STATS_PATH)?;
+
+    info!("computing cluster size distribution");
+    let sizes = size_distribution(stats.clone()).collect()?;
+    save_df_parquet(sizes, SIZE_DIST_PATH)?;
+
+    info!("computing node degree distribution");
+    let degrees = degree_distribution(nodes.clone(), edges)?.collect()?;
+    save_df_parquet(degrees, DEGREE_DIST_PATH)?;
+
+    info!("computing namespace combinations");
+    let combos = namespace_combinations(stats.clone())?;
+    save_df_parquet(combos, NS_COMBO_PATH)?;
+
+    info!("computing namespace link statistics");
+    let ns_stats = namespace_stats(nodes, stats).collect()?;
+    save_df_parquet(ns_stats, NS_STATS_PATH)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_combo_name() {
+    assert_eq!(combo_name(0), "");
+    assert_eq!(combo_name(0b1), "ISBN");
+    assert_eq!(combo_name(0b101001), "ISBN+OL-E+GR-B");
+}
@@ -0,0 +1,346 @@
+//! Streaming union-find clustering.
+//!
+//! Computing connected components does not require the full graph: union-find over
+//! the edge list suffices.  This module implements a clustering backend that reads
+//! the node codes into a sorted array (so each node has a dense index), then streams
+//! the edges from each [EdgeRead] source in batches into a disjoint-set structure
+//! over those indices.  It never builds adjacency lists or holds node labels in
+//! memory; labels are re-read from the node sources when writing the cluster
+//! outputs.
+//!
+//! The outputs are the same as [save_graph_cluster_data][super::model::save_graph_cluster_data],
+//! although rows are written in a different order.
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use log::*;
+use polars::prelude::*;
+
+use super::model::*;
+use super::sources::*;
+use super::stats::save_detailed_stats;
+use crate::arrow::{scan_df_batches, TableWriter};
+use crate::ids::codes::{ns_of_book_code, NS_ISBN};
+use crate::io::ObjectWriter;
+use crate::layout::Config;
+use crate::util::logging::item_progress;
+
+/// Number of edges to read from a source at a time.
+const EDGE_BATCH_SIZE: usize = 1024 * 1024;
+
+/// Disjoint-set forest with union by size and path halving.
+pub struct DisjointSet {
+    parent: Vec<u32>,
+    size: Vec<u32>,
+}
+
+impl DisjointSet {
+    /// Create a disjoint-set forest with `n` singleton sets.
+    pub fn new(n: usize) -> DisjointSet {
+        DisjointSet {
+            parent: (0..n as u32).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    /// Find the representative of an element's set.
+    pub fn find(&mut self, mut x: u32) -> u32 {
+        while self.parent[x as usize] != x {
+            let gp = self.parent[self.parent[x as usize] as usize];
+            self.parent[x as usize] = gp;
+            x = gp;
+        }
+        x
+    }
+
+    /// Merge the sets containing two elements.  Returns `true` if they were
+    /// previously in different sets.
+    pub fn union(&mut self, a: u32, b: u32) -> bool {
+        let ra = self.find(a);
+        let rb = self.find(b);
+        if ra == rb {
+            return false;
+        }
+        let (big, small) = if self.size[ra as usize] >= self.size[rb as usize] {
+            (ra, rb)
+        } else {
+            (rb, ra)
+        };
+        self.parent[small as usize] = big;
+        self.size[big as usize] += self.size[small as usize];
+        true
+    }
+}
+
+/// Sorted array of node codes, used to map codes to dense indices.
+struct NodeCodes {
+    codes: Vec<i32>,
+}
+
+impl NodeCodes {
+    /// Read the node codes from all node sources.
+    fn load(sources: &[Box<dyn NodeRead>]) -> Result<NodeCodes> {
+        let mut codes = Vec::new();
+        for src in sources {
+            info!("scanning vertices from {:?}", src);
+            let df = src.read_node_ids()?.select([col("code")]).collect()?;
+            let code_s = df.column("code")?.cast(&DataType::Int32)?;
+            codes.extend(code_s.i32()?.into_iter().flatten());
+        }
+        info!("sorting {} node codes", codes.len());
+        codes.sort_unstable();
+        codes.dedup();
+        codes.shrink_to_fit();
+        info!("found {} distinct nodes", codes.len());
+        Ok(NodeCodes { codes })
+    }
+
+    fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Look up the dense index of a code.
+    fn index(&self, code: i32) -> Option<u32> {
+        self.codes.binary_search(&code).ok().map(|i| i as u32)
+    }
+}
+
+/// Stream edges from a source into the disjoint sets, writing them to the edge table.
+///
+/// The edge file is read once, in batches of at most `batch_size` rows, so
+/// memory use does not grow with the size of the edge table.
+fn stream_edges(
+    src: &dyn EdgeRead,
+    nodes: &NodeCodes,
+    sets: &mut DisjointSet,
+    out: &mut TableWriter<GraphEdge>,
+    batch_size: usize,
+) -> Result<()> {
+    info!("streaming edges from {:?}", src);
+    let pb = item_progress(0, "edges");
+
+    let mut n = 0;
+    let mut n_merged = 0;
+    for batch in scan_df_batches(src.edge_file(), batch_size)? {
+        let rows = batch?;
+        let n_rows = rows.height();
+        let df = src
+            .select_edges(rows.lazy())?
+            .select([
+                col("src").cast(DataType::Int32),
+                col("dst").cast(DataType::Int32),
+            ])
+            .collect()?;
+        let srcs = df.column("src")?.i32()?;
+        let dsts = df.column("dst")?.i32()?;
+        for pair in srcs.into_iter().zip(dsts) {
+            if let (Some(sn), Some(dn)) = pair {
+                let si = nodes
+                    .index(sn)
+                    .ok_or_else(|| anyhow!("unknown source node {}", sn))?;
+                let di = nodes
+                    .index(dn)
+                    .ok_or_else(|| anyhow!("unknown destination node {}", dn))?;
+                if sets.union(si, di) {
+                    n_merged += 1;
+                }
+                out.write_object(GraphEdge { src: sn, dst: dn })?;
+                n += 1;
+            }
+        }
+        pb.inc(n_rows as u64);
+    }
+    pb.finish_and_clear();
+
+    info!("streamed {} edges ({} merges) from {:?}", n, n_merged, src);
+    Ok(())
+}
+
+/// Resolve each node's cluster ID (the smallest code in its set).
+fn resolve_clusters(nodes: &NodeCodes, sets: DisjointSet) -> Vec<i32> {
+    let mut sets = sets;
+    let n = nodes.len();
+    // since codes are sorted, the first node we see in each set has its smallest code
+    let mut root_cluster = vec![0i32; n];
+    let mut clusters = Vec::with_capacity(n);
+    for i in 0..n {
+        let root = sets.find(i as u32) as usize;
+        if root_cluster[root] == 0 {
+            root_cluster[root] = nodes.codes[i];
+        }
+        clusters.push(root_cluster[root]);
+    }
+    clusters
+}
+
+/// Write the node and ISBN tables, re-reading labels from the node sources.
+fn write_nodes(sources: &[Box<dyn NodeRead>], nodes: &NodeCodes, clusters: &[i32]) -> Result<()> {
+    let mut ic_w = TableWriter::open(ISBN_CLUSTER_PATH)?;
+    let mut n_w = TableWriter::open(GRAPH_NODE_PATH)?;
+    let mut written = vec![false; nodes.len()];
+
+    for src in sources {
+        info!("writing nodes from {:?}", src);
+        let mut df = src.read_node_ids()?.collect()?;
+        let code_s = df.drop_in_place("code")?.cast(&DataType::Int32)?;
+        let codes = code_s.i32()?;
+        let labels = df.column("label").ok().map(|c| c.str()).transpose()?;
+        for i in 0..codes.len() {
+            let code = codes.get(i).unwrap();
+            let idx = nodes.index(code).expect("node missing from index") as usize;
+            if written[idx] {
+                continue;
+            }
+            written[idx] = true;
+            let cluster = clusters[idx];
+            let label = labels.and_then(|c| c.get(i)).map(|s| s.to_string());
+            if let Some(id) = NS_ISBN.from_code(code) {
+                ic_w.write_object(ISBNClusterRec {
+                    cluster,
+                    isbn_id: id,
+                    isbn: label
+                        .clone()
+                        .ok_or_else(|| anyhow!("graph node missing ISBN label"))?,
+                })?;
+            }
+            n_w.write_object(ClusterCode {
+                cluster,
+                book_code: code,
+                node_type: ns_of_book_code(code).unwrap().name.to_string(),
+                label,
+            })?;
+        }
+    }
+
+    ic_w.finish()?;
+    n_w.finish()?;
+    Ok(())
+}
+
+/// Write the cluster statistics and metrics.
+fn write_stats(nodes: &NodeCodes, clusters: &[i32]) -> Result<()> {
+    info!("computing cluster statistics");
+    let mut stats: HashMap<i32, ClusterStat> = HashMap::new();
+    for (code, cluster) in nodes.codes.iter().zip(clusters) {
+        stats
+            .entry(*cluster)
+            .or_insert_with(|| ClusterStat {
+                cluster: *cluster,
+                ..ClusterStat::default()
+            })
+            .add_code(*code);
+    }
+
+    let mut metrics = ClusteringStatistics {
+        clusters: stats.len(),
+        largest: 0,
+        max_isbns: 0,
+    };
+    let mut m_id = 0;
+    let mut cs_w = TableWriter::open(CLUSTER_STATS_PATH)?;
+    for (_, cs) in stats {
+        if cs.n_nodes as usize > metrics.largest {
+            metrics.largest = cs.n_nodes as usize;
+            m_id = cs.cluster;
+        }
+        metrics.max_isbns = metrics.max_isbns.max(cs.n_isbns as usize);
+        cs_w.write_object(cs)?;
+    }
+    cs_w.finish()?;
+
+    info!("largest cluster {} has {} nodes", m_id, metrics.largest);
+    save_metrics(&metrics)
+}
+
+/// Cluster the book graph with streaming union-find and save the cluster outputs.
+pub fn cluster_union_find(cfg: &Config, edges: &[Box<dyn EdgeRead>]) -> Result<()> {
+    let node_srcs = node_sources(cfg);
+    let nodes = NodeCodes::load(&node_srcs)?;
+    let mut sets = DisjointSet::new(nodes.len());
+
+    info!("writing graph edges");
+    let mut e_w = TableWriter::open(GRAPH_EDGE_PATH)?;
+    for src in edges {
+        stream_edges(src.as_ref(), &nodes, &mut sets, &mut e_w, EDGE_BATCH_SIZE)?;
+    }
+    e_w.finish()?;
+
+    info!("resolving clusters");
+    let clusters = resolve_clusters(&nodes, sets);
+
+    info!("writing graph nodes");
+    write_nodes(&node_srcs, &nodes, &clusters)?;
+    write_stats(&nodes, &clusters)?;
+    save_detailed_stats()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_union_find_singletons() {
+    let mut sets = DisjointSet::new(5);
+    for i in 0..5 {
+        assert_eq!(sets.find(i), i);
+    }
+}
+
+#[test]
+fn test_union_find_merge() {
+    let mut sets = DisjointSet::new(6);
+    assert!(sets.union(0, 1));
+    assert!(sets.union(2, 3));
+    assert!(sets.union(1, 3));
+    assert!(!sets.union(0, 2));
+    assert_eq!(sets.find(0), sets.find(3));
+    assert_ne!(sets.find(0), sets.find(4));
+    assert_ne!(sets.find(4), sets.find(5));
+}
+
+#[test]
+fn test_resolve_min_code() {
+    let nodes = NodeCodes {
+        codes: vec![100, 200, 300, 400],
+    };
+    let mut sets = DisjointSet::new(4);
+    sets.union(3, 1);
+    sets.union(2, 3);
+    let clusters = resolve_clusters(&nodes, sets);
+    assert_eq!(clusters, vec![100, 200, 200, 200]);
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct TestEdges(std::path::PathBuf);
+
+#[cfg(test)]
+impl EdgeRead for TestEdges {
+    fn edge_file(&self) -> std::path::PathBuf {
+        self.0.clone()
+    }
+
+    fn select_edges(&self, df: LazyFrame) -> Result<LazyFrame> {
+        Ok(df.filter(col("src").is_not_null()))
+    }
+}
+
+#[test]
+fn test_stream_edge_batches() {
+    let dir = tempfile::tempdir().unwrap();
+    let nodes = NodeCodes {
+        codes: vec![10, 20, 30, 40, 50, 60],
+    };
+    let path = dir.path().join("input.parquet");
+    let df = df!(
+        "src" => [Some(10), None, Some(20), Some(40), Some(50), Some(60)],
+        "dst" => [20, 10, 30, 50, 60, 40]
+    )
+    .unwrap();
+    crate::arrow::save_df_parquet(df, &path).unwrap();
+    let edges = TestEdges(path);
+    let mut sets = DisjointSet::new(nodes.len());
+    let mut out = TableWriter::open(dir.path().join("edges.parquet")).unwrap();
+    stream_edges(&edges, &nodes, &mut sets, &mut out, 2).unwrap();
+    assert_eq!(out.finish().unwrap(), 5);
+    let clusters = resolve_clusters(&nodes, sets);
+    assert_eq!(clusters, vec![10, 10, 10, 40, 40, 40]);
+}
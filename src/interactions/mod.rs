@@ -4,11 +4,16 @@
 //! logic across data sets.  We always store timestamps, dropping them at output time, because
 //! the largest data sets have timestamps.  Saving space for the smallest data set doesn't
 //! seem worthwhile.
+//!
+//! It also harmonizes rating scales across data sets (see [normalize_ratings]).
 use anyhow::Result;
 use std::path::Path;
 
 mod actions;
 mod ratings;
+mod scale;
+
+pub use scale::normalize_ratings;
 
 /// Trait for an interaction.
 pub trait Interaction {
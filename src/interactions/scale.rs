@@ -0,0 +1,80 @@
+//! Harmonize rating scales across data sets.
+//!
+//! The rating data sets use different scales (BookCrossing rates 1–10, while
+//! Amazon and GoodReads rate 1–5).  When enabled in `config.yaml`, the cluster
+//! rating files get additional columns with ratings on common scales:
+//!
+//! - `rating_norm` — the rating rescaled to 0–1
+//! - `rating_5pt` — the rating rescaled to 1–5
+//! - `liked` — whether the rating is at or above the data set's “liked” threshold
+use anyhow::Result;
+use log::*;
+use polars::prelude::*;
+
+use crate::layout::{load_config, RatingConfig};
+
+/// Compute the normalized rating columns from a `rating` column.
+pub fn rating_scale_columns(rc: &RatingConfig) -> Vec<Expr> {
+    let (lo, hi) = rc.scale;
+    let unit = (col("rating").cast(DataType::Float32) - lit(lo)) / lit(hi - lo);
+    vec![
+        unit.clone().alias("rating_norm"),
+        (lit(1.0f32) + unit * lit(4.0f32)).alias("rating_5pt"),
+        col("rating").gt_eq(lit(rc.liked)).alias("liked"),
+    ]
+}
+
+/// Add normalized rating columns to a frame, if configured for the data set.
+pub fn normalize_ratings(frame: LazyFrame, dataset: &str) -> Result<LazyFrame> {
+    let cfg = load_config()?;
+    if let Some(rc) = cfg.rating_normalization(dataset) {
+        info!(
+            "normalizing {} ratings from scale {}–{} (liked at {})",
+            dataset, rc.scale.0, rc.scale.1, rc.liked
+        );
+        Ok(frame.with_columns(rating_scale_columns(rc)))
+    } else {
+        Ok(frame)
+    }
+}
+
+#[test]
+fn test_rating_scale() {
+    let rc = RatingConfig {
+        normalize: true,
+        scale: (1.0, 10.0),
+        liked: 8.0,
+    };
+    let df = df!("rating" => [1.0f32, 5.5, 8.0, 10.0]).unwrap();
+    let df = df
+        .lazy()
+        .with_columns(rating_scale_columns(&rc))
+        .collect()
+        .unwrap();
+    let norm: Vec<f32> = df
+        .column("rating_norm")
+        .unwrap()
+        .f32()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(norm, vec![0.0, 0.5, 7.0 / 9.0, 1.0]);
+    let five: Vec<f32> = df
+        .column("rating_5pt")
+        .unwrap()
+        .f32()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(five[0], 1.0);
+    assert_eq!(five[1], 3.0);
+    assert_eq!(five[3], 5.0);
+    let liked: Vec<bool> = df
+        .column("liked")
+        .unwrap()
+        .bool()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(liked, vec![false, false, true, true]);
+}
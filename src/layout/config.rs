@@ -8,14 +8,30 @@ use super::path::BDPath;
 
 const CFG_PATH: BDPath<'static> = BDPath::new("config.yaml");
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct DSConfig {
     pub enabled: bool,
+    #[serde(default)]
+    pub ratings: Option<RatingConfig>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct GRConfig {
     pub enabled: bool,
+    #[serde(default)]
+    pub ratings: Option<RatingConfig>,
+}
+
+/// Rating scale configuration for a data set.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RatingConfig {
+    /// Whether to add normalized rating columns to cluster rating files.
+    #[serde(default)]
+    pub normalize: bool,
+    /// The minimum and maximum of the data set's native rating scale.
+    pub scale: (f32, f32),
+    /// The threshold at or above which a rating counts as “liked”.
+    pub liked: f32,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -23,6 +39,9 @@ pub struct Config {
     pub bx: DSConfig,
     pub az2014: DSConfig,
     pub az2018: DSConfig,
+    /// Added after the other data sets, so older configurations leave it disabled.
+    #[serde(default)]
+    pub az2023: DSConfig,
     pub goodreads: GRConfig,
 }
 
@@ -39,10 +58,25 @@ impl Config {
             "goodreads" | "GR" => self.goodreads.enabled,
             "az2014" | "AZ14" => self.az2014.enabled,
             "az2018" | "AZ18" => self.az2018.enabled,
+            "az2023" | "AZ23" => self.az2023.enabled,
             "bx" | "BX" => self.bx.enabled,
             _ => panic!("unsupported data set {}", name),
         }
     }
+
+    /// Get the rating scale configuration for a data set, if its ratings
+    /// should be normalized.
+    pub fn rating_normalization(&self, name: &str) -> Option<&RatingConfig> {
+        let rc = match name {
+            "goodreads" | "GR" => self.goodreads.ratings.as_ref(),
+            "az2014" | "AZ14" => self.az2014.ratings.as_ref(),
+            "az2018" | "AZ18" => self.az2018.ratings.as_ref(),
+            "az2023" | "AZ23" => self.az2023.ratings.as_ref(),
+            "bx" | "BX" => self.bx.ratings.as_ref(),
+            _ => None,
+        };
+        rc.filter(|rc| rc.normalize)
+    }
 }
 
 /// Load the configuration for this project.
@@ -53,3 +87,21 @@ pub fn load_config() -> Result<Config> {
     let cfg: Config = serde_yaml::from_str(&f)?;
     Ok(cfg)
 }
+
+#[test]
+fn test_config_without_az2023() {
+    let yaml = "
+bx:
+  enabled: true
+az2014:
+  enabled: true
+az2018:
+  enabled: false
+goodreads:
+  enabled: true
+";
+    let cfg: Config = serde_yaml::from_str(yaml).unwrap();
+    assert!(cfg.ds_enabled("bx"));
+    assert!(!cfg.ds_enabled("az2023"));
+    assert!(cfg.rating_normalization("az2023").is_none());
+}
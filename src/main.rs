@@ -22,6 +22,7 @@ mod parsing;
 mod prelude;
 mod tsv;
 mod util;
+mod wikidata;
 
 // use mimalloc::MiMalloc;
 
@@ -26,6 +26,14 @@ pub struct AuthorNameRec {
     pub name: String,
 }
 
+/// An author remote identifier record in the extracted Parquet.
+#[derive(ParquetRecordWriter)]
+pub struct AuthorRemoteIdRec {
+    pub id: u32,
+    pub source: String,
+    pub remote_id: String,
+}
+
 /// Get a list of author name records for an author.
 pub fn author_name_records(src: &OLAuthorSource, id: u32) -> Vec<AuthorNameRec> {
     let mut names = Vec::new();
@@ -61,6 +69,7 @@ pub fn author_name_records(src: &OLAuthorSource, id: u32) -> Vec<AuthorNameRec>
 pub struct AuthorProcessor {
     rec_writer: TableWriter<AuthorRec>,
     name_writer: TableWriter<AuthorNameRec>,
+    remote_writer: TableWriter<AuthorRemoteIdRec>,
 }
 
 impl AuthorProcessor {
@@ -68,6 +77,7 @@ impl AuthorProcessor {
         Ok(AuthorProcessor {
             rec_writer: TableWriter::open("authors.parquet")?,
             name_writer: TableWriter::open("author-names.parquet")?,
+            remote_writer: TableWriter::open("author-remote-ids.parquet")?,
         })
     }
 }
@@ -90,12 +100,24 @@ impl ObjectWriter<Row<OLAuthorSource>> for AuthorProcessor {
             self.name_writer.write_object(name)?;
         }
 
+        for (source, rid) in &row.record.remote_ids {
+            // a few records have non-string identifiers; we only keep strings
+            if let Some(rid) = rid.as_str() {
+                self.remote_writer.write_object(AuthorRemoteIdRec {
+                    id,
+                    source: source.clone(),
+                    remote_id: rid.trim().to_string(),
+                })?;
+            }
+        }
+
         Ok(())
     }
 
     fn finish(self) -> Result<usize> {
         let nr = self.rec_writer.finish()?;
         self.name_writer.finish()?;
+        self.remote_writer.finish()?;
         Ok(nr)
     }
 }
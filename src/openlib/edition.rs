@@ -5,6 +5,7 @@ use parquet_derive::ParquetRecordWriter;
 use crate::arrow::*;
 use crate::cleaning::isbns::clean_asin_chars;
 use crate::cleaning::isbns::clean_isbn_chars;
+use crate::parsing::trim_owned;
 use crate::prelude::*;
 
 use super::key::parse_ol_key;
@@ -19,6 +20,9 @@ pub struct EditionRec {
     pub id: u32,
     pub key: String,
     pub title: Option<String>,
+    /// The MARC code of the edition's first language.
+    pub language: Option<String>,
+    pub physical_format: Option<String>,
 }
 
 /// Link between edition and work.
@@ -112,6 +116,12 @@ impl ObjectWriter<Row<OLEditionRecord>> for EditionProcessor {
             id,
             key: row.key.clone(),
             title: row.record.title.clone(),
+            language: row
+                .record
+                .languages
+                .first()
+                .map(|l| l.key.trim_start_matches("/languages/").to_string()),
+            physical_format: row.record.physical_format.as_deref().and_then(trim_owned),
         })?;
 
         self.save_isbns(id, row.record.isbn_10, clean_isbn_chars)?;
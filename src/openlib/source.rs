@@ -1,4 +1,5 @@
 //! Parse OpenLibrary JSON.
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use log::*;
@@ -99,6 +100,9 @@ pub struct OLAuthorSource {
     pub personal_name: Option<String>,
     #[serde(default)]
     pub alternate_names: Vec<String>,
+    /// Identifiers for this author in other services (e.g. VIAF or Wikidata).
+    #[serde(default)]
+    pub remote_ids: HashMap<String, serde_json::Value>,
 }
 
 /// An edition record parsed from OpenLibrary JSON.
@@ -113,6 +117,10 @@ pub struct OLEditionRecord {
 
     #[serde(default)]
     pub title: Option<String>,
+    #[serde(default)]
+    pub physical_format: Option<String>,
+    #[serde(default)]
+    pub languages: Vec<Keyed>,
 
     #[serde(default)]
     pub works: Vec<Keyed>,
@@ -13,12 +13,21 @@ pub fn parse_gr_date(s: &str) -> ParseResult<NaiveDateTime> {
     Ok(date)
 }
 
-/// Check a date and convert to a timestamp.
-pub fn check_ts(context: &'static str, cutoff: i32) -> impl Fn(NaiveDateTime) -> f32 {
+/// Check a date and convert to a UNIX timestamp (in seconds).
+pub fn check_ts(context: &'static str, cutoff: i32) -> impl Fn(NaiveDateTime) -> i64 {
     move |date| {
         if date.year() < cutoff {
             warn!("{} is ancient: {}", context, date);
         }
-        date.timestamp() as f32
+        date.timestamp()
     }
 }
+
+#[test]
+fn test_check_ts_precision() {
+    let d1 = parse_gr_date("Mon Aug 25 17:43:07 -0700 2014").unwrap();
+    let d2 = parse_gr_date("Mon Aug 25 17:43:08 -0700 2014").unwrap();
+    let check = check_ts("test", 2000);
+    assert_eq!(check(d1), 1409013787);
+    assert_eq!(check(d2) - check(d1), 1);
+}
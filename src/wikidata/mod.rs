@@ -0,0 +1,387 @@
+//! Wikidata entity schemas and record processing.
+//!
+//! The Wikidata JSON dump is a single JSON array with one entity per line.  We
+//! only extract humans (instances of [Q5][HUMAN]), recording their labels and
+//! aliases, gender ([P21][P_GENDER]), birth and death dates, and identifiers in
+//! other authority files so they can be linked to VIAF and OpenLibrary.
+use std::collections::{HashMap, HashSet};
+
+use parquet_derive::ParquetRecordWriter;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::arrow::*;
+use crate::cleaning::names::clean_name;
+use crate::prelude::*;
+
+/// The item ID for humans.
+pub const HUMAN: &str = "Q5";
+
+/// Property for “instance of”.
+pub const P_INSTANCE: &str = "P31";
+/// Property for “sex or gender”.
+pub const P_GENDER: &str = "P21";
+/// Property for “date of birth”.
+pub const P_BIRTH: &str = "P569";
+/// Property for “date of death”.
+pub const P_DEATH: &str = "P570";
+
+/// External identifier properties we extract, with their source names.
+pub const EXTERNAL_IDS: &[(&str, &str)] = &[
+    ("P214", "viaf"),
+    ("P244", "loc"),
+    ("P648", "openlibrary"),
+    ("P2963", "goodreads"),
+];
+
+/// Gender items with the labels we record for them.  Other gender items are
+/// recorded by their item ID.
+const GENDER_ITEMS: &[(&str, &str)] = &[
+    ("Q6581097", "male"),
+    ("Q6581072", "female"),
+    ("Q1097630", "intersex"),
+    ("Q1052281", "trans woman"),
+    ("Q2449503", "trans man"),
+    ("Q48270", "non-binary"),
+];
+
+const HUMAN_FILE: &str = "humans.parquet";
+const NAME_FILE: &str = "names.parquet";
+const GENDER_FILE: &str = "genders.parquet";
+const EXT_ID_FILE: &str = "external-ids.parquet";
+
+/// A language-tagged string value.
+#[derive(Deserialize, Debug)]
+pub struct LangValue {
+    pub value: String,
+}
+
+/// A statement's main property-value pair.
+#[derive(Deserialize, Debug)]
+pub struct Snak {
+    pub datavalue: Option<DataValue>,
+}
+
+/// A value in a statement.
+#[derive(Deserialize, Debug)]
+pub struct DataValue {
+    pub value: Value,
+}
+
+/// A statement (claim) about an entity.
+#[derive(Deserialize, Debug)]
+pub struct Statement {
+    pub mainsnak: Snak,
+    #[serde(default)]
+    pub rank: Option<String>,
+}
+
+/// An entity as parsed from the JSON dump.
+#[derive(Deserialize, Debug)]
+pub struct RawEntity {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub etype: String,
+    #[serde(default)]
+    pub labels: HashMap<String, LangValue>,
+    #[serde(default)]
+    pub aliases: HashMap<String, Vec<LangValue>>,
+    #[serde(default)]
+    pub claims: HashMap<String, Vec<Statement>>,
+}
+
+/// A human in the extracted Parquet.
+#[derive(ParquetRecordWriter, Debug)]
+pub struct HumanRec {
+    pub id: u32,
+    pub label: Option<String>,
+    pub birth_year: Option<i16>,
+    pub death_year: Option<i16>,
+    /// The birth date, to the precision Wikidata records it (e.g. `1775-12-16`,
+    /// `1775-12`, or `1775`).
+    pub birth_date: Option<String>,
+    /// The death date, to the precision Wikidata records it.
+    pub death_date: Option<String>,
+}
+
+/// A human's name (label or alias) in the extracted Parquet.
+#[derive(ParquetRecordWriter, Debug)]
+pub struct NameRec {
+    pub id: u32,
+    pub name: String,
+}
+
+/// A human's gender in the extracted Parquet.
+#[derive(ParquetRecordWriter, Debug)]
+pub struct GenderRec {
+    pub id: u32,
+    pub gender: String,
+}
+
+/// A human's identifier in another authority file.
+#[derive(ParquetRecordWriter, Debug)]
+pub struct ExternalIdRec {
+    pub id: u32,
+    pub source: String,
+    pub ext_id: String,
+}
+
+/// Parse the numeric part of an item ID (e.g. `Q42`).
+pub fn parse_qid(id: &str) -> Result<u32> {
+    let num = id
+        .strip_prefix('Q')
+        .ok_or_else(|| anyhow!("invalid item ID {}", id))?;
+    Ok(num.parse()?)
+}
+
+/// Parse the year from a Wikidata time value (e.g. `+1775-12-16T00:00:00Z`).
+pub fn parse_time_year(time: &str) -> Option<i16> {
+    let (neg, rest) = match time.as_bytes().first()? {
+        b'+' => (false, &time[1..]),
+        b'-' => (true, &time[1..]),
+        _ => (false, time),
+    };
+    let year: i16 = rest.split('-').next()?.parse().ok()?;
+    Some(if neg { -year } else { year })
+}
+
+/// Format a Wikidata time value as a date, truncated to its precision.
+///
+/// Wikidata times are ISO 8601 timestamps with a sign, and a precision of 11 for
+/// days, 10 for months, and 9 (or coarser) for years; imprecise times have zeros
+/// for the unknown parts (`+1775-00-00T00:00:00Z`).
+pub fn parse_time_date(time: &str, precision: u64) -> Option<String> {
+    let neg = time.starts_with('-');
+    let date = time.trim_start_matches(['+', '-']).split('T').next()?;
+    let mut parts = date.split('-');
+    let year = parts.next()?;
+    if year.is_empty() || !year.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut out = if neg {
+        format!("-{}", year)
+    } else {
+        year.to_string()
+    };
+    for (part, prec) in parts.zip([10, 11]) {
+        if precision < prec || part == "00" {
+            break;
+        }
+        out.push('-');
+        out.push_str(part);
+    }
+    Some(out)
+}
+
+/// Get the label to record for a gender item.
+pub fn gender_label(item: &str) -> String {
+    GENDER_ITEMS
+        .iter()
+        .find(|(q, _)| *q == item)
+        .map(|(_, l)| l.to_string())
+        .unwrap_or_else(|| item.to_string())
+}
+
+impl RawEntity {
+    /// Iterate over the values of a property's non-deprecated statements.
+    fn values<'a>(&'a self, prop: &str) -> impl Iterator<Item = &'a Value> + 'a {
+        self.claims
+            .get(prop)
+            .into_iter()
+            .flatten()
+            .filter(|s| s.rank.as_deref() != Some("deprecated"))
+            .filter_map(|s| s.mainsnak.datavalue.as_ref())
+            .map(|dv| &dv.value)
+    }
+
+    /// Get the item IDs that are values of a property.
+    pub fn item_values<'a>(&'a self, prop: &str) -> impl Iterator<Item = &'a str> + 'a {
+        self.values(prop).filter_map(|v| v.get("id")?.as_str())
+    }
+
+    /// Get the string values of a property.
+    pub fn string_values<'a>(&'a self, prop: &str) -> impl Iterator<Item = &'a str> + 'a {
+        self.values(prop).filter_map(|v| v.as_str())
+    }
+
+    /// Get the first year recorded for a time-valued property.
+    pub fn year_value(&self, prop: &str) -> Option<i16> {
+        self.values(prop)
+            .filter_map(|v| v.get("time")?.as_str())
+            .find_map(parse_time_year)
+    }
+
+    /// Get the first date recorded for a time-valued property.
+    pub fn date_value(&self, prop: &str) -> Option<String> {
+        self.values(prop).find_map(|v| {
+            let time = v.get("time")?.as_str()?;
+            let precision = v.get("precision").and_then(|p| p.as_u64()).unwrap_or(9);
+            parse_time_date(time, precision)
+        })
+    }
+
+    /// Check whether this entity is a human.
+    pub fn is_human(&self) -> bool {
+        self.etype == "item" && self.item_values(P_INSTANCE).any(|q| q == HUMAN)
+    }
+
+    /// Get the entity's preferred label (English if available).
+    pub fn label(&self) -> Option<&str> {
+        self.labels
+            .get("en")
+            .or_else(|| self.labels.get("mul"))
+            .or_else(|| self.labels.values().next())
+            .map(|l| l.value.as_str())
+    }
+
+    /// Get the distinct cleaned names (labels and aliases) of this entity.
+    pub fn names(&self) -> Vec<String> {
+        let labels = self.labels.values();
+        let aliases = self.aliases.values().flatten();
+        let names: HashSet<String> = labels
+            .chain(aliases)
+            .map(|l| clean_name(&l.value))
+            .filter(|n| !n.is_empty())
+            .collect();
+        let mut names: Vec<_> = names.into_iter().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Process Wikidata entities into Parquet.
+pub struct EntityProcessor {
+    human_writer: TableWriter<HumanRec>,
+    name_writer: TableWriter<NameRec>,
+    gender_writer: TableWriter<GenderRec>,
+    ext_writer: TableWriter<ExternalIdRec>,
+}
+
+impl EntityProcessor {
+    pub fn new() -> Result<EntityProcessor> {
+        Ok(EntityProcessor {
+            human_writer: TableWriter::open(HUMAN_FILE)?,
+            name_writer: TableWriter::open(NAME_FILE)?,
+            gender_writer: TableWriter::open(GENDER_FILE)?,
+            ext_writer: TableWriter::open(EXT_ID_FILE)?,
+        })
+    }
+}
+
+impl DataSink for EntityProcessor {
+    fn output_files(&self) -> Vec<PathBuf> {
+        path_list(&[HUMAN_FILE, NAME_FILE, GENDER_FILE, EXT_ID_FILE])
+    }
+}
+
+impl ObjectWriter<RawEntity> for EntityProcessor {
+    fn write_object(&mut self, ent: RawEntity) -> Result<()> {
+        if !ent.is_human() {
+            return Ok(());
+        }
+        let id = parse_qid(&ent.id)?;
+
+        self.human_writer.write_object(HumanRec {
+            id,
+            label: ent.label().map(clean_name),
+            birth_year: ent.year_value(P_BIRTH),
+            death_year: ent.year_value(P_DEATH),
+            birth_date: ent.date_value(P_BIRTH),
+            death_date: ent.date_value(P_DEATH),
+        })?;
+
+        for name in ent.names() {
+            self.name_writer.write_object(NameRec { id, name })?;
+        }
+
+        for g in ent.item_values(P_GENDER) {
+            self.gender_writer.write_object(GenderRec {
+                id,
+                gender: gender_label(g),
+            })?;
+        }
+
+        for (prop, source) in EXTERNAL_IDS {
+            for ext_id in ent.string_values(prop) {
+                self.ext_writer.write_object(ExternalIdRec {
+                    id,
+                    source: source.to_string(),
+                    ext_id: ext_id.to_string(),
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<usize> {
+        let n = self.human_writer.finish()?;
+        self.name_writer.finish()?;
+        self.gender_writer.finish()?;
+        self.ext_writer.finish()?;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+const TEST_ENTITY: &str = r#"{"type":"item","id":"Q36322",
+"labels":{"en":{"language":"en","value":"Jane Austen"},"fr":{"language":"fr","value":"Jane Austen"}},
+"aliases":{"en":[{"language":"en","value":"Austen, Jane"}]},
+"claims":{
+"P31":[{"mainsnak":{"snaktype":"value","property":"P31","datavalue":{"value":{"entity-type":"item","numeric-id":5,"id":"Q5"},"type":"wikibase-entityid"}},"rank":"normal"}],
+"P21":[{"mainsnak":{"snaktype":"value","property":"P21","datavalue":{"value":{"entity-type":"item","numeric-id":6581072,"id":"Q6581072"},"type":"wikibase-entityid"}},"rank":"normal"}],
+"P569":[{"mainsnak":{"snaktype":"value","property":"P569","datavalue":{"value":{"time":"+1775-12-16T00:00:00Z","precision":11},"type":"time"}},"rank":"normal"}],
+"P570":[{"mainsnak":{"snaktype":"somevalue","property":"P570"},"rank":"normal"}],
+"P214":[{"mainsnak":{"snaktype":"value","property":"P214","datavalue":{"value":"102333412","type":"string"}},"rank":"normal"}]
+}}"#;
+
+#[test]
+fn test_parse_entity() {
+    let ent: RawEntity = serde_json::from_str(TEST_ENTITY).unwrap();
+    assert!(ent.is_human());
+    assert_eq!(parse_qid(&ent.id).unwrap(), 36322);
+    assert_eq!(ent.label(), Some("Jane Austen"));
+    assert_eq!(ent.names(), vec!["Austen, Jane", "Jane Austen"]);
+    let genders: Vec<_> = ent.item_values(P_GENDER).map(gender_label).collect();
+    assert_eq!(genders, vec!["female"]);
+    assert_eq!(ent.year_value("P569"), Some(1775));
+    assert_eq!(ent.year_value("P570"), None);
+    assert_eq!(ent.date_value(P_BIRTH).as_deref(), Some("1775-12-16"));
+    assert_eq!(ent.date_value(P_DEATH), None);
+    let viaf: Vec<_> = ent.string_values("P214").collect();
+    assert_eq!(viaf, vec!["102333412"]);
+}
+
+#[test]
+fn test_parse_time_year() {
+    assert_eq!(parse_time_year("+1775-12-16T00:00:00Z"), Some(1775));
+    assert_eq!(parse_time_year("-0043-03-15T00:00:00Z"), Some(-43));
+    assert_eq!(parse_time_year("garbage"), None);
+}
+
+#[test]
+fn test_parse_time_date() {
+    assert_eq!(
+        parse_time_date("+1775-12-16T00:00:00Z", 11).as_deref(),
+        Some("1775-12-16")
+    );
+    assert_eq!(
+        parse_time_date("+1775-12-16T00:00:00Z", 10).as_deref(),
+        Some("1775-12")
+    );
+    assert_eq!(
+        parse_time_date("+1775-00-00T00:00:00Z", 9).as_deref(),
+        Some("1775")
+    );
+    assert_eq!(
+        parse_time_date("-0043-03-15T00:00:00Z", 11).as_deref(),
+        Some("-0043-03-15")
+    );
+    assert_eq!(parse_time_date("garbage", 11), None);
+}
+
+#[test]
+fn test_gender_label() {
+    assert_eq!(gender_label("Q6581097"), "male");
+    assert_eq!(gender_label("Q123"), "Q123");
+}